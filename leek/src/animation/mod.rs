@@ -0,0 +1,241 @@
+use atlas::{AtlasRegion, FrameTag, TextureAtlas};
+
+use std::sync::Arc;
+
+mod player;
+
+pub use self::player::AnimationPlayer;
+
+/// Used for the frames without a duration.
+pub const DEFAULT_FRAME_DURATION: u32 = 100;
+
+/// Name of the clip containing every frame, when the file has no tags.
+pub const DEFAULT_CLIP: &str = "default";
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PlayMode {
+    /// Play the frames once and stop on the last one.
+    Once,
+    Loop,
+    /// Go back and forth between the first and the last frame.
+    PingPong,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnimationFrame {
+    /// Name of the atlas region to draw.
+    pub region: String,
+    /// Duration in milliseconds.
+    pub duration: u32,
+    /// Events fired when the frame is reached.
+    pub events: Vec<String>,
+}
+
+/// A sequence of atlas frames.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnimationClip {
+    pub name: String,
+    pub frames: Vec<AnimationFrame>,
+    pub mode: PlayMode,
+}
+
+impl AnimationClip {
+    pub fn new(name: &str, mode: PlayMode) -> AnimationClip {
+        AnimationClip {
+            name: name.to_string(),
+            frames: Vec::new(),
+            mode,
+        }
+    }
+
+    pub fn with_frame(mut self, region: &str, duration: u32) -> AnimationClip {
+        self.frames.push(AnimationFrame {
+            region: region.to_string(),
+            duration,
+            events: Vec::new(),
+        });
+        self
+    }
+
+    /// Fire `event` every time the frame at `index` is reached.
+    pub fn with_event(mut self, index: usize, event: &str) -> AnimationClip {
+        if let Some(frame) = self.frames.get_mut(index) {
+            frame.events.push(event.to_string());
+        }
+        self
+    }
+
+    pub fn with_mode(mut self, mode: PlayMode) -> AnimationClip {
+        self.mode = mode;
+        self
+    }
+
+    /// Total duration of a single play of the frames.
+    pub fn duration(&self) -> u32 {
+        self.frames.iter().map(|frame| frame.duration).sum()
+    }
+
+    /// Build a clip from an Aseprite tag and the regions of the same file.
+    pub fn from_tag(tag: &FrameTag, regions: &[AtlasRegion]) -> Result<AnimationClip, String> {
+        if tag.from > tag.to || tag.to >= regions.len() {
+            return Err(format!(
+                "Tag {} uses the frames {} to {}, there are {} frames",
+                tag.name,
+                tag.from,
+                tag.to,
+                regions.len()
+            ));
+        }
+        let mut frames: Vec<AnimationFrame> =
+            regions[tag.from..=tag.to].iter().map(frame_of).collect();
+
+        let mode = match tag.direction.as_str() {
+            "pingpong" | "pingpong_reverse" => PlayMode::PingPong,
+            _ => PlayMode::Loop,
+        };
+        if tag.direction == "reverse" || tag.direction == "pingpong_reverse" {
+            frames.reverse();
+        }
+        let mode = match tag.repeat.as_ref().map(String::as_str) {
+            Some("1") => PlayMode::Once,
+            _ => mode,
+        };
+
+        Ok(AnimationClip {
+            name: tag.name.clone(),
+            frames,
+            mode,
+        })
+    }
+}
+
+fn frame_of(region: &AtlasRegion) -> AnimationFrame {
+    AnimationFrame {
+        region: region.name.clone(),
+        duration: region.duration.unwrap_or(DEFAULT_FRAME_DURATION),
+        events: Vec::new(),
+    }
+}
+
+/// Clips imported from an Aseprite JSON export, with the atlas of their frames.
+///
+/// The atlas can be shared with the atlas cache of `Resources`.
+pub struct AnimationSet {
+    atlas: Arc<TextureAtlas>,
+    clips: Vec<Arc<AnimationClip>>,
+}
+
+impl AnimationSet {
+    /// One clip per tag, or a single `DEFAULT_CLIP` when there are none.
+    pub fn new(atlas: Arc<TextureAtlas>, tags: &[FrameTag]) -> Result<AnimationSet, String> {
+        let clips = if tags.is_empty() {
+            vec![AnimationClip {
+                name: DEFAULT_CLIP.to_string(),
+                frames: atlas.regions().iter().map(frame_of).collect(),
+                mode: PlayMode::Loop,
+            }]
+        } else {
+            tags.iter()
+                .map(|tag| AnimationClip::from_tag(tag, atlas.regions()))
+                .collect::<Result<_, _>>()?
+        };
+
+        Ok(AnimationSet {
+            atlas,
+            clips: clips.into_iter().map(Arc::new).collect(),
+        })
+    }
+
+    pub fn atlas(&self) -> &TextureAtlas {
+        &self.atlas
+    }
+
+    pub fn clip(&self, name: &str) -> Option<Arc<AnimationClip>> {
+        self.clips
+            .iter()
+            .find(|clip| clip.name == name)
+            .map(Arc::clone)
+    }
+
+    pub fn clips(&self) -> &[Arc<AnimationClip>] {
+        &self.clips
+    }
+
+    pub fn vram_size(&self) -> usize {
+        self.atlas.vram_size()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use sdl2::rect::Rect;
+
+    fn regions(count: usize) -> Vec<AtlasRegion> {
+        (0..count)
+            .map(|index| AtlasRegion {
+                name: format!("hero {}.aseprite", index),
+                rect: Rect::new(index as i32 * 16, 0, 16, 16),
+                rotated: false,
+                trimmed: false,
+                offset: (0, 0),
+                source_size: (16, 16),
+                pivot: (0.0, 0.0),
+                duration: Some(50 * (index as u32 + 1)),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_from_tag() {
+        let tag = FrameTag {
+            name: "run".to_string(),
+            from: 1,
+            to: 3,
+            direction: "reverse".to_string(),
+            repeat: None,
+        };
+        let clip = AnimationClip::from_tag(&tag, &regions(5)).unwrap();
+        assert_eq!(clip.mode, PlayMode::Loop);
+        assert_eq!(clip.frames.len(), 3);
+        assert_eq!(clip.frames[0].region, "hero 3.aseprite");
+        assert_eq!(clip.duration(), 200 + 150 + 100);
+
+        let tag = FrameTag {
+            repeat: Some("1".to_string()),
+            direction: "pingpong".to_string(),
+            ..tag
+        };
+        assert_eq!(
+            AnimationClip::from_tag(&tag, &regions(5)).unwrap().mode,
+            PlayMode::Once
+        );
+    }
+
+    #[test]
+    fn test_from_tag_errors() {
+        let tag = FrameTag {
+            name: "run".to_string(),
+            from: 3,
+            to: 1,
+            direction: "forward".to_string(),
+            repeat: None,
+        };
+        assert!(AnimationClip::from_tag(&tag, &regions(5)).is_err());
+        let tag = FrameTag {
+            from: 1,
+            to: 5,
+            ..tag
+        };
+        assert_eq!(
+            AnimationClip::from_tag(&tag, &regions(5)).unwrap_err(),
+            "Tag run uses the frames 1 to 5, there are 5 frames"
+        );
+        let tag = FrameTag {
+            from: 0,
+            to: 0,
+            ..tag
+        };
+        assert!(AnimationClip::from_tag(&tag, &[]).is_err());
+    }
+}
@@ -0,0 +1,235 @@
+use sdl2::render::WindowCanvas;
+
+use std::sync::Arc;
+
+use super::{AnimationClip, AnimationFrame, PlayMode};
+use atlas::{DrawParams, DrawRegion, TextureAtlas};
+use EngineContext;
+
+/// Plays an animation clip, to be updated once per frame.
+#[derive(Clone, Debug)]
+pub struct AnimationPlayer {
+    clip: Option<Arc<AnimationClip>>,
+    frame: usize,
+    // Time spent on the current frame, in milliseconds
+    elapsed: u32,
+    backward: bool,
+    started: bool,
+    finished: bool,
+    /// Multiplier applied to the delta time.
+    pub speed: f32,
+    pub paused: bool,
+}
+
+impl Default for AnimationPlayer {
+    fn default() -> Self {
+        AnimationPlayer {
+            clip: None,
+            frame: 0,
+            elapsed: 0,
+            backward: false,
+            started: false,
+            finished: false,
+            speed: 1.0,
+            paused: false,
+        }
+    }
+}
+
+impl AnimationPlayer {
+    pub fn new() -> Self {
+        AnimationPlayer::default()
+    }
+
+    /// Play `clip` from its first frame, unless it is already playing.
+    pub fn play(&mut self, clip: &Arc<AnimationClip>) {
+        let playing = self
+            .clip
+            .as_ref()
+            .map_or(false, |current| Arc::ptr_eq(current, clip));
+        if !playing {
+            self.clip = Some(Arc::clone(clip));
+            self.restart();
+        }
+    }
+
+    /// Go back to the first frame of the clip.
+    pub fn restart(&mut self) {
+        self.frame = 0;
+        self.elapsed = 0;
+        self.backward = false;
+        self.started = false;
+        self.finished = false;
+    }
+
+    pub fn stop(&mut self) {
+        self.clip = None;
+        self.restart();
+    }
+
+    pub fn clip(&self) -> Option<&Arc<AnimationClip>> {
+        self.clip.as_ref()
+    }
+
+    pub fn frame_index(&self) -> usize {
+        self.frame
+    }
+
+    pub fn current_frame(&self) -> Option<&AnimationFrame> {
+        self.clip
+            .as_ref()
+            .and_then(|clip| clip.frames.get(self.frame))
+    }
+
+    /// `true` when a `PlayMode::Once` clip reached its last frame.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Advance with the delta time of the context, returns the events of the reached frames.
+    pub fn update(&mut self, context: &EngineContext) -> Vec<String> {
+        let delta_time = (context.delta_time as f32 * self.speed).round() as u32;
+        self.advance(delta_time)
+    }
+
+    /// Advance by `delta_time` milliseconds, returns the events of the reached frames.
+    pub fn advance(&mut self, delta_time: u32) -> Vec<String> {
+        let mut events = Vec::new();
+        let clip = match self.clip {
+            Some(ref clip) if !self.paused && !self.finished && !clip.frames.is_empty() => {
+                Arc::clone(clip)
+            }
+            _ => return events,
+        };
+
+        // The events of the first frame are fired when the clip starts
+        if !self.started {
+            self.started = true;
+            events.extend(clip.frames[0].events.iter().cloned());
+        }
+
+        self.elapsed += delta_time;
+        while self.elapsed >= clip.frames[self.frame].duration.max(1) {
+            self.elapsed -= clip.frames[self.frame].duration.max(1);
+            if !self.next_frame(&clip) {
+                self.elapsed = 0;
+                break;
+            }
+            events.extend(clip.frames[self.frame].events.iter().cloned());
+        }
+        events
+    }
+
+    // Returns `false` when the clip is finished
+    fn next_frame(&mut self, clip: &AnimationClip) -> bool {
+        let last = clip.frames.len() - 1;
+        match clip.mode {
+            PlayMode::Once => {
+                if self.frame == last {
+                    self.finished = true;
+                    return false;
+                }
+                self.frame += 1;
+            }
+            PlayMode::Loop => self.frame = (self.frame + 1) % clip.frames.len(),
+            PlayMode::PingPong => {
+                if last == 0 {
+                    return true;
+                }
+                if self.backward && self.frame == 0 {
+                    self.backward = false;
+                } else if !self.backward && self.frame == last {
+                    self.backward = true;
+                }
+                if self.backward {
+                    self.frame -= 1;
+                } else {
+                    self.frame += 1;
+                }
+            }
+        }
+        true
+    }
+
+    /// Draw the current frame with its pivot at `position`.
+    pub fn draw(
+        &self,
+        renderer: &mut WindowCanvas,
+        atlas: &TextureAtlas,
+        position: (i32, i32),
+        params: &DrawParams,
+    ) -> Result<(), String> {
+        match self.current_frame() {
+            Some(frame) => renderer.draw_region_ex(atlas, &frame.region, position, params),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn clip(mode: PlayMode) -> Arc<AnimationClip> {
+        Arc::new(
+            AnimationClip::new("test", mode)
+                .with_frame("a", 100)
+                .with_frame("b", 50)
+                .with_frame("c", 100)
+                .with_event(0, "start")
+                .with_event(2, "hit"),
+        )
+    }
+
+    #[test]
+    fn test_loop_and_events() {
+        let mut player = AnimationPlayer::new();
+        player.play(&clip(PlayMode::Loop));
+        assert_eq!(player.advance(99), vec!["start".to_string()]);
+        assert_eq!(player.frame_index(), 0);
+        assert!(player.advance(1).is_empty());
+        assert_eq!(player.frame_index(), 1);
+        assert_eq!(
+            player.advance(200),
+            vec!["hit".to_string(), "start".to_string()]
+        );
+        assert_eq!(player.frame_index(), 0);
+        assert!(!player.is_finished());
+    }
+
+    #[test]
+    fn test_once() {
+        let mut player = AnimationPlayer::new();
+        player.play(&clip(PlayMode::Once));
+        player.advance(1000);
+        assert!(player.is_finished());
+        assert_eq!(player.current_frame().unwrap().region, "c");
+        assert!(player.advance(1000).is_empty());
+    }
+
+    #[test]
+    fn test_ping_pong() {
+        let mut player = AnimationPlayer::new();
+        player.play(&clip(PlayMode::PingPong));
+        let frames: Vec<usize> = (0..6)
+            .map(|_| {
+                let duration = player.current_frame().unwrap().duration;
+                player.advance(duration);
+                player.frame_index()
+            })
+            .collect();
+        assert_eq!(frames, vec![1, 2, 1, 0, 1, 2]);
+    }
+
+    #[test]
+    fn test_play_same_clip() {
+        let clip = clip(PlayMode::Loop);
+        let mut player = AnimationPlayer::new();
+        player.play(&clip);
+        player.advance(120);
+        player.play(&clip);
+        assert_eq!(player.frame_index(), 1);
+        player.restart();
+        assert_eq!(player.frame_index(), 0);
+    }
+}
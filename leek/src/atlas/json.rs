@@ -0,0 +1,181 @@
+use sdl2::rect::Rect;
+use serde::de::{Deserialize, Deserializer, MapAccess, Visitor};
+use serde_json;
+
+use std::fmt;
+
+use super::AtlasRegion;
+
+#[derive(Deserialize)]
+struct JsonRect {
+    x: i32,
+    y: i32,
+    w: u32,
+    h: u32,
+}
+
+#[derive(Deserialize)]
+struct JsonSize {
+    w: u32,
+    h: u32,
+}
+
+#[derive(Deserialize)]
+struct JsonPoint {
+    x: f32,
+    y: f32,
+}
+
+#[derive(Deserialize)]
+struct JsonFrame {
+    #[serde(default)]
+    filename: Option<String>,
+    frame: JsonRect,
+    #[serde(default)]
+    rotated: bool,
+    #[serde(default)]
+    trimmed: bool,
+    #[serde(rename = "spriteSourceSize")]
+    sprite_source_size: Option<JsonRect>,
+    #[serde(rename = "sourceSize")]
+    source_size: Option<JsonSize>,
+    pivot: Option<JsonPoint>,
+    duration: Option<u32>,
+}
+
+/// Frames exported as a map (keeping the file order) or as an array.
+enum JsonFrames {
+    Hash(Vec<(String, JsonFrame)>),
+    Array(Vec<JsonFrame>),
+}
+
+impl<'de> Deserialize<'de> for JsonFrames {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct FramesVisitor;
+
+        impl<'de> Visitor<'de> for FramesVisitor {
+            type Value = JsonFrames;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a map or an array of frames")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<JsonFrames, A::Error> {
+                let mut frames = Vec::new();
+                while let Some(entry) = map.next_entry()? {
+                    frames.push(entry);
+                }
+                Ok(JsonFrames::Hash(frames))
+            }
+
+            fn visit_seq<A: ::serde::de::SeqAccess<'de>>(
+                self,
+                mut seq: A,
+            ) -> Result<JsonFrames, A::Error> {
+                let mut frames = Vec::new();
+                while let Some(frame) = seq.next_element()? {
+                    frames.push(frame);
+                }
+                Ok(JsonFrames::Array(frames))
+            }
+        }
+
+        deserializer.deserialize_any(FramesVisitor)
+    }
+}
+
+#[derive(Deserialize)]
+struct JsonMeta {
+    image: String,
+}
+
+#[derive(Deserialize)]
+struct JsonAtlas {
+    frames: JsonFrames,
+    meta: JsonMeta,
+}
+
+/// A range of frames tagged in Aseprite, `from` and `to` are inclusive frame indices.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct FrameTag {
+    pub name: String,
+    pub from: usize,
+    pub to: usize,
+    /// "forward", "reverse", "pingpong" or "pingpong_reverse".
+    #[serde(default = "default_direction")]
+    pub direction: String,
+    /// Number of times the tag is played, infinite when missing or "0".
+    #[serde(default)]
+    pub repeat: Option<String>,
+}
+
+fn default_direction() -> String {
+    "forward".to_string()
+}
+
+#[derive(Deserialize)]
+struct JsonTagsMeta {
+    #[serde(default, rename = "frameTags")]
+    frame_tags: Vec<FrameTag>,
+}
+
+#[derive(Deserialize)]
+struct JsonTags {
+    meta: JsonTagsMeta,
+}
+
+/// Parse the frame tags of an Aseprite JSON export.
+pub fn parse_frame_tags(json: &str) -> Result<Vec<FrameTag>, String> {
+    let tags: JsonTags = serde_json::from_str(json).map_err(|e| format!("{}", e))?;
+    Ok(tags.meta.frame_tags)
+}
+
+/// Parse a TexturePacker / Aseprite JSON export (hash or array),
+/// returns the image path (relative to the JSON file) and the regions.
+pub fn parse_atlas(json: &str) -> Result<(String, Vec<AtlasRegion>), String> {
+    let atlas: JsonAtlas = serde_json::from_str(json).map_err(|e| format!("{}", e))?;
+
+    let frames = match atlas.frames {
+        JsonFrames::Hash(frames) => frames,
+        JsonFrames::Array(frames) => frames
+            .into_iter()
+            .enumerate()
+            .map(|(index, frame)| {
+                let name = frame.filename.clone().unwrap_or_else(|| index.to_string());
+                (name, frame)
+            })
+            .collect(),
+    };
+
+    let regions = frames
+        .into_iter()
+        .map(|(name, frame)| {
+            let (width, height) = (frame.frame.w, frame.frame.h);
+            // Rotated frames are stored 90° clockwise in the texture
+            let rect = if frame.rotated {
+                Rect::new(frame.frame.x, frame.frame.y, height, width)
+            } else {
+                Rect::new(frame.frame.x, frame.frame.y, width, height)
+            };
+            let offset = frame
+                .sprite_source_size
+                .map_or((0, 0), |source| (source.x, source.y));
+            let source_size = frame
+                .source_size
+                .map_or((width, height), |size| (size.w, size.h));
+
+            AtlasRegion {
+                name,
+                rect,
+                rotated: frame.rotated,
+                trimmed: frame.trimmed,
+                offset,
+                source_size,
+                pivot: frame.pivot.map_or((0.0, 0.0), |pivot| (pivot.x, pivot.y)),
+                duration: frame.duration,
+            }
+        })
+        .collect();
+
+    Ok((atlas.meta.image, regions))
+}
@@ -0,0 +1,329 @@
+use sdl2::rect::{Point, Rect};
+use sdl2::render::{Texture, WindowCanvas};
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+mod json;
+
+pub use self::json::{parse_atlas, parse_frame_tags, FrameTag};
+
+/// A named sub-image of an atlas.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AtlasRegion {
+    pub name: String,
+    /// Area of the texture, width and height are swapped when `rotated`.
+    pub rect: Rect,
+    /// The region is stored rotated 90° clockwise in the texture.
+    pub rotated: bool,
+    /// Transparent borders have been removed by the packer.
+    pub trimmed: bool,
+    /// Position of the trimmed image inside the original one.
+    pub offset: (i32, i32),
+    /// Size of the original image, before trimming.
+    pub source_size: (u32, u32),
+    /// Normalized position of the pivot inside the original image, (0, 0) is the top left corner.
+    pub pivot: (f32, f32),
+    /// Frame duration in milliseconds (Aseprite exports).
+    pub duration: Option<u32>,
+}
+
+impl AtlasRegion {
+    /// Size of the image drawn by this region, once un-rotated.
+    pub fn size(&self) -> (u32, u32) {
+        if self.rotated {
+            (self.rect.height(), self.rect.width())
+        } else {
+            (self.rect.width(), self.rect.height())
+        }
+    }
+}
+
+/// A texture split in named regions, loaded from a TexturePacker / Aseprite JSON or a grid.
+///
+/// The texture can be shared with the texture cache of `Resources`.
+pub struct TextureAtlas {
+    texture: Arc<Texture>,
+    image_path: String,
+    regions: Vec<AtlasRegion>,
+    names: HashMap<String, usize>,
+}
+
+impl TextureAtlas {
+    pub fn new(texture: Arc<Texture>, image_path: &str, regions: Vec<AtlasRegion>) -> Self {
+        let names = regions
+            .iter()
+            .enumerate()
+            .map(|(index, region)| (region.name.clone(), index))
+            .collect();
+        TextureAtlas {
+            texture,
+            image_path: image_path.to_string(),
+            regions,
+            names,
+        }
+    }
+
+    /// Split the texture in cells of `cell_size`, named "0", "1", ... row by row.
+    pub fn from_grid(texture: Arc<Texture>, image_path: &str, cell_size: (u32, u32)) -> Self {
+        let query = texture.query();
+        let regions = grid_regions((query.width, query.height), cell_size);
+        TextureAtlas::new(texture, image_path, regions)
+    }
+
+    pub fn texture(&self) -> &Texture {
+        &self.texture
+    }
+
+    /// Path of the image used by the atlas.
+    pub fn image_path(&self) -> &str {
+        &self.image_path
+    }
+
+    pub fn region(&self, name: &str) -> Option<&AtlasRegion> {
+        self.names.get(name).map(|index| &self.regions[*index])
+    }
+
+    /// Regions in the order of the file.
+    pub fn regions(&self) -> &[AtlasRegion] {
+        &self.regions
+    }
+
+    pub fn vram_size(&self) -> usize {
+        let tex_query = self.texture.query();
+
+        let pixels = tex_query.width * tex_query.height;
+        tex_query.format.byte_size_of_pixels(pixels as usize)
+    }
+}
+
+pub fn grid_regions(texture_size: (u32, u32), cell_size: (u32, u32)) -> Vec<AtlasRegion> {
+    let (cell_width, cell_height) = (cell_size.0.max(1), cell_size.1.max(1));
+    let columns = texture_size.0 / cell_width;
+    let rows = texture_size.1 / cell_height;
+
+    (0..rows * columns)
+        .map(|index| AtlasRegion {
+            name: index.to_string(),
+            rect: Rect::new(
+                ((index % columns) * cell_width) as i32,
+                ((index / columns) * cell_height) as i32,
+                cell_width,
+                cell_height,
+            ),
+            rotated: false,
+            trimmed: false,
+            offset: (0, 0),
+            source_size: (cell_width, cell_height),
+            pivot: (0.0, 0.0),
+            duration: None,
+        })
+        .collect()
+}
+
+/// How a region is drawn, the scale and rotation are applied around the pivot.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DrawParams {
+    pub scale: (f32, f32),
+    /// Clockwise rotation in degrees.
+    pub angle: f64,
+    pub flip_horizontal: bool,
+    pub flip_vertical: bool,
+}
+
+impl Default for DrawParams {
+    fn default() -> Self {
+        DrawParams {
+            scale: (1.0, 1.0),
+            angle: 0.0,
+            flip_horizontal: false,
+            flip_vertical: false,
+        }
+    }
+}
+
+/// Returns the destination rect and the angle to use with `copy_ex` to draw
+/// `region` with its pivot at `position`.
+pub fn region_destination(
+    region: &AtlasRegion,
+    position: (i32, i32),
+    params: &DrawParams,
+) -> (Rect, f64) {
+    let (width, height) = region.size();
+    let (source_width, source_height) = (region.source_size.0 as f32, region.source_size.1 as f32);
+    let (scale_x, scale_y) = params.scale;
+
+    // Flipping mirrors the image around the pivot
+    let (mut offset_x, mut offset_y) = (region.offset.0 as f32, region.offset.1 as f32);
+    let (mut pivot_x, mut pivot_y) = (
+        region.pivot.0 * source_width,
+        region.pivot.1 * source_height,
+    );
+    if params.flip_horizontal {
+        offset_x = source_width - offset_x - width as f32;
+        pivot_x = source_width - pivot_x;
+    }
+    if params.flip_vertical {
+        offset_y = source_height - offset_y - height as f32;
+        pivot_y = source_height - pivot_y;
+    }
+
+    // Center of the image before the rotation, relative to the pivot
+    let center_x = (offset_x + width as f32 / 2.0 - pivot_x) * scale_x;
+    let center_y = (offset_y + height as f32 / 2.0 - pivot_y) * scale_y;
+
+    let (sin, cos) = params.angle.to_radians().sin_cos();
+    let center = Point::new(
+        position.0 + (f64::from(center_x) * cos - f64::from(center_y) * sin).round() as i32,
+        position.1 + (f64::from(center_x) * sin + f64::from(center_y) * cos).round() as i32,
+    );
+
+    let (dest_width, dest_height) = (
+        (width as f32 * scale_x).abs().round() as u32,
+        (height as f32 * scale_y).abs().round() as u32,
+    );
+    if region.rotated {
+        (
+            Rect::from_center(center, dest_height, dest_width),
+            params.angle - 90.0,
+        )
+    } else {
+        (
+            Rect::from_center(center, dest_width, dest_height),
+            params.angle,
+        )
+    }
+}
+
+/// Draw atlas regions on a canvas.
+pub trait DrawRegion {
+    /// Draw the region `name` with its pivot at `position`.
+    fn draw_region(
+        &mut self,
+        atlas: &TextureAtlas,
+        name: &str,
+        position: (i32, i32),
+    ) -> Result<(), String> {
+        self.draw_region_ex(atlas, name, position, &DrawParams::default())
+    }
+
+    fn draw_region_ex(
+        &mut self,
+        atlas: &TextureAtlas,
+        name: &str,
+        position: (i32, i32),
+        params: &DrawParams,
+    ) -> Result<(), String>;
+}
+
+impl DrawRegion for WindowCanvas {
+    fn draw_region_ex(
+        &mut self,
+        atlas: &TextureAtlas,
+        name: &str,
+        position: (i32, i32),
+        params: &DrawParams,
+    ) -> Result<(), String> {
+        let region = atlas
+            .region(name)
+            .ok_or_else(|| format!("No region named {}", name))?;
+        let (dest, angle) = region_destination(region, position, params);
+
+        // The texture holds the rotated image, so the flips are swapped too
+        let (flip_horizontal, flip_vertical) = if region.rotated {
+            (params.flip_vertical, params.flip_horizontal)
+        } else {
+            (params.flip_horizontal, params.flip_vertical)
+        };
+
+        self.copy_ex(
+            atlas.texture(),
+            region.rect,
+            dest,
+            angle,
+            None,
+            flip_horizontal,
+            flip_vertical,
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const ATLAS: &str = r#"{
+        "frames": {
+            "hero_idle.png": {
+                "frame": {"x": 2, "y": 2, "w": 20, "h": 30},
+                "rotated": false,
+                "trimmed": true,
+                "spriteSourceSize": {"x": 6, "y": 2, "w": 20, "h": 30},
+                "sourceSize": {"w": 32, "h": 32},
+                "pivot": {"x": 0.5, "y": 1.0}
+            },
+            "coin.png": {
+                "frame": {"x": 24, "y": 2, "w": 16, "h": 8},
+                "rotated": true,
+                "trimmed": false,
+                "spriteSourceSize": {"x": 0, "y": 0, "w": 16, "h": 8},
+                "sourceSize": {"w": 16, "h": 8}
+            }
+        },
+        "meta": {"image": "sprites.png"}
+    }"#;
+
+    #[test]
+    fn test_parse_hash() {
+        let (image, regions) = parse_atlas(ATLAS).unwrap();
+        assert_eq!(image, "sprites.png");
+        assert_eq!(regions[0].name, "hero_idle.png");
+        assert_eq!(regions[0].offset, (6, 2));
+        assert_eq!(regions[0].pivot, (0.5, 1.0));
+        assert_eq!(regions[1].rect, Rect::new(24, 2, 8, 16));
+        assert_eq!(regions[1].size(), (16, 8));
+    }
+
+    #[test]
+    fn test_parse_array() {
+        let json = r#"{
+            "frames": [
+                {"filename": "a", "frame": {"x": 0, "y": 0, "w": 8, "h": 8}, "duration": 100},
+                {"frame": {"x": 8, "y": 0, "w": 8, "h": 8}, "duration": 50}
+            ],
+            "meta": {"image": "anim.png"}
+        }"#;
+        let (_, regions) = parse_atlas(json).unwrap();
+        assert_eq!(regions[0].name, "a");
+        assert_eq!(regions[1].name, "1");
+        assert_eq!(regions[1].duration, Some(50));
+        assert_eq!(regions[1].source_size, (8, 8));
+    }
+
+    #[test]
+    fn test_grid() {
+        let regions = grid_regions((100, 40), (32, 16));
+        assert_eq!(regions.len(), 6);
+        assert_eq!(regions[4].rect, Rect::new(32, 16, 32, 16));
+    }
+
+    #[test]
+    fn test_destination() {
+        let (_, regions) = parse_atlas(ATLAS).unwrap();
+        let (dest, angle) = region_destination(&regions[0], (100, 100), &DrawParams::default());
+        // Bottom center pivot of a 32x32 image, trimmed to 20x30 at (6, 2)
+        assert_eq!(dest, Rect::new(90, 70, 20, 30));
+        assert_eq!(angle, 0.0);
+
+        let params = DrawParams {
+            flip_horizontal: true,
+            ..DrawParams::default()
+        };
+        let (dest, _) = region_destination(&regions[0], (100, 100), &params);
+        assert_eq!(dest, Rect::new(90, 70, 20, 30));
+
+        let (dest, angle) = region_destination(&regions[1], (0, 0), &DrawParams::default());
+        assert_eq!(dest, Rect::new(4, -4, 8, 16));
+        assert_eq!(angle, -90.0);
+    }
+}
@@ -0,0 +1,499 @@
+use sdl2::gfx::primitives::DrawRenderer;
+use sdl2::rect::{Point, Rect};
+use sdl2::render::{Texture, WindowCanvas};
+
+use atlas::{DrawParams, DrawRegion, TextureAtlas};
+use EngineContext;
+
+const SHAKE_FREQUENCY: f32 = 15.0;
+
+/// A 2D camera looking at `position`, converts between world and screen coordinates.
+///
+/// The screen coordinates are the logical coordinates of the renderer.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Camera2D {
+    /// World position shown at the center of the viewport.
+    pub position: (f32, f32),
+    pub zoom: f32,
+    /// Clockwise rotation of the camera in degrees, the world appears rotated the other way.
+    pub rotation: f32,
+    pub viewport_size: (u32, u32),
+    /// The target can move inside this area, centered on the camera, without moving it.
+    pub deadzone: (f32, f32),
+    /// How fast the camera catches up with its target, per second. 0 moves it instantly.
+    pub follow_speed: f32,
+    /// World area that the camera cannot show past.
+    pub bounds: Option<Rect>,
+    /// Maximum shake offset in pixels, reached with a trauma of 1.
+    pub max_shake_offset: f32,
+    /// Maximum shake rotation in degrees, reached with a trauma of 1.
+    pub max_shake_angle: f32,
+    /// Trauma removed every second.
+    pub trauma_decay: f32,
+    target: Option<(f32, f32)>,
+    trauma: f32,
+    time: f32,
+    shake_offset: (f32, f32),
+    shake_angle: f32,
+}
+
+impl Camera2D {
+    pub fn new(viewport_size: (u32, u32)) -> Self {
+        Camera2D {
+            position: (viewport_size.0 as f32 / 2.0, viewport_size.1 as f32 / 2.0),
+            zoom: 1.0,
+            rotation: 0.0,
+            viewport_size,
+            deadzone: (0.0, 0.0),
+            follow_speed: 0.0,
+            bounds: None,
+            max_shake_offset: 10.0,
+            max_shake_angle: 5.0,
+            trauma_decay: 1.0,
+            target: None,
+            trauma: 0.0,
+            time: 0.0,
+            shake_offset: (0.0, 0.0),
+            shake_angle: 0.0,
+        }
+    }
+
+    /// A camera covering the logical size of the renderer (or its output size when there is none).
+    pub fn from_renderer(renderer: &WindowCanvas) -> Self {
+        let size = match renderer.logical_size() {
+            (0, 0) => renderer.output_size().unwrap_or((0, 0)),
+            size => size,
+        };
+        Camera2D::new(size)
+    }
+
+    /// Move towards `target` on the next updates.
+    pub fn follow(&mut self, target: (f32, f32)) {
+        self.target = Some(target);
+    }
+
+    pub fn stop_following(&mut self) {
+        self.target = None;
+    }
+
+    /// Add trauma (clamped to 0..1), the shake grows with the square of the trauma.
+    pub fn add_trauma(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount).max(0.0).min(1.0);
+    }
+
+    pub fn trauma(&self) -> f32 {
+        self.trauma
+    }
+
+    /// Follow the target, then clamp to the bounds and shake, using the delta time of the context.
+    pub fn update(&mut self, context: &EngineContext) {
+        self.advance(context.delta_time);
+    }
+
+    /// Same as `update`, `delta_time` is in milliseconds.
+    pub fn advance(&mut self, delta_time: u32) {
+        let seconds = delta_time as f32 / 1000.0;
+        self.time += seconds;
+
+        if let Some(target) = self.target {
+            let desired = (
+                deadzone_follow(self.position.0, target.0, self.deadzone.0),
+                deadzone_follow(self.position.1, target.1, self.deadzone.1),
+            );
+            let t = if self.follow_speed <= 0.0 {
+                1.0
+            } else {
+                1.0 - (-self.follow_speed * seconds).exp()
+            };
+            self.position.0 += (desired.0 - self.position.0) * t;
+            self.position.1 += (desired.1 - self.position.1) * t;
+        }
+        self.clamp_to_bounds();
+
+        self.trauma = (self.trauma - self.trauma_decay * seconds).max(0.0);
+        let shake = self.trauma * self.trauma;
+        let time = self.time * SHAKE_FREQUENCY;
+        self.shake_offset = (
+            self.max_shake_offset * shake * noise(time, 0),
+            self.max_shake_offset * shake * noise(time, 1),
+        );
+        self.shake_angle = self.max_shake_angle * shake * noise(time, 2);
+    }
+
+    /// Keep the visible area inside the bounds, centering it when the bounds are smaller.
+    pub fn clamp_to_bounds(&mut self) {
+        let bounds = match self.bounds {
+            Some(bounds) => bounds,
+            None => return,
+        };
+        let zoom = self.zoom.max(0.001);
+        let half_width = self.viewport_size.0 as f32 / zoom / 2.0;
+        let half_height = self.viewport_size.1 as f32 / zoom / 2.0;
+
+        self.position.0 = clamp_axis(
+            self.position.0,
+            bounds.left() as f32 + half_width,
+            bounds.right() as f32 - half_width,
+        );
+        self.position.1 = clamp_axis(
+            self.position.1,
+            bounds.top() as f32 + half_height,
+            bounds.bottom() as f32 - half_height,
+        );
+    }
+
+    /// Position and rotation actually used for drawing, shake included.
+    fn transform(&self) -> ((f32, f32), f32) {
+        (
+            (
+                self.position.0 + self.shake_offset.0 / self.zoom.max(0.001),
+                self.position.1 + self.shake_offset.1 / self.zoom.max(0.001),
+            ),
+            self.rotation + self.shake_angle,
+        )
+    }
+
+    pub fn world_to_screen(&self, point: (f32, f32)) -> (f32, f32) {
+        let ((x, y), rotation) = self.transform();
+        let (sin, cos) = (-rotation).to_radians().sin_cos();
+        let (dx, dy) = (point.0 - x, point.1 - y);
+        (
+            (dx * cos - dy * sin) * self.zoom + self.viewport_size.0 as f32 / 2.0,
+            (dx * sin + dy * cos) * self.zoom + self.viewport_size.1 as f32 / 2.0,
+        )
+    }
+
+    pub fn screen_to_world(&self, point: (f32, f32)) -> (f32, f32) {
+        let ((x, y), rotation) = self.transform();
+        let (sin, cos) = rotation.to_radians().sin_cos();
+        let zoom = self.zoom.max(0.001);
+        let dx = (point.0 - self.viewport_size.0 as f32 / 2.0) / zoom;
+        let dy = (point.1 - self.viewport_size.1 as f32 / 2.0) / zoom;
+        (x + dx * cos - dy * sin, y + dx * sin + dy * cos)
+    }
+
+    fn world_to_screen_point(&self, point: (f32, f32)) -> Point {
+        let (x, y) = self.world_to_screen(point);
+        Point::new(x.round() as i32, y.round() as i32)
+    }
+
+    /// Smallest world rect containing everything visible.
+    pub fn visible_bounds(&self) -> Rect {
+        let (width, height) = (self.viewport_size.0 as f32, self.viewport_size.1 as f32);
+        let corners = [
+            self.screen_to_world((0.0, 0.0)),
+            self.screen_to_world((width, 0.0)),
+            self.screen_to_world((width, height)),
+            self.screen_to_world((0.0, height)),
+        ];
+        bounding_rect(&corners)
+    }
+
+    /// `false` when nothing of the world rect `rect` can be seen, to skip drawing it.
+    pub fn is_visible(&self, rect: Rect) -> bool {
+        self.visible_bounds().has_intersection(rect)
+    }
+
+    /// Draw through this camera, the coordinates passed to the view are in world space.
+    pub fn view<'a>(&'a self, renderer: &'a mut WindowCanvas) -> CameraView<'a> {
+        CameraView {
+            camera: self,
+            renderer,
+        }
+    }
+}
+
+/// New position of the camera on one axis, so that `target` is inside the deadzone.
+fn deadzone_follow(position: f32, target: f32, deadzone: f32) -> f32 {
+    let half = deadzone / 2.0;
+    if target > position + half {
+        target - half
+    } else if target < position - half {
+        target + half
+    } else {
+        position
+    }
+}
+
+fn clamp_axis(position: f32, min: f32, max: f32) -> f32 {
+    if min > max {
+        (min + max) / 2.0
+    } else {
+        position.max(min).min(max)
+    }
+}
+
+fn bounding_rect(points: &[(f32, f32)]) -> Rect {
+    let min_x = points.iter().map(|p| p.0).fold(::std::f32::MAX, f32::min);
+    let min_y = points.iter().map(|p| p.1).fold(::std::f32::MAX, f32::min);
+    let max_x = points.iter().map(|p| p.0).fold(::std::f32::MIN, f32::max);
+    let max_y = points.iter().map(|p| p.1).fold(::std::f32::MIN, f32::max);
+    Rect::new(
+        min_x.floor() as i32,
+        min_y.floor() as i32,
+        (max_x.ceil() - min_x.floor()).max(1.0) as u32,
+        (max_y.ceil() - min_y.floor()).max(1.0) as u32,
+    )
+}
+
+fn hash(n: i32, seed: i32) -> f32 {
+    let mut n = (n as u32).wrapping_mul(0x27d4_eb2d) ^ (seed as u32).wrapping_mul(0x1656_67b1);
+    n = (n ^ (n >> 15)).wrapping_mul(0x85eb_ca6b);
+    n ^= n >> 13;
+    (n & 0xffff) as f32 / 32_767.5 - 1.0
+}
+
+/// Smooth value noise in the -1..1 range, different for each `seed`.
+fn noise(x: f32, seed: i32) -> f32 {
+    let cell = x.floor();
+    let t = x - cell;
+    let t = t * t * (3.0 - 2.0 * t);
+    let (a, b) = (hash(cell as i32, seed), hash(cell as i32 + 1, seed));
+    a + (b - a) * t
+}
+
+/// A renderer seen through a camera.
+pub struct CameraView<'a> {
+    camera: &'a Camera2D,
+    pub renderer: &'a mut WindowCanvas,
+}
+
+impl<'a> CameraView<'a> {
+    pub fn camera(&self) -> &Camera2D {
+        self.camera
+    }
+
+    /// Screen corners of a world rect, clockwise from the top left one.
+    fn corners(&self, rect: Rect) -> [Point; 4] {
+        let (left, top) = (rect.left() as f32, rect.top() as f32);
+        let (right, bottom) = (rect.right() as f32, rect.bottom() as f32);
+        [
+            self.camera.world_to_screen_point((left, top)),
+            self.camera.world_to_screen_point((right, top)),
+            self.camera.world_to_screen_point((right, bottom)),
+            self.camera.world_to_screen_point((left, bottom)),
+        ]
+    }
+
+    pub fn copy<R>(&mut self, texture: &Texture, src: R, dst: Rect) -> Result<(), String>
+    where
+        R: Into<Option<Rect>>,
+    {
+        self.copy_ex(texture, src, dst, 0.0, None, false, false)
+    }
+
+    /// Same as `WindowCanvas::copy_ex` with `dst` and `center` in world space.
+    // Same arguments as `WindowCanvas::copy_ex`, so that it can replace it
+    #[allow(clippy::too_many_arguments)]
+    pub fn copy_ex<R, P>(
+        &mut self,
+        texture: &Texture,
+        src: R,
+        dst: Rect,
+        angle: f64,
+        center: P,
+        flip_horizontal: bool,
+        flip_vertical: bool,
+    ) -> Result<(), String>
+    where
+        R: Into<Option<Rect>>,
+        P: Into<Option<Point>>,
+    {
+        if !self.camera.is_visible(dst) {
+            return Ok(());
+        }
+        let zoom = self.camera.zoom;
+        let center = center
+            .into()
+            .unwrap_or_else(|| Point::new(dst.width() as i32 / 2, dst.height() as i32 / 2));
+
+        // Rotate around the same point of the texture, moved to the screen
+        let pivot = self
+            .camera
+            .world_to_screen(((dst.x() + center.x()) as f32, (dst.y() + center.y()) as f32));
+        let screen_center = (center.x() as f32 * zoom, center.y() as f32 * zoom);
+        let screen_dst = Rect::new(
+            (pivot.0 - screen_center.0).round() as i32,
+            (pivot.1 - screen_center.1).round() as i32,
+            (dst.width() as f32 * zoom).round() as u32,
+            (dst.height() as f32 * zoom).round() as u32,
+        );
+        let (_, rotation) = self.camera.transform();
+
+        self.renderer.copy_ex(
+            texture,
+            src,
+            screen_dst,
+            angle - f64::from(rotation),
+            Point::new(
+                screen_center.0.round() as i32,
+                screen_center.1.round() as i32,
+            ),
+            flip_horizontal,
+            flip_vertical,
+        )
+    }
+
+    pub fn draw_point(&mut self, point: (f32, f32)) -> Result<(), String> {
+        let point = self.camera.world_to_screen_point(point);
+        self.renderer.draw_point(point)
+    }
+
+    pub fn draw_line(&mut self, start: (f32, f32), end: (f32, f32)) -> Result<(), String> {
+        let start = self.camera.world_to_screen_point(start);
+        let end = self.camera.world_to_screen_point(end);
+        self.renderer.draw_line(start, end)
+    }
+
+    pub fn draw_rect(&mut self, rect: Rect) -> Result<(), String> {
+        if !self.camera.is_visible(rect) {
+            return Ok(());
+        }
+        let corners = self.corners(rect);
+        self.renderer
+            .draw_lines(&[corners[0], corners[1], corners[2], corners[3], corners[0]][..])
+    }
+
+    /// Fill a world rect with the draw color, as a polygon when the camera is rotated.
+    pub fn fill_rect(&mut self, rect: Rect) -> Result<(), String> {
+        if !self.camera.is_visible(rect) {
+            return Ok(());
+        }
+        let corners = self.corners(rect);
+        if corners[0].y() == corners[1].y() && corners[0].x() == corners[3].x() {
+            let screen_rect = bounding_rect(&[
+                (corners[0].x() as f32, corners[0].y() as f32),
+                (corners[2].x() as f32, corners[2].y() as f32),
+            ]);
+            return self.renderer.fill_rect(screen_rect);
+        }
+
+        let xs: Vec<i16> = corners.iter().map(|p| p.x() as i16).collect();
+        let ys: Vec<i16> = corners.iter().map(|p| p.y() as i16).collect();
+        let color = self.renderer.draw_color();
+        self.renderer.filled_polygon(&xs, &ys, color)
+    }
+}
+
+impl<'a> DrawRegion for CameraView<'a> {
+    /// `position` is in world space, the region is culled when it cannot be seen.
+    fn draw_region_ex(
+        &mut self,
+        atlas: &TextureAtlas,
+        name: &str,
+        position: (i32, i32),
+        params: &DrawParams,
+    ) -> Result<(), String> {
+        let region = atlas
+            .region(name)
+            .ok_or_else(|| format!("No region named {}", name))?;
+
+        // The pivot is inside the source image, whatever the scale and rotation
+        let (width, height) = region.source_size;
+        let radius = ((width * width + height * height) as f32).sqrt()
+            * params.scale.0.abs().max(params.scale.1.abs());
+        let radius = radius.ceil() as u32;
+        let area = Rect::from_center(Point::new(position.0, position.1), radius * 2, radius * 2);
+        if !self.camera.is_visible(area) {
+            return Ok(());
+        }
+
+        let (_, rotation) = self.camera.transform();
+        let screen_position = self
+            .camera
+            .world_to_screen_point((position.0 as f32, position.1 as f32));
+        let params = DrawParams {
+            scale: (
+                params.scale.0 * self.camera.zoom,
+                params.scale.1 * self.camera.zoom,
+            ),
+            angle: params.angle - f64::from(rotation),
+            ..*params
+        };
+        self.renderer.draw_region_ex(
+            atlas,
+            name,
+            (screen_position.x(), screen_position.y()),
+            &params,
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn assert_near(a: (f32, f32), b: (f32, f32)) {
+        assert!(
+            (a.0 - b.0).abs() < 0.01 && (a.1 - b.1).abs() < 0.01,
+            "{:?} != {:?}",
+            a,
+            b
+        );
+    }
+
+    #[test]
+    fn test_world_to_screen() {
+        let mut camera = Camera2D::new((200, 100));
+        camera.position = (0.0, 0.0);
+        assert_near(camera.world_to_screen((10.0, 10.0)), (110.0, 60.0));
+
+        camera.zoom = 2.0;
+        camera.rotation = 90.0;
+        // Rotating the camera clockwise turns the world counter-clockwise
+        assert_near(camera.world_to_screen((10.0, 0.0)), (100.0, 30.0));
+        assert_near(camera.screen_to_world((100.0, 30.0)), (10.0, 0.0));
+        let screen = camera.world_to_screen((3.0, -7.0));
+        assert_near(camera.screen_to_world(screen), (3.0, -7.0));
+    }
+
+    #[test]
+    fn test_follow_deadzone() {
+        let mut camera = Camera2D::new((200, 100));
+        camera.position = (0.0, 0.0);
+        camera.deadzone = (40.0, 20.0);
+        camera.follow((15.0, 5.0));
+        camera.advance(16);
+        assert_near(camera.position, (0.0, 0.0));
+
+        camera.follow((50.0, -30.0));
+        camera.advance(16);
+        assert_near(camera.position, (30.0, -20.0));
+
+        camera.follow_speed = 10.0;
+        camera.follow((130.0, -20.0));
+        camera.advance(100);
+        assert!(camera.position.0 > 30.0 && camera.position.0 < 110.0);
+    }
+
+    #[test]
+    fn test_bounds() {
+        let mut camera = Camera2D::new((200, 100));
+        camera.bounds = Some(Rect::new(0, 0, 1000, 80));
+        camera.position = (-50.0, 500.0);
+        camera.clamp_to_bounds();
+        // The bounds are smaller than the viewport vertically
+        assert_near(camera.position, (100.0, 40.0));
+
+        camera.zoom = 2.0;
+        camera.position = (990.0, 40.0);
+        camera.clamp_to_bounds();
+        assert_near(camera.position, (950.0, 40.0));
+    }
+
+    #[test]
+    fn test_shake_and_culling() {
+        let mut camera = Camera2D::new((200, 100));
+        camera.add_trauma(2.0);
+        assert_eq!(camera.trauma(), 1.0);
+        camera.advance(500);
+        assert_eq!(camera.trauma(), 0.5);
+        camera.advance(1000);
+        assert_eq!(camera.trauma(), 0.0);
+        assert_near(camera.world_to_screen((0.0, 0.0)), (0.0, 0.0));
+
+        assert_eq!(camera.visible_bounds(), Rect::new(0, 0, 200, 100));
+        assert!(camera.is_visible(Rect::new(190, 90, 20, 20)));
+        assert!(!camera.is_visible(Rect::new(210, 0, 20, 20)));
+    }
+}
@@ -0,0 +1,386 @@
+use sdl2::pixels::Color;
+use sdl2::rect::{Point, Rect};
+use sdl2::render::{Texture, WindowCanvas};
+
+use std::cmp::Ordering;
+use std::sync::Arc;
+
+use atlas::{DrawParams, DrawRegion, TextureAtlas};
+use camera::CameraView;
+use font::BitmapFont;
+use shapes::{DrawShapes, ShapeStyle};
+
+/// A texture, or part of it, drawn like `WindowCanvas::copy_ex`.
+#[derive(Clone)]
+pub struct Sprite {
+    pub texture: Arc<Texture>,
+    pub src: Option<Rect>,
+    pub dst: Rect,
+    /// Clockwise rotation in degrees.
+    pub angle: f64,
+    /// Center of the rotation, relative to `dst`. `None` for the center of `dst`.
+    pub center: Option<Point>,
+    pub flip_horizontal: bool,
+    pub flip_vertical: bool,
+}
+
+impl Sprite {
+    pub fn new<R: Into<Option<Rect>>>(texture: &Arc<Texture>, src: R, dst: Rect) -> Self {
+        Sprite {
+            texture: Arc::clone(texture),
+            src: src.into(),
+            dst,
+            angle: 0.0,
+            center: None,
+            flip_horizontal: false,
+            flip_vertical: false,
+        }
+    }
+
+    pub fn with_angle(mut self, angle: f64, center: Option<Point>) -> Self {
+        self.angle = angle;
+        self.center = center;
+        self
+    }
+
+    pub fn with_flip(mut self, horizontal: bool, vertical: bool) -> Self {
+        self.flip_horizontal = horizontal;
+        self.flip_vertical = vertical;
+        self
+    }
+}
+
+/// Shapes of `shapes::DrawShapes` that can be queued.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Shape {
+    Polygon(Vec<(f32, f32)>),
+    /// Open line, anti-aliased unless the style is `Outline` or `Filled`.
+    Polyline(Vec<(f32, f32)>),
+    ThickLine {
+        start: (f32, f32),
+        end: (f32, f32),
+        width: f32,
+    },
+    Circle {
+        center: (f32, f32),
+        radius: f32,
+    },
+    Ellipse {
+        center: (f32, f32),
+        radii: (f32, f32),
+    },
+    Rectangle(Rect),
+    RoundedRect(Rect, f32),
+}
+
+#[derive(Clone)]
+pub enum DrawCommand {
+    Sprite(Sprite),
+    /// Region of an atlas, with its pivot at `position`.
+    Region {
+        atlas: Arc<TextureAtlas>,
+        name: String,
+        position: (i32, i32),
+        params: DrawParams,
+    },
+    Shape {
+        shape: Shape,
+        color: Color,
+        style: ShapeStyle,
+    },
+    Text {
+        font: Arc<BitmapFont>,
+        text: String,
+        position: (i32, i32),
+    },
+}
+
+impl DrawCommand {
+    /// Identifies the texture used, 0 for shapes.
+    fn texture_id(&self) -> usize {
+        match *self {
+            DrawCommand::Sprite(ref sprite) => &*sprite.texture as *const Texture as usize,
+            DrawCommand::Region { ref atlas, .. } => atlas.texture() as *const Texture as usize,
+            DrawCommand::Text { ref font, .. } => &**font as *const BitmapFont as usize,
+            DrawCommand::Shape { .. } => 0,
+        }
+    }
+}
+
+struct QueuedCommand {
+    layer: i32,
+    depth: f32,
+    texture_id: usize,
+    command: DrawCommand,
+}
+
+/// Draw commands collected during a frame and drawn sorted by layer, then by depth.
+///
+/// Commands with the same layer and depth are grouped by texture, their order is kept
+/// only among the ones using the same texture: give them different depths when they overlap.
+/// The engine flushes the queue after `GameScene::render`, so it is drawn over what the
+/// scene drew directly and under ImGui.
+#[derive(Default)]
+pub struct DrawQueue {
+    commands: Vec<QueuedCommand>,
+    texture_switches: usize,
+}
+
+impl DrawQueue {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.commands.clear();
+    }
+
+    /// Number of texture changes during the last flush.
+    pub fn texture_switches(&self) -> usize {
+        self.texture_switches
+    }
+
+    /// Lower layers are drawn first, and lower depths first inside a layer.
+    /// For y-sorting use the bottom of the sprites as depth.
+    pub fn push(&mut self, layer: i32, depth: f32, command: DrawCommand) {
+        let texture_id = command.texture_id();
+        self.commands.push(QueuedCommand {
+            layer,
+            depth,
+            texture_id,
+            command,
+        });
+    }
+
+    pub fn sprite(&mut self, layer: i32, depth: f32, sprite: Sprite) {
+        self.push(layer, depth, DrawCommand::Sprite(sprite));
+    }
+
+    pub fn region(
+        &mut self,
+        layer: i32,
+        depth: f32,
+        atlas: &Arc<TextureAtlas>,
+        name: &str,
+        position: (i32, i32),
+        params: DrawParams,
+    ) {
+        let command = DrawCommand::Region {
+            atlas: Arc::clone(atlas),
+            name: name.to_string(),
+            position,
+            params,
+        };
+        self.push(layer, depth, command);
+    }
+
+    pub fn shape(&mut self, layer: i32, depth: f32, shape: Shape, color: Color, style: ShapeStyle) {
+        let command = DrawCommand::Shape {
+            shape,
+            color,
+            style,
+        };
+        self.push(layer, depth, command);
+    }
+
+    pub fn text(
+        &mut self,
+        layer: i32,
+        depth: f32,
+        font: &Arc<BitmapFont>,
+        text: &str,
+        position: (i32, i32),
+    ) {
+        let command = DrawCommand::Text {
+            font: Arc::clone(font),
+            text: text.to_string(),
+            position,
+        };
+        self.push(layer, depth, command);
+    }
+
+    fn sort(&mut self) {
+        // Stable, so that commands with the same key keep their order
+        self.commands.sort_by(|a, b| {
+            a.layer
+                .cmp(&b.layer)
+                .then(a.depth.partial_cmp(&b.depth).unwrap_or(Ordering::Equal))
+                .then(a.texture_id.cmp(&b.texture_id))
+        });
+        self.texture_switches = count_switches(self.commands.iter().map(|c| c.texture_id));
+    }
+
+    /// Draw and remove every command, in logical coordinates.
+    pub fn flush(&mut self, renderer: &mut WindowCanvas) -> Result<(), String> {
+        self.flush_to(renderer)
+    }
+
+    /// Draw and remove every command, in world coordinates.
+    ///
+    /// Useful to flush the world layers before submitting the interface ones.
+    pub fn flush_with_camera(&mut self, view: &mut CameraView) -> Result<(), String> {
+        self.flush_to(view)
+    }
+
+    fn flush_to<T: QueueTarget>(&mut self, target: &mut T) -> Result<(), String> {
+        self.sort();
+        let result = self
+            .commands
+            .iter()
+            .map(|queued| target.draw_command(&queued.command))
+            .collect();
+        self.commands.clear();
+        result
+    }
+}
+
+fn count_switches<I: Iterator<Item = usize>>(ids: I) -> usize {
+    let mut current = 0;
+    let mut switches = 0;
+    for id in ids.filter(|&id| id != 0) {
+        if id != current {
+            switches += 1;
+            current = id;
+        }
+    }
+    switches
+}
+
+/// What the queue can be flushed to.
+trait QueueTarget: DrawShapes + DrawRegion {
+    fn draw_sprite(&mut self, sprite: &Sprite) -> Result<(), String>;
+
+    fn draw_text(&mut self, font: &BitmapFont, text: &str, position: (i32, i32));
+
+    fn draw_command(&mut self, command: &DrawCommand) -> Result<(), String> {
+        match *command {
+            DrawCommand::Sprite(ref sprite) => self.draw_sprite(sprite),
+            DrawCommand::Region {
+                ref atlas,
+                ref name,
+                position,
+                ref params,
+            } => self.draw_region_ex(atlas, name, position, params),
+            DrawCommand::Shape {
+                ref shape,
+                color,
+                style,
+            } => draw_shape(self, shape, color, style),
+            DrawCommand::Text {
+                ref font,
+                ref text,
+                position,
+            } => {
+                self.draw_text(font, text, position);
+                Ok(())
+            }
+        }
+    }
+}
+
+fn draw_shape<T: DrawShapes + ?Sized>(
+    target: &mut T,
+    shape: &Shape,
+    color: Color,
+    style: ShapeStyle,
+) -> Result<(), String> {
+    match *shape {
+        Shape::Polygon(ref points) => target.draw_polygon(points, color, style),
+        Shape::Polyline(ref points) => {
+            let smooth = style == ShapeStyle::SmoothOutline || style == ShapeStyle::SmoothFilled;
+            target.draw_polyline(points, color, smooth)
+        }
+        Shape::ThickLine { start, end, width } => target.draw_thick_line(start, end, width, color),
+        Shape::Circle { center, radius } => target.draw_circle(center, radius, color, style),
+        Shape::Ellipse { center, radii } => target.draw_ellipse(center, radii, color, style),
+        Shape::Rectangle(rect) => target.draw_rectangle(rect, color, style),
+        Shape::RoundedRect(rect, radius) => target.draw_rounded_rect(rect, radius, color, style),
+    }
+}
+
+impl QueueTarget for WindowCanvas {
+    fn draw_sprite(&mut self, sprite: &Sprite) -> Result<(), String> {
+        self.copy_ex(
+            &sprite.texture,
+            sprite.src,
+            sprite.dst,
+            sprite.angle,
+            sprite.center,
+            sprite.flip_horizontal,
+            sprite.flip_vertical,
+        )
+    }
+
+    fn draw_text(&mut self, font: &BitmapFont, text: &str, position: (i32, i32)) {
+        font.render_text(text, position, self);
+    }
+}
+
+impl<'a> QueueTarget for CameraView<'a> {
+    fn draw_sprite(&mut self, sprite: &Sprite) -> Result<(), String> {
+        self.copy_ex(
+            &sprite.texture,
+            sprite.src,
+            sprite.dst,
+            sprite.angle,
+            sprite.center,
+            sprite.flip_horizontal,
+            sprite.flip_vertical,
+        )
+    }
+
+    /// The text follows the camera but keeps its size.
+    fn draw_text(&mut self, font: &BitmapFont, text: &str, position: (i32, i32)) {
+        let (x, y) = self
+            .camera()
+            .world_to_screen((position.0 as f32, position.1 as f32));
+        font.render_text(text, (x.round() as i32, y.round() as i32), self.renderer);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_sort() {
+        let mut queue = DrawQueue::new();
+        let shape = |x| Shape::Circle {
+            center: (x, 0.0),
+            radius: 1.0,
+        };
+        let white = Color::RGB(255, 255, 255);
+        queue.shape(1, 0.0, shape(1.0), white, ShapeStyle::Filled);
+        queue.shape(0, 5.0, shape(2.0), white, ShapeStyle::Filled);
+        queue.shape(0, -1.0, shape(3.0), white, ShapeStyle::Filled);
+        queue.shape(0, 5.0, shape(4.0), white, ShapeStyle::Filled);
+        queue.sort();
+
+        let order: Vec<f32> = queue
+            .commands
+            .iter()
+            .map(|queued| match queued.command {
+                DrawCommand::Shape {
+                    shape: Shape::Circle { center, .. },
+                    ..
+                } => center.0,
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(order, vec![3.0, 2.0, 4.0, 1.0]);
+    }
+
+    #[test]
+    fn test_count_switches() {
+        assert_eq!(count_switches(vec![1, 1, 0, 1, 2, 2, 1].into_iter()), 3);
+        assert_eq!(count_switches(vec![0, 0].into_iter()), 0);
+    }
+}
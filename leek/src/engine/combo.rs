@@ -0,0 +1,533 @@
+use sdl2::controller::Button;
+use sdl2::keyboard::Scancode;
+use std::collections::{HashMap, HashSet};
+
+use engine::keyboard::KeyboardSnapshot;
+use game_controllers::{ControllerState, GameController, MAX_PLAYERS};
+
+const DEFAULT_WINDOW: u32 = 500;
+const DEFAULT_STEP_DELAY: u32 = 200;
+const DEFAULT_SIMULTANEOUS_WINDOW: u32 = 50;
+const DEFAULT_BUFFER_TIME: u32 = 150;
+const STICK_THRESHOLD: f32 = 0.5;
+
+/// Stick, D-pad or arrow keys direction.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Direction {
+    Up,
+    UpRight,
+    Right,
+    DownRight,
+    Down,
+    DownLeft,
+    Left,
+    UpLeft,
+}
+
+impl Direction {
+    /// Direction of a stick (y pointing down), `None` when it is centered.
+    pub fn from_xy(x: f32, y: f32) -> Option<Direction> {
+        let horizontal = if x > STICK_THRESHOLD {
+            1
+        } else if x < -STICK_THRESHOLD {
+            -1
+        } else {
+            0
+        };
+        let vertical = if y > STICK_THRESHOLD {
+            1
+        } else if y < -STICK_THRESHOLD {
+            -1
+        } else {
+            0
+        };
+
+        match (horizontal, vertical) {
+            (0, -1) => Some(Direction::Up),
+            (1, -1) => Some(Direction::UpRight),
+            (1, 0) => Some(Direction::Right),
+            (1, 1) => Some(Direction::DownRight),
+            (0, 1) => Some(Direction::Down),
+            (-1, 1) => Some(Direction::DownLeft),
+            (-1, 0) => Some(Direction::Left),
+            (-1, -1) => Some(Direction::UpLeft),
+            _ => None,
+        }
+    }
+
+    /// Same direction with left and right swapped.
+    pub fn mirrored(self) -> Direction {
+        match self {
+            Direction::UpRight => Direction::UpLeft,
+            Direction::Right => Direction::Left,
+            Direction::DownRight => Direction::DownLeft,
+            Direction::DownLeft => Direction::DownRight,
+            Direction::Left => Direction::Right,
+            Direction::UpLeft => Direction::UpRight,
+            direction => direction,
+        }
+    }
+}
+
+/// A single input that can be part of a combo.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ComboInput {
+    Direction(Direction),
+    Key(Scancode),
+    Button(Button),
+}
+
+impl From<Direction> for ComboInput {
+    fn from(direction: Direction) -> Self {
+        ComboInput::Direction(direction)
+    }
+}
+
+impl From<Scancode> for ComboInput {
+    fn from(scancode: Scancode) -> Self {
+        ComboInput::Key(scancode)
+    }
+}
+
+impl From<Button> for ComboInput {
+    fn from(button: Button) -> Self {
+        ComboInput::Button(button)
+    }
+}
+
+#[derive(Clone, Debug)]
+struct ComboStep {
+    inputs: Vec<ComboInput>,
+    charge: u32,
+}
+
+/// An ordered sequence of inputs, described for a character facing right.
+///
+/// ```ignore
+/// let fireball = Combo::new("fireball")
+///     .then(Direction::Down)
+///     .then(Direction::DownRight)
+///     .together(&[Direction::Right.into(), Button::X.into()])
+///     .within(300);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Combo {
+    name: String,
+    steps: Vec<ComboStep>,
+    window: u32,
+    step_delay: u32,
+}
+
+impl Combo {
+    pub fn new(name: &str) -> Combo {
+        Combo {
+            name: name.to_string(),
+            steps: Vec::new(),
+            window: DEFAULT_WINDOW,
+            step_delay: DEFAULT_STEP_DELAY,
+        }
+    }
+
+    pub fn then<I: Into<ComboInput>>(self, input: I) -> Combo {
+        self.together(&[input.into()])
+    }
+
+    /// A step where all the `inputs` must be pressed at the same time.
+    pub fn together(mut self, inputs: &[ComboInput]) -> Combo {
+        if !inputs.is_empty() {
+            self.steps.push(ComboStep {
+                inputs: inputs.to_vec(),
+                charge: 0,
+            });
+        }
+        self
+    }
+
+    /// A step where `input` must be held for at least `duration` milliseconds.
+    pub fn charged<I: Into<ComboInput>>(mut self, input: I, duration: u32) -> Combo {
+        self.steps.push(ComboStep {
+            inputs: vec![input.into()],
+            charge: duration,
+        });
+        self
+    }
+
+    /// Maximum time between the first and the last step (charge time excluded).
+    pub fn within(mut self, window: u32) -> Combo {
+        self.window = window;
+        self
+    }
+
+    /// Maximum time between two consecutive steps.
+    pub fn with_step_delay(mut self, step_delay: u32) -> Combo {
+        self.step_delay = step_delay;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn history_length(&self) -> u64 {
+        let charge: u32 = self.steps.iter().map(|step| step.charge).sum();
+        u64::from(self.window) + u64::from(self.step_delay) + u64::from(charge)
+    }
+}
+
+/// A combo recognized by a `ComboDetector`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ComboMatch {
+    pub name: String,
+    /// Player of the detector that recognized it.
+    pub player: Option<usize>,
+    /// Milliseconds since the combo has been completed, 0 during the frame it happened.
+    pub age: u32,
+}
+
+#[derive(Clone, Debug)]
+struct Press {
+    input: ComboInput,
+    time: u64,
+    released: Option<u64>,
+}
+
+/// Recognizes combos from the keyboard and controller inputs.
+///
+/// Add detectors to `Engine::combo_detectors`, their matches are reported by `EngineContext`.
+#[derive(Clone, Debug)]
+pub struct ComboDetector {
+    /// Read the controller of this player, or every controller when `None`.
+    pub player: Option<usize>,
+    pub use_keyboard: bool,
+    /// Keys used as directions: up, down, left, right.
+    pub direction_keys: [Scancode; 4],
+    /// Swap left and right, for a character facing left.
+    pub mirrored: bool,
+    /// Maximum time between the inputs of a step pressed together.
+    pub simultaneous_window: u32,
+    /// How long a match stays reported, so it can be used once the game is ready for it.
+    pub buffer_time: u32,
+    combos: Vec<Combo>,
+    held: HashSet<ComboInput>,
+    history: Vec<Press>,
+    matches: Vec<(String, u64)>,
+    time: u64,
+}
+
+impl Default for ComboDetector {
+    fn default() -> Self {
+        ComboDetector {
+            player: None,
+            use_keyboard: true,
+            direction_keys: [
+                Scancode::Up,
+                Scancode::Down,
+                Scancode::Left,
+                Scancode::Right,
+            ],
+            mirrored: false,
+            simultaneous_window: DEFAULT_SIMULTANEOUS_WINDOW,
+            buffer_time: DEFAULT_BUFFER_TIME,
+            combos: Vec::new(),
+            held: HashSet::new(),
+            history: Vec::new(),
+            matches: Vec::new(),
+            time: 0,
+        }
+    }
+}
+
+impl ComboDetector {
+    pub fn new() -> ComboDetector {
+        ComboDetector::default()
+    }
+
+    /// Detector reading only the controller of `player` (0 is P1).
+    pub fn for_player(player: usize) -> ComboDetector {
+        ComboDetector {
+            player: Some(player),
+            use_keyboard: false,
+            ..ComboDetector::default()
+        }
+    }
+
+    pub fn add_combo(&mut self, combo: Combo) {
+        self.combos.push(combo);
+    }
+
+    pub fn remove_combo(&mut self, name: &str) {
+        self.combos.retain(|combo| combo.name != name);
+    }
+
+    /// Forget the inputs and matches so far (ex: when a round starts).
+    pub fn reset(&mut self) {
+        self.history.clear();
+        self.matches.clear();
+    }
+
+    /// Read the inputs of this frame, `time` is in milliseconds.
+    pub fn update(
+        &mut self,
+        time: u64,
+        keyboard: &KeyboardSnapshot,
+        controllers: &HashMap<u32, GameController>,
+        players: &[Option<u32>; MAX_PLAYERS],
+    ) {
+        let mut held = HashSet::new();
+        let mut direction = (0.0, 0.0);
+
+        if self.use_keyboard {
+            let [up, down, left, right] = self.direction_keys;
+            let axis = |negative, positive| {
+                keyboard.is_down(positive) as i32 as f32 - keyboard.is_down(negative) as i32 as f32
+            };
+            direction = (axis(left, right), axis(up, down));
+            held.extend(keyboard.keys_down().map(ComboInput::Key));
+        }
+
+        let states: Vec<&ControllerState> = match self.player {
+            Some(player) => players
+                .get(player)
+                .and_then(|id| *id)
+                .and_then(|id| controllers.get(&id))
+                .map(GameController::state)
+                .into_iter()
+                .collect(),
+            None => controllers.values().map(GameController::state).collect(),
+        };
+        for state in states {
+            let dpad = |negative, positive| {
+                state.is_button_down(positive) as i32 as f32
+                    - state.is_button_down(negative) as i32 as f32
+            };
+            let (stick_x, stick_y) = state.left_stick();
+            direction.0 += dpad(Button::DPadLeft, Button::DPadRight) + stick_x;
+            direction.1 += dpad(Button::DPadUp, Button::DPadDown) + stick_y;
+            held.extend(state.buttons_down().iter().cloned().map(ComboInput::Button));
+        }
+
+        if let Some(direction) = Direction::from_xy(direction.0, direction.1) {
+            held.insert(ComboInput::Direction(direction));
+        }
+        self.update_inputs(time, held);
+    }
+
+    /// Record the inputs held at `time` and look for completed combos.
+    pub fn update_inputs(&mut self, time: u64, held: HashSet<ComboInput>) {
+        let held: HashSet<ComboInput> = if self.mirrored {
+            held.into_iter()
+                .map(|input| match input {
+                    ComboInput::Direction(direction) => ComboInput::Direction(direction.mirrored()),
+                    input => input,
+                })
+                .collect()
+        } else {
+            held
+        };
+        self.time = time;
+
+        for press in &mut self.history {
+            if press.released.is_none() && !held.contains(&press.input) {
+                press.released = Some(time);
+            }
+        }
+        let new_presses = held.difference(&self.held).count() > 0;
+        for input in held.difference(&self.held) {
+            self.history.push(Press {
+                input: *input,
+                time,
+                released: None,
+            });
+        }
+        self.held = held;
+
+        let history_length = self
+            .combos
+            .iter()
+            .map(Combo::history_length)
+            .max()
+            .unwrap_or(0);
+        self.history.retain(|press| match press.released {
+            Some(released) => time - released <= history_length,
+            None => true,
+        });
+        let buffer_time = u64::from(self.buffer_time);
+        self.matches
+            .retain(|&(_, matched)| time - matched <= buffer_time);
+
+        if new_presses {
+            let matched: Vec<String> = self
+                .combos
+                .iter()
+                .filter(|combo| self.is_completed(combo))
+                .map(|combo| combo.name.clone())
+                .collect();
+            self.matches
+                .extend(matched.into_iter().map(|name| (name, time)));
+        }
+    }
+
+    /// Combos completed during the last `buffer_time` milliseconds.
+    pub fn matches<'a>(&'a self) -> impl Iterator<Item = ComboMatch> + 'a {
+        self.matches
+            .iter()
+            .map(move |&(ref name, time)| ComboMatch {
+                name: name.clone(),
+                player: self.player,
+                age: (self.time - time) as u32,
+            })
+    }
+
+    fn is_completed(&self, combo: &Combo) -> bool {
+        !combo.steps.is_empty()
+            && self.match_step(combo, combo.steps.len() - 1, self.history.len(), None)
+    }
+
+    /// Match the steps up to `index` using the presses before `bound`,
+    /// `next` being the time of the step that follows.
+    fn match_step(&self, combo: &Combo, index: usize, bound: usize, next: Option<u64>) -> bool {
+        let step = &combo.steps[index];
+        let history = &self.history[..bound];
+        let simultaneous_window = u64::from(self.simultaneous_window);
+
+        for (first, press) in history.iter().enumerate().rev() {
+            if press.input != step.inputs[0] {
+                continue;
+            }
+
+            let mut indices = vec![first];
+            let together = step.inputs[1..].iter().all(|input| {
+                let other = history.iter().rposition(|other| {
+                    other.input == *input && distance(other.time, press.time) <= simultaneous_window
+                });
+                match other {
+                    Some(other) => {
+                        indices.push(other);
+                        true
+                    }
+                    None => false,
+                }
+            });
+            if !together {
+                continue;
+            }
+            let start = indices.iter().map(|i| history[*i].time).min().unwrap();
+            let pressed = indices.iter().map(|i| history[*i].time).max().unwrap();
+
+            let end = if step.charge > 0 {
+                let released = press.released.unwrap_or(self.time);
+                if released - press.time < u64::from(step.charge) {
+                    continue;
+                }
+                released
+            } else {
+                pressed
+            };
+
+            match next {
+                // The last step must be completed during this frame
+                None if pressed != self.time => continue,
+                Some(next) if next.saturating_sub(end) > u64::from(combo.step_delay) => continue,
+                _ => {}
+            }
+
+            if index == 0 {
+                if self.time - end <= u64::from(combo.window) {
+                    return true;
+                }
+            } else {
+                let bound = *indices.iter().min().unwrap();
+                if self.match_step(combo, index - 1, bound, Some(start)) {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+}
+
+fn distance(a: u64, b: u64) -> u64 {
+    if a > b {
+        a - b
+    } else {
+        b - a
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn inputs(inputs: &[ComboInput]) -> HashSet<ComboInput> {
+        inputs.iter().cloned().collect()
+    }
+
+    fn fireball() -> ComboDetector {
+        let mut detector = ComboDetector::new();
+        detector.add_combo(
+            Combo::new("fireball")
+                .then(Direction::Down)
+                .then(Direction::DownRight)
+                .together(&[Direction::Right.into(), Button::X.into()])
+                .within(300),
+        );
+        detector
+    }
+
+    #[test]
+    fn test_sequence() {
+        let mut detector = fireball();
+        detector.update_inputs(0, inputs(&[Direction::Down.into()]));
+        detector.update_inputs(50, inputs(&[Direction::DownRight.into()]));
+        detector.update_inputs(100, inputs(&[Direction::Right.into()]));
+        assert_eq!(detector.matches().count(), 0);
+
+        detector.update_inputs(120, inputs(&[Direction::Right.into(), Button::X.into()]));
+        let matches: Vec<ComboMatch> = detector.matches().collect();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].name, "fireball");
+        assert_eq!(matches[0].age, 0);
+
+        detector.update_inputs(200, inputs(&[]));
+        assert_eq!(detector.matches().next().map(|m| m.age), Some(80));
+    }
+
+    #[test]
+    fn test_too_slow() {
+        let mut detector = fireball();
+        detector.update_inputs(0, inputs(&[Direction::Down.into()]));
+        detector.update_inputs(150, inputs(&[Direction::DownRight.into()]));
+        detector.update_inputs(300, inputs(&[Direction::Right.into()]));
+        detector.update_inputs(400, inputs(&[Direction::Right.into(), Button::X.into()]));
+        assert_eq!(detector.matches().count(), 0);
+    }
+
+    #[test]
+    fn test_mirrored() {
+        let mut detector = fireball();
+        detector.mirrored = true;
+        detector.update_inputs(0, inputs(&[Direction::Down.into()]));
+        detector.update_inputs(50, inputs(&[Direction::DownLeft.into()]));
+        detector.update_inputs(100, inputs(&[Direction::Left.into(), Button::X.into()]));
+        assert_eq!(detector.matches().count(), 1);
+    }
+
+    #[test]
+    fn test_charge() {
+        let mut detector = ComboDetector::new();
+        detector.add_combo(
+            Combo::new("sonic")
+                .charged(Direction::Left, 1000)
+                .together(&[Direction::Right.into(), Button::Y.into()]),
+        );
+
+        detector.update_inputs(0, inputs(&[Direction::Left.into()]));
+        detector.update_inputs(500, inputs(&[Direction::Right.into(), Button::Y.into()]));
+        assert_eq!(detector.matches().count(), 0);
+
+        detector.update_inputs(600, inputs(&[Direction::Left.into()]));
+        detector.update_inputs(1700, inputs(&[Direction::Right.into()]));
+        detector.update_inputs(1720, inputs(&[Direction::Right.into(), Button::Y.into()]));
+        assert_eq!(detector.matches().count(), 1);
+    }
+}
@@ -1,58 +1,192 @@
-use sdl2::keyboard::Scancode;
+use sdl2::controller::Button;
+use sdl2::keyboard::{Keycode, Mod, Scancode};
 use sdl2::mouse::MouseState;
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 
-use game_controllers::GameController;
+use engine::combo::ComboMatch;
+use engine::keyboard::KeyboardSnapshot;
+use engine::touch::{Gesture, TouchPoint, TouchSnapshot};
+use game_controllers::{ControllerState, GameController, MAX_PLAYERS};
+use text_input::TextInputState;
+
+/// State of the input devices during a frame, see `EngineContext::new`.
+pub struct FrameInput {
+    pub keyboard: KeyboardSnapshot,
+    pub text_input: TextInputState,
+    pub mouse_state: MouseState,
+    /// Mouse position in logical coordinates.
+    pub mouse_position: (f32, f32),
+    pub touch: TouchSnapshot,
+}
 
 #[derive(Clone)]
 pub struct EngineContext {
-    keyboard_down: HashSet<Scancode>,
-    keyboard_pressed: HashSet<Scancode>,
+    keyboard: KeyboardSnapshot,
+    text_input: TextInputState,
     mouse_state: MouseState,
+    mouse_position: (f32, f32),
+    touch: TouchSnapshot,
     pub delta_time: u32,
     pub elapsed_time: u64,
     // TODO use a reference instead of a clone
     pub controllers: HashMap<u32, GameController>,
+    players: [Option<u32>; MAX_PLAYERS],
+    combos: Vec<ComboMatch>,
 }
 
 impl EngineContext {
     pub fn new(
-        keyboard_down: HashSet<Scancode>,
-        keyboard_pressed: HashSet<Scancode>,
+        input: FrameInput,
         delta_time: u32,
         elapsed_time: u64,
-        mouse_state: MouseState,
         controllers: HashMap<u32, GameController>,
+        players: [Option<u32>; MAX_PLAYERS],
+        combos: Vec<ComboMatch>,
     ) -> EngineContext {
         EngineContext {
-            keyboard_down,
-            keyboard_pressed,
+            keyboard: input.keyboard,
+            text_input: input.text_input,
             delta_time,
-            mouse_state,
+            mouse_state: input.mouse_state,
+            mouse_position: input.mouse_position,
+            touch: input.touch,
             elapsed_time,
             controllers,
+            players,
+            combos,
         }
     }
     pub fn is_key_down(&self, scancode: Scancode) -> bool {
-        self.keyboard_down.contains(&scancode)
+        self.keyboard.is_down(scancode)
     }
     pub fn is_key_pressed(&self, scancode: Scancode) -> bool {
-        self.keyboard_pressed.contains(&scancode)
+        self.keyboard.is_pressed(scancode)
+    }
+    /// Returns `true` if the key has been released during this frame.
+    pub fn is_key_released(&self, scancode: Scancode) -> bool {
+        self.keyboard.is_released(scancode)
+    }
+    /// Returns `true` if the OS sent a key repeat for this key during this frame.
+    pub fn is_key_repeated(&self, scancode: Scancode) -> bool {
+        self.keyboard.is_repeated(scancode)
+    }
+
+    // Keycode queries depend on the keyboard layout (Z is where W is on AZERTY).
+
+    pub fn is_keycode_down(&self, keycode: Keycode) -> bool {
+        self.keyboard.is_keycode_down(keycode)
+    }
+    pub fn is_keycode_pressed(&self, keycode: Keycode) -> bool {
+        self.keyboard.is_keycode_pressed(keycode)
+    }
+    pub fn is_keycode_released(&self, keycode: Keycode) -> bool {
+        self.keyboard.is_keycode_released(keycode)
+    }
+    pub fn is_keycode_repeated(&self, keycode: Keycode) -> bool {
+        self.keyboard.is_keycode_repeated(keycode)
     }
+
+    /// Modifier keys (Ctrl, Shift, Alt, GUI, ...) held during this frame.
+    pub fn key_modifiers(&self) -> Mod {
+        self.keyboard.modifiers()
+    }
+    pub fn is_ctrl_down(&self) -> bool {
+        self.keyboard.is_ctrl_down()
+    }
+    pub fn is_shift_down(&self) -> bool {
+        self.keyboard.is_shift_down()
+    }
+    pub fn is_alt_down(&self) -> bool {
+        self.keyboard.is_alt_down()
+    }
+    pub fn is_gui_down(&self) -> bool {
+        self.keyboard.is_gui_down()
+    }
+
+    /// Text typed (and IME composition) during this frame, see `Engine::text_input`.
+    pub fn text_input(&self) -> &TextInputState {
+        &self.text_input
+    }
+
+    /// State of the controller with the given instance id, with deadzones applied.
+    pub fn controller_state(&self, instance_id: u32) -> Option<&ControllerState> {
+        self.controllers.get(&instance_id).map(GameController::state)
+    }
+    /// Returns `true` if `button` has been pressed during this frame on the given controller.
+    pub fn is_button_pressed(&self, instance_id: u32, button: Button) -> bool {
+        self.controller_state(instance_id)
+            .map_or(false, |state| state.is_button_pressed(button))
+    }
+    /// Returns `true` if `button` has been released during this frame on the given controller.
+    pub fn is_button_released(&self, instance_id: u32, button: Button) -> bool {
+        self.controller_state(instance_id)
+            .map_or(false, |state| state.is_button_released(button))
+    }
+
+    /// Instance id of the controller used by `player` (0 is P1), if connected.
+    pub fn player_controller_id(&self, player: usize) -> Option<u32> {
+        self.players.get(player).and_then(|id| *id)
+    }
+    /// State of the controller used by `player` (0 is P1), if connected.
+    pub fn player_state(&self, player: usize) -> Option<&ControllerState> {
+        self.player_controller_id(player)
+            .and_then(|instance_id| self.controller_state(instance_id))
+    }
+
+    /// Combos recognized by the detectors of `Engine::combo_detectors`, still in their buffer time.
+    pub fn combos(&self) -> &[ComboMatch] {
+        &self.combos
+    }
+    /// Returns `true` if the combo has been completed during this frame.
+    pub fn is_combo_matched(&self, name: &str) -> bool {
+        self.combos
+            .iter()
+            .any(|combo| combo.name == name && combo.age == 0)
+    }
+    /// Returns `true` if the combo has been completed recently (see `ComboDetector::buffer_time`).
+    pub fn is_combo_buffered(&self, name: &str) -> bool {
+        self.combos.iter().any(|combo| combo.name == name)
+    }
+
     pub fn mouse_state(&self) -> &MouseState {
         &self.mouse_state
     }
+
+    /// Position of the mouse in logical coordinates, the one of `mouse_state` is in window points.
+    pub fn mouse_position(&self) -> (f32, f32) {
+        self.mouse_position
+    }
+
+    /// Fingers touching the screen, in logical coordinates.
+    pub fn touches(&self) -> &[TouchPoint] {
+        self.touch.points()
+    }
+    pub fn touch(&self, id: i64) -> Option<&TouchPoint> {
+        self.touch.point(id)
+    }
+    /// The first finger of the current touch, also used to emulate the mouse.
+    pub fn primary_touch(&self) -> Option<&TouchPoint> {
+        self.touch.primary()
+    }
+    /// Gestures recognized during this frame.
+    pub fn gestures(&self) -> &[Gesture] {
+        self.touch.gestures()
+    }
 }
 
 impl Default for EngineContext {
     fn default() -> Self {
         EngineContext {
-            keyboard_down: HashSet::default(),
-            keyboard_pressed: HashSet::default(),
+            keyboard: KeyboardSnapshot::default(),
+            text_input: TextInputState::default(),
             mouse_state: MouseState::from_sdl_state(0),
+            mouse_position: (0.0, 0.0),
+            touch: TouchSnapshot::default(),
             elapsed_time: 0,
             delta_time: 0,
             controllers: HashMap::default(),
+            players: [None; MAX_PLAYERS],
+            combos: Vec::new(),
         }
     }
 }
@@ -20,6 +20,26 @@ pub trait GameScene {
     #[allow(unused)]
     fn on_cache_updated(&mut self, engine: &mut Engine, key: PathKey) {}
 
+    /// Called when a controller is connected, `player` is the slot it got (0 is P1).
+    #[allow(unused)]
+    fn on_controller_connected(
+        &mut self,
+        engine: &mut Engine,
+        instance_id: u32,
+        player: Option<usize>,
+    ) {
+    }
+
+    /// Called when a controller is disconnected, `player` is the slot it had.
+    #[allow(unused)]
+    fn on_controller_disconnected(
+        &mut self,
+        engine: &mut Engine,
+        instance_id: u32,
+        player: Option<usize>,
+    ) {
+    }
+
     /// Called when another scene has been pushed on the stack
     fn on_pause(&mut self) {}
     /// Called when this scene is reactivated.
@@ -0,0 +1,231 @@
+use sdl2::event::Event;
+use sdl2::keyboard::{Keycode, Mod, Scancode};
+use sdl2::keyboard::{LALTMOD, LCTRLMOD, LGUIMOD, LSHIFTMOD, RALTMOD, RCTRLMOD, RGUIMOD, RSHIFTMOD};
+use sdl2::EventPump;
+use std::collections::HashSet;
+
+/// State of the keyboard during a single frame.
+///
+/// Scancodes refer to the physical position of a key, keycodes to the symbol
+/// it produces with the current layout (use them for shortcuts like Ctrl+Z).
+#[derive(Clone, Debug)]
+pub struct KeyboardSnapshot {
+    down: HashSet<Scancode>,
+    pressed: HashSet<Scancode>,
+    released: HashSet<Scancode>,
+    repeated: HashSet<Scancode>,
+    keycodes_down: HashSet<Keycode>,
+    keycodes_pressed: HashSet<Keycode>,
+    keycodes_released: HashSet<Keycode>,
+    keycodes_repeated: HashSet<Keycode>,
+    modifiers: Mod,
+}
+
+impl KeyboardSnapshot {
+    pub fn new(
+        down: HashSet<Scancode>,
+        pressed: HashSet<Scancode>,
+        released: HashSet<Scancode>,
+        repeated: HashSet<Scancode>,
+        modifiers: Mod,
+    ) -> KeyboardSnapshot {
+        KeyboardSnapshot::with_keymap(
+            down,
+            pressed,
+            released,
+            repeated,
+            modifiers,
+            Keycode::from_scancode,
+        )
+    }
+
+    /// Same as `new`, with the keycodes given by `keymap` instead of the current layout.
+    fn with_keymap<F>(
+        down: HashSet<Scancode>,
+        pressed: HashSet<Scancode>,
+        released: HashSet<Scancode>,
+        repeated: HashSet<Scancode>,
+        modifiers: Mod,
+        keymap: F,
+    ) -> KeyboardSnapshot
+    where
+        F: Fn(Scancode) -> Option<Keycode>,
+    {
+        KeyboardSnapshot {
+            keycodes_down: to_keycodes(&down, &keymap),
+            keycodes_pressed: to_keycodes(&pressed, &keymap),
+            keycodes_released: to_keycodes(&released, &keymap),
+            keycodes_repeated: to_keycodes(&repeated, &keymap),
+            down,
+            pressed,
+            released,
+            repeated,
+            modifiers,
+        }
+    }
+
+    pub fn is_down(&self, scancode: Scancode) -> bool {
+        self.down.contains(&scancode)
+    }
+    /// Every key held during this frame.
+    pub fn keys_down<'a>(&'a self) -> impl Iterator<Item = Scancode> + 'a {
+        self.down.iter().cloned()
+    }
+    pub fn is_pressed(&self, scancode: Scancode) -> bool {
+        self.pressed.contains(&scancode)
+    }
+    pub fn is_released(&self, scancode: Scancode) -> bool {
+        self.released.contains(&scancode)
+    }
+    pub fn is_repeated(&self, scancode: Scancode) -> bool {
+        self.repeated.contains(&scancode)
+    }
+
+    pub fn is_keycode_down(&self, keycode: Keycode) -> bool {
+        self.keycodes_down.contains(&keycode)
+    }
+    pub fn is_keycode_pressed(&self, keycode: Keycode) -> bool {
+        self.keycodes_pressed.contains(&keycode)
+    }
+    pub fn is_keycode_released(&self, keycode: Keycode) -> bool {
+        self.keycodes_released.contains(&keycode)
+    }
+    pub fn is_keycode_repeated(&self, keycode: Keycode) -> bool {
+        self.keycodes_repeated.contains(&keycode)
+    }
+
+    pub fn modifiers(&self) -> Mod {
+        self.modifiers
+    }
+    pub fn is_ctrl_down(&self) -> bool {
+        self.modifiers.intersects(LCTRLMOD | RCTRLMOD)
+    }
+    pub fn is_shift_down(&self) -> bool {
+        self.modifiers.intersects(LSHIFTMOD | RSHIFTMOD)
+    }
+    pub fn is_alt_down(&self) -> bool {
+        self.modifiers.intersects(LALTMOD | RALTMOD)
+    }
+    pub fn is_gui_down(&self) -> bool {
+        self.modifiers.intersects(LGUIMOD | RGUIMOD)
+    }
+}
+
+impl Default for KeyboardSnapshot {
+    fn default() -> Self {
+        KeyboardSnapshot {
+            down: HashSet::default(),
+            pressed: HashSet::default(),
+            released: HashSet::default(),
+            repeated: HashSet::default(),
+            keycodes_down: HashSet::default(),
+            keycodes_pressed: HashSet::default(),
+            keycodes_released: HashSet::default(),
+            keycodes_repeated: HashSet::default(),
+            modifiers: Mod::empty(),
+        }
+    }
+}
+
+fn to_keycodes<F>(scancodes: &HashSet<Scancode>, keymap: &F) -> HashSet<Keycode>
+where
+    F: Fn(Scancode) -> Option<Keycode>,
+{
+    scancodes
+        .iter()
+        .filter_map(|scancode| keymap(*scancode))
+        .collect()
+}
+
+/// Keeps track of the keyboard between frames, to compute what changed.
+#[derive(Debug, Default)]
+pub struct KeyboardTracker {
+    keys_down: HashSet<Scancode>,
+    repeated: HashSet<Scancode>,
+}
+
+impl KeyboardTracker {
+    pub fn process_event(&mut self, event: &Event) {
+        if let Event::KeyDown {
+            scancode: Some(scancode),
+            repeat: true,
+            ..
+        } = *event
+        {
+            self.repeated.insert(scancode);
+        }
+    }
+
+    /// Returns the keyboard state for the current frame.
+    pub fn snapshot(&mut self, event_pump: &EventPump, modifiers: Mod) -> KeyboardSnapshot {
+        let keys_snapshot: HashSet<Scancode> =
+            event_pump.keyboard_state().pressed_scancodes().collect();
+        let newly_pressed = &keys_snapshot - &self.keys_down;
+        let released = &self.keys_down - &keys_snapshot;
+        self.keys_down.clone_from(&keys_snapshot);
+
+        let repeated = self.repeated.drain().collect();
+
+        KeyboardSnapshot::new(keys_snapshot, newly_pressed, released, repeated, modifiers)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Part of an AZERTY layout, where the key at the position of W gives a Z.
+    fn azerty(scancode: Scancode) -> Option<Keycode> {
+        match scancode {
+            Scancode::W => Some(Keycode::Z),
+            Scancode::Z => Some(Keycode::W),
+            Scancode::Q => Some(Keycode::A),
+            Scancode::LCtrl => Some(Keycode::LCtrl),
+            _ => None,
+        }
+    }
+
+    fn scancodes(scancodes: &[Scancode]) -> HashSet<Scancode> {
+        scancodes.iter().cloned().collect()
+    }
+
+    #[test]
+    fn test_keycodes() {
+        let keyboard = KeyboardSnapshot::with_keymap(
+            scancodes(&[Scancode::LCtrl, Scancode::W]),
+            scancodes(&[Scancode::W]),
+            scancodes(&[Scancode::Q]),
+            scancodes(&[Scancode::LCtrl]),
+            LCTRLMOD,
+            azerty,
+        );
+        assert!(keyboard.is_pressed(Scancode::W));
+        assert!(keyboard.is_keycode_pressed(Keycode::Z));
+        assert!(!keyboard.is_keycode_pressed(Keycode::W));
+        assert!(keyboard.is_keycode_down(Keycode::LCtrl));
+        assert!(keyboard.is_keycode_released(Keycode::A));
+        assert!(keyboard.is_keycode_repeated(Keycode::LCtrl));
+        assert!(!keyboard.is_keycode_repeated(Keycode::Z));
+        assert_eq!(keyboard.keys_down().count(), 2);
+    }
+
+    #[test]
+    fn test_modifiers() {
+        let keyboard = KeyboardSnapshot::with_keymap(
+            HashSet::new(),
+            HashSet::new(),
+            HashSet::new(),
+            HashSet::new(),
+            RSHIFTMOD | LGUIMOD,
+            azerty,
+        );
+        assert!(keyboard.is_shift_down());
+        assert!(keyboard.is_gui_down());
+        assert!(!keyboard.is_ctrl_down());
+        assert!(!keyboard.is_alt_down());
+
+        let keyboard = KeyboardSnapshot::default();
+        assert!(!keyboard.is_ctrl_down() && !keyboard.is_shift_down());
+        assert!(!keyboard.is_alt_down() && !keyboard.is_gui_down());
+    }
+}
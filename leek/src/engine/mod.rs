@@ -1,6 +1,9 @@
 pub mod action;
+pub mod combo;
 pub mod context;
 pub mod game;
+pub mod keyboard;
+pub mod touch;
 use debug;
 
 use alto;
@@ -16,16 +19,23 @@ use sdl2::ttf::Sdl2TtfContext;
 use sdl2::video::WindowContext;
 use sdl2::EventPump;
 use sdl2::Sdl;
-use std::collections::HashSet;
+use std::path::PathBuf;
 
-use {AnyGameScene, EngineAction, EngineBuilder, EngineContext, FromEngine, GameScene};
+use {AnyGameScene, EngineAction, EngineBuilder, EngineContext, FromEngine, FrameInput, GameScene};
 
+use self::combo::ComboDetector;
+use self::keyboard::KeyboardTracker;
+use self::touch::TouchTracker;
+use draw_queue::DrawQueue;
 use fps_counter::FpsCounter;
-use game_controllers::GameControllerManager;
+use game_controllers::{ControllerEvent, GameControllerManager};
+use pixel_perfect::{self, PixelPerfect};
+use post_processing::PostProcessPipeline;
 
-use super::resources::Resources;
+use super::resources::{Cache, Resources};
 
 use super::sdl2_utils;
+use text_input::TextInput;
 
 use imgui::ImGui;
 use imgui_backend;
@@ -38,7 +48,19 @@ pub struct Engine {
     pub resources: Resources,
     pub alto_context: alto::Context,
     pub clear_color: Color,
+    pub text_input: TextInput,
+    pub game_controllers: GameControllerManager,
+    /// Per user writable directory, to store settings and saves.
+    pub settings_dir: Option<PathBuf>,
+    /// Combo detectors updated every frame, see `EngineContext::combos`.
+    pub combo_detectors: Vec<ComboDetector>,
     pub imgui_draw_cursor: bool,
+    /// Flushed after `GameScene::render`, see `DrawQueue`.
+    pub draw_queue: DrawQueue,
+    /// Set by `EngineBuilder::with_pixel_perfect`.
+    pub pixel_perfect: Option<PixelPerfect>,
+    /// Effects applied to the frame when at least one pass is enabled.
+    pub post_processing: PostProcessPipeline,
     event_pump: EventPump,
 }
 
@@ -53,10 +75,28 @@ where
         width,
         height,
         options.fullscreen,
-        options.logical_size,
+        if options.pixel_perfect.is_some() {
+            None
+        } else {
+            options.logical_size
+        },
     )?;
 
+    if let Some((width, height)) = options.pixel_perfect {
+        let mut pixel_perfect =
+            PixelPerfect::new(&engine.renderer, width, height).map_err(err_msg)?;
+        pixel_perfect.letterbox_color = options.letterbox_color;
+        engine.pixel_perfect = Some(pixel_perfect);
+    }
+    for make_pass in &options.post_process_passes {
+        let pass = make_pass().map_err(err_msg)?;
+        engine.post_processing.add_pass(pass);
+    }
     engine.clear_color = options.clear_color;
+    engine.settings_dir = sdl2_utils::settings_dir(options.organization, options.window_title);
+    engine
+        .game_controllers
+        .load_default_mappings(engine.settings_dir.as_ref().map(PathBuf::as_path));
 
     let mut imgui = ImGui::init();
     imgui_backend::configure_keys(&mut imgui);
@@ -67,15 +107,19 @@ where
 
     let mut fps_counter = FpsCounter::new();
 
-    let mut game_controller_manager = GameControllerManager::new();
-
     let mut game: AnyGameScene = Box::new(Scene::init(&mut engine));
 
     game.set_up();
 
     let mut game_stack = vec![game];
 
-    let mut keys_down: HashSet<Scancode> = Default::default();
+    let mut keyboard_tracker = KeyboardTracker::default();
+    let mut touch_tracker = TouchTracker::default();
+    let mut timer = engine.sdl2_context.timer().map_err(err_msg)?;
+    sdl2::hint::set(
+        "SDL_TOUCH_MOUSE_EVENTS",
+        if options.touch_mouse_events { "1" } else { "0" },
+    );
 
     let mouse = engine.sdl2_context.mouse();
     mouse.show_cursor(!options.hide_cursor);
@@ -93,17 +137,39 @@ where
         }
 
         // EVENT HANDLING
+        let window_size = engine.renderer.window().size();
+        let output_size = engine.renderer.output_size().map_err(err_msg)?;
+        let logical_size = engine.renderer.logical_size();
+        let pixel_perfect_size = engine.pixel_perfect.as_ref().map(PixelPerfect::size);
+        // From a normalized window position
+        let to_logical = |position: (f32, f32)| match pixel_perfect_size {
+            Some(size) => {
+                let point = (
+                    position.0 * window_size.0 as f32,
+                    position.1 * window_size.1 as f32,
+                );
+                pixel_perfect::window_to_logical(point, window_size, output_size, size)
+            }
+            None => touch::to_logical(position, window_size, logical_size),
+        };
         for event in engine.event_pump.poll_iter() {
+            // The hint is ignored before SDL 2.0.10
+            if !options.touch_mouse_events && touch::is_touch_mouse_event(&event) {
+                continue;
+            }
             imgui_backend::process_event(&mut imgui, &event);
+            keyboard_tracker.process_event(&event);
+            touch_tracker.process_event(&event, &to_logical);
+            engine.text_input.process_event(&event);
 
             match event {
                 Event::Quit { .. } => break 'running,
                 Event::ControllerDeviceAdded { which, .. } => {
-                    game_controller_manager.added_controller(which)
+                    engine.game_controllers.added_controller(which)
                 }
                 Event::ControllerDeviceRemoved { which, .. } => {
                     // TODO check if cast is ok
-                    game_controller_manager.removed_controller(which as u32)
+                    engine.game_controllers.removed_controller(which as u32)
                 }
                 _ => {
                     if let Event::KeyUp { scancode, .. } = event {
@@ -113,6 +179,10 @@ where
                                 engine.resources.inspect_window = !engine.resources.inspect_window;
                             }
                             Some(Scancode::F11) => debug_stats.toggle(),
+                            Some(Scancode::F10) => {
+                                engine.post_processing.inspect_window =
+                                    !engine.post_processing.inspect_window;
+                            }
                             _ => {}
                         }
                     }
@@ -122,7 +192,32 @@ where
             }
         }
 
-        if let Some(key) = engine.resources.sync_resources() {
+        for event in engine.game_controllers.drain_events() {
+            let scene = game_stack.last_mut().unwrap();
+            match event {
+                ControllerEvent::Connected {
+                    instance_id,
+                    player,
+                } => scene.on_controller_connected(&mut engine, instance_id, player),
+                ControllerEvent::Disconnected {
+                    instance_id,
+                    player,
+                } => scene.on_controller_disconnected(&mut engine, instance_id, player),
+            }
+        }
+
+        let updated_key = engine.resources.sync_resources();
+        for (shader, result) in engine.resources.take_reloaded_shaders() {
+            match result {
+                Ok(()) => {
+                    if let Some(source) = engine.resources.get(&shader) {
+                        engine.post_processing.reload_shader(&source);
+                    }
+                }
+                Err(error) => engine.post_processing.shader_reload_failed(shader.0, &error),
+            }
+        }
+        if let Some(key) = updated_key {
             game_stack
                 .last_mut()
                 .unwrap()
@@ -137,22 +232,42 @@ where
         let ui = imgui.frame(size_points, size_pixels, 0.016);
 
         // LOGIC
-        let keys_snapshot = engine
-            .event_pump
-            .keyboard_state()
-            .pressed_scancodes()
-            .collect();
-        let newly_pressed = &keys_snapshot - &keys_down;
-        keys_down.clone_from(&keys_snapshot);
+        engine.game_controllers.update(delta_time);
+        let keyboard = keyboard_tracker.snapshot(
+            &engine.event_pump,
+            engine.sdl2_context.keyboard().mod_state(),
+        );
+
+        let elapsed_time = fps_counter.elapsed();
+        let controllers = engine.game_controllers.snapshot();
+        let players = engine.game_controllers.player_instance_ids();
+
+        let mut combos = Vec::new();
+        for detector in &mut engine.combo_detectors {
+            detector.update(elapsed_time, &keyboard, &controllers, &players);
+            combos.extend(detector.matches());
+        }
 
         {
+            let mouse_state = MouseState::new(&engine.event_pump);
+            let mouse_position = to_logical((
+                mouse_state.x() as f32 / window_size.0 as f32,
+                mouse_state.y() as f32 / window_size.1 as f32,
+            ));
+            let input = FrameInput {
+                keyboard,
+                text_input: engine.text_input.take_state(),
+                mouse_state,
+                mouse_position,
+                touch: touch_tracker.snapshot(timer.ticks()),
+            };
             let context = EngineContext::new(
-                keys_snapshot,
-                newly_pressed,
+                input,
                 delta_time,
-                fps_counter.elapsed(),
-                MouseState::new(&engine.event_pump),
-                game_controller_manager.snapshot(),
+                elapsed_time,
+                controllers,
+                players,
+                combos,
             );
             let action = game_stack
                 .last_mut()
@@ -203,10 +318,23 @@ where
                     #[cfg(debug_assertions)]
                     engine.resources.inspect(&ui);
                 }
+                if engine.post_processing.inspect_window {
+                    engine.post_processing.inspect(&ui);
+                }
                 debug_stats.imgui_render_stats(&ui);
             }
 
             // RENDERING
+            let post_processing = engine.post_processing.is_active();
+            if post_processing {
+                engine
+                    .post_processing
+                    .begin(&mut engine.renderer)
+                    .map_err(err_msg)?;
+            }
+            if let Some(ref mut pixel_perfect) = engine.pixel_perfect {
+                pixel_perfect.begin(&mut engine.renderer).map_err(err_msg)?;
+            }
             engine.renderer.set_draw_color(engine.clear_color);
             engine.renderer.clear();
 
@@ -214,6 +342,22 @@ where
                 .last_mut()
                 .unwrap()
                 .render(&context, &mut engine, &ui);
+            engine
+                .draw_queue
+                .flush(&mut engine.renderer)
+                .map_err(err_msg)?;
+            if let Some(ref mut pixel_perfect) = engine.pixel_perfect {
+                pixel_perfect
+                    .present(&mut engine.renderer)
+                    .map_err(err_msg)?;
+            }
+            if post_processing {
+                let time = elapsed_time as f32 / 1000.0;
+                engine
+                    .post_processing
+                    .present(&mut engine.renderer, time)
+                    .map_err(err_msg)?;
+            }
         }
         imgui_renderer.render(ui).unwrap();
 
@@ -232,17 +376,26 @@ pub fn make_engine(
     texture_creator: TextureCreator<WindowContext>,
     ttf_context: Sdl2TtfContext,
     event_pump: EventPump,
+    text_input: TextInput,
 ) -> Result<Engine, Error> {
     let alto_context = super::alto_utils::initialize_context()?;
+    let game_controllers = GameControllerManager::new(&sdl2_context).map_err(err_msg)?;
 
     Ok(Engine {
         sdl2_context,
         renderer,
         ttf_context,
         event_pump,
+        text_input,
+        game_controllers,
+        settings_dir: None,
+        combo_detectors: Vec::new(),
         alto_context: alto_context.clone(),
         clear_color: Color::RGB(0, 0, 0),
         imgui_draw_cursor: false,
+        draw_queue: DrawQueue::new(),
+        pixel_perfect: None,
+        post_processing: PostProcessPipeline::new(),
         resources: Resources::new(texture_creator, alto_context.clone()),
     })
 }
@@ -0,0 +1,423 @@
+use sdl2::event::Event;
+
+use engine::combo::Direction;
+
+const TAP_TIME: u32 = 250;
+const TAP_DISTANCE: f32 = 10.0;
+const DOUBLE_TAP_TIME: u32 = 300;
+const DOUBLE_TAP_DISTANCE: f32 = 30.0;
+const LONG_PRESS_TIME: u32 = 500;
+const SWIPE_TIME: u32 = 500;
+const SWIPE_DISTANCE: f32 = 50.0;
+
+/// A finger touching the screen, positions are in logical coordinates.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TouchPoint {
+    pub id: i64,
+    pub x: f32,
+    pub y: f32,
+    pub pressure: f32,
+    pub start_x: f32,
+    pub start_y: f32,
+    /// SDL ticks when the finger touched the screen.
+    pub start_time: u32,
+    /// `true` for the first finger of a touch, the one SDL uses to emulate the mouse.
+    pub is_primary: bool,
+    long_pressed: bool,
+}
+
+impl TouchPoint {
+    fn distance_moved(&self) -> f32 {
+        distance((self.start_x, self.start_y), (self.x, self.y))
+    }
+}
+
+/// Gestures recognized from the touch points.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Gesture {
+    Tap {
+        x: f32,
+        y: f32,
+    },
+    DoubleTap {
+        x: f32,
+        y: f32,
+    },
+    LongPress {
+        x: f32,
+        y: f32,
+    },
+    /// Fast movement of a single finger, `direction` is one of Up, Down, Left or Right.
+    Swipe {
+        x: f32,
+        y: f32,
+        dx: f32,
+        dy: f32,
+        direction: Direction,
+    },
+    /// Two fingers moving apart (`scale` > 1) or closer (`scale` < 1) since the previous frame.
+    Pinch {
+        x: f32,
+        y: f32,
+        scale: f32,
+    },
+    /// Two fingers turning around their center since the previous frame, in radians.
+    Rotate {
+        x: f32,
+        y: f32,
+        angle: f32,
+    },
+}
+
+/// Touch points and gestures of a single frame.
+#[derive(Clone, Debug, Default)]
+pub struct TouchSnapshot {
+    points: Vec<TouchPoint>,
+    gestures: Vec<Gesture>,
+}
+
+impl TouchSnapshot {
+    pub fn points(&self) -> &[TouchPoint] {
+        &self.points
+    }
+    pub fn point(&self, id: i64) -> Option<&TouchPoint> {
+        self.points.iter().find(|point| point.id == id)
+    }
+    pub fn primary(&self) -> Option<&TouchPoint> {
+        self.points.iter().find(|point| point.is_primary)
+    }
+    pub fn gestures(&self) -> &[Gesture] {
+        &self.gestures
+    }
+}
+
+/// Follows the fingers from the SDL finger events and recognizes gestures.
+#[derive(Clone, Debug, Default)]
+pub struct TouchTracker {
+    points: Vec<TouchPoint>,
+    gestures: Vec<Gesture>,
+    // Position and time of the last tap, for double taps
+    last_tap: Option<(f32, f32, u32)>,
+    // Fingers used by the pinch / rotate gestures in the previous frame
+    last_pair: Option<(i64, i64, (f32, f32), (f32, f32))>,
+    // A finger that is part of a multi finger touch does not tap or swipe
+    multi_touch: bool,
+}
+
+impl TouchTracker {
+    /// `to_logical` converts the normalized positions of the events to logical coordinates.
+    pub fn process_event<F>(&mut self, event: &Event, to_logical: F)
+    where
+        F: Fn((f32, f32)) -> (f32, f32),
+    {
+        let position = |x, y| to_logical((x, y));
+        match *event {
+            Event::FingerDown {
+                timestamp,
+                finger_id,
+                x,
+                y,
+                pressure,
+                ..
+            } => {
+                let (x, y) = position(x, y);
+                self.finger_down(finger_id, x, y, pressure, timestamp);
+            }
+            Event::FingerMotion {
+                finger_id,
+                x,
+                y,
+                pressure,
+                ..
+            } => {
+                let (x, y) = position(x, y);
+                self.finger_motion(finger_id, x, y, pressure);
+            }
+            Event::FingerUp {
+                timestamp,
+                finger_id,
+                x,
+                y,
+                ..
+            } => {
+                let (x, y) = position(x, y);
+                self.finger_up(finger_id, x, y, timestamp);
+            }
+            _ => {}
+        }
+    }
+
+    pub fn finger_down(&mut self, id: i64, x: f32, y: f32, pressure: f32, time: u32) {
+        self.multi_touch = !self.points.is_empty();
+        let is_primary = self.points.is_empty();
+        self.points.push(TouchPoint {
+            id,
+            x,
+            y,
+            pressure,
+            start_x: x,
+            start_y: y,
+            start_time: time,
+            is_primary,
+            long_pressed: false,
+        });
+    }
+
+    pub fn finger_motion(&mut self, id: i64, x: f32, y: f32, pressure: f32) {
+        if let Some(point) = self.points.iter_mut().find(|point| point.id == id) {
+            point.x = x;
+            point.y = y;
+            point.pressure = pressure;
+        }
+    }
+
+    pub fn finger_up(&mut self, id: i64, x: f32, y: f32, time: u32) {
+        let index = match self.points.iter().position(|point| point.id == id) {
+            Some(index) => index,
+            None => return,
+        };
+        let mut point = self.points.remove(index);
+        point.x = x;
+        point.y = y;
+
+        if !self.multi_touch && !point.long_pressed {
+            self.single_finger_gesture(&point, time);
+        }
+        if self.points.is_empty() {
+            self.multi_touch = false;
+        }
+    }
+
+    fn single_finger_gesture(&mut self, point: &TouchPoint, time: u32) {
+        let duration = time.wrapping_sub(point.start_time);
+        let (dx, dy) = (point.x - point.start_x, point.y - point.start_y);
+
+        if duration <= TAP_TIME && point.distance_moved() <= TAP_DISTANCE {
+            let (x, y) = (point.x, point.y);
+            self.gestures.push(Gesture::Tap { x, y });
+
+            let double_tap = self.last_tap.map_or(false, |(last_x, last_y, last_time)| {
+                time.wrapping_sub(last_time) <= DOUBLE_TAP_TIME
+                    && distance((last_x, last_y), (x, y)) <= DOUBLE_TAP_DISTANCE
+            });
+            if double_tap {
+                self.gestures.push(Gesture::DoubleTap { x, y });
+                self.last_tap = None;
+            } else {
+                self.last_tap = Some((x, y, time));
+            }
+        } else if duration <= SWIPE_TIME && point.distance_moved() >= SWIPE_DISTANCE {
+            let direction = if dx.abs() > dy.abs() {
+                if dx > 0.0 {
+                    Direction::Right
+                } else {
+                    Direction::Left
+                }
+            } else if dy > 0.0 {
+                Direction::Down
+            } else {
+                Direction::Up
+            };
+            self.gestures.push(Gesture::Swipe {
+                x: point.start_x,
+                y: point.start_y,
+                dx,
+                dy,
+                direction,
+            });
+        }
+    }
+
+    /// Recognize the gestures that depend on time and finger pairs, then return
+    /// the state of this frame. `time` is in SDL ticks.
+    pub fn snapshot(&mut self, time: u32) -> TouchSnapshot {
+        for point in &mut self.points {
+            if !point.long_pressed
+                && time.wrapping_sub(point.start_time) >= LONG_PRESS_TIME
+                && point.distance_moved() <= TAP_DISTANCE
+                && !self.multi_touch
+            {
+                point.long_pressed = true;
+                self.gestures.push(Gesture::LongPress {
+                    x: point.x,
+                    y: point.y,
+                });
+            }
+        }
+        self.two_fingers_gestures();
+
+        TouchSnapshot {
+            points: self.points.clone(),
+            gestures: self.gestures.drain(..).collect(),
+        }
+    }
+
+    fn two_fingers_gestures(&mut self) {
+        if self.points.len() < 2 {
+            self.last_pair = None;
+            return;
+        }
+        let (first, second) = (self.points[0], self.points[1]);
+        let positions = ((first.x, first.y), (second.x, second.y));
+
+        if let Some((first_id, second_id, last_first, last_second)) = self.last_pair {
+            if first_id == first.id && second_id == second.id {
+                let (x, y) = ((first.x + second.x) / 2.0, (first.y + second.y) / 2.0);
+                let last_distance = distance(last_first, last_second);
+                let scale = distance(positions.0, positions.1) / last_distance.max(1.0);
+                if (scale - 1.0).abs() > 0.001 {
+                    self.gestures.push(Gesture::Pinch { x, y, scale });
+                }
+
+                let angle = normalize_angle(
+                    angle(positions.0, positions.1) - angle(last_first, last_second),
+                );
+                if angle.abs() > 0.001 {
+                    self.gestures.push(Gesture::Rotate { x, y, angle });
+                }
+            }
+        }
+        self.last_pair = Some((first.id, second.id, positions.0, positions.1));
+    }
+}
+
+/// Convert a normalized touch position to the logical coordinates of the renderer.
+pub fn to_logical(
+    position: (f32, f32),
+    window_size: (u32, u32),
+    logical_size: (u32, u32),
+) -> (f32, f32) {
+    let (window_width, window_height) = (window_size.0 as f32, window_size.1 as f32);
+    let (x, y) = (position.0 * window_width, position.1 * window_height);
+    if logical_size.0 == 0 || logical_size.1 == 0 {
+        return (x, y);
+    }
+
+    // Same letterboxing as SDL_RenderSetLogicalSize
+    let (logical_width, logical_height) = (logical_size.0 as f32, logical_size.1 as f32);
+    let scale = (window_width / logical_width).min(window_height / logical_height);
+    let offset_x = (window_width - logical_width * scale) / 2.0;
+    let offset_y = (window_height - logical_height * scale) / 2.0;
+    ((x - offset_x) / scale, (y - offset_y) / scale)
+}
+
+/// `which` of the mouse events emulated from touches, `SDL_TOUCH_MOUSEID`.
+pub const TOUCH_MOUSE_ID: u32 = ::std::u32::MAX;
+
+/// `true` for a mouse event emulated by SDL from a touch.
+pub fn is_touch_mouse_event(event: &Event) -> bool {
+    match *event {
+        Event::MouseMotion { which, .. }
+        | Event::MouseButtonDown { which, .. }
+        | Event::MouseButtonUp { which, .. }
+        | Event::MouseWheel { which, .. } => which == TOUCH_MOUSE_ID,
+        _ => false,
+    }
+}
+
+fn distance(a: (f32, f32), b: (f32, f32)) -> f32 {
+    ((b.0 - a.0).powi(2) + (b.1 - a.1).powi(2)).sqrt()
+}
+
+fn angle(a: (f32, f32), b: (f32, f32)) -> f32 {
+    (b.1 - a.1).atan2(b.0 - a.0)
+}
+
+fn normalize_angle(angle: f32) -> f32 {
+    use std::f32::consts::PI;
+    if angle > PI {
+        angle - 2.0 * PI
+    } else if angle < -PI {
+        angle + 2.0 * PI
+    } else {
+        angle
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_double_tap() {
+        let mut tracker = TouchTracker::default();
+        tracker.finger_down(1, 100.0, 100.0, 1.0, 0);
+        tracker.finger_up(1, 102.0, 100.0, 100);
+        tracker.finger_down(1, 105.0, 100.0, 1.0, 200);
+        tracker.finger_up(1, 105.0, 100.0, 250);
+
+        let gestures = tracker.snapshot(300).gestures().to_vec();
+        assert_eq!(gestures.len(), 3);
+        assert_eq!(gestures[2], Gesture::DoubleTap { x: 105.0, y: 100.0 });
+    }
+
+    #[test]
+    fn test_swipe_and_long_press() {
+        let mut tracker = TouchTracker::default();
+        tracker.finger_down(1, 100.0, 100.0, 1.0, 0);
+        tracker.finger_up(1, 100.0, 20.0, 200);
+        match tracker.snapshot(200).gestures() {
+            [Gesture::Swipe { direction, .. }] => assert_eq!(*direction, Direction::Up),
+            gestures => panic!("unexpected gestures {:?}", gestures),
+        }
+
+        tracker.finger_down(2, 50.0, 50.0, 1.0, 1000);
+        assert!(tracker.snapshot(1200).gestures().is_empty());
+        assert_eq!(tracker.snapshot(1600).gestures().len(), 1);
+        tracker.finger_up(2, 50.0, 50.0, 1700);
+        assert!(tracker.snapshot(1700).gestures().is_empty());
+    }
+
+    #[test]
+    fn test_pinch() {
+        let mut tracker = TouchTracker::default();
+        tracker.finger_down(1, 100.0, 100.0, 1.0, 0);
+        tracker.finger_down(2, 200.0, 100.0, 1.0, 0);
+        tracker.snapshot(10);
+
+        tracker.finger_motion(2, 300.0, 100.0, 1.0);
+        let gestures = tracker.snapshot(20).gestures().to_vec();
+        assert_eq!(
+            gestures,
+            vec![Gesture::Pinch {
+                x: 200.0,
+                y: 100.0,
+                scale: 2.0,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_touch_mouse_event() {
+        use sdl2::mouse::{MouseButton, MouseState};
+        let motion = |which| Event::MouseMotion {
+            timestamp: 0,
+            window_id: 1,
+            which,
+            mousestate: MouseState::from_sdl_state(0),
+            x: 10,
+            y: 20,
+            xrel: 1,
+            yrel: 0,
+        };
+        assert!(is_touch_mouse_event(&motion(TOUCH_MOUSE_ID)));
+        assert!(!is_touch_mouse_event(&motion(0)));
+        assert!(is_touch_mouse_event(&Event::MouseButtonDown {
+            timestamp: 0,
+            window_id: 1,
+            which: TOUCH_MOUSE_ID,
+            mouse_btn: MouseButton::Left,
+            clicks: 1,
+            x: 10,
+            y: 20,
+        }));
+        assert!(!is_touch_mouse_event(&Event::Quit { timestamp: 0 }));
+    }
+
+    #[test]
+    fn test_logical_coordinates() {
+        // 800x600 window showing a 400x400 logical size, letterboxed horizontally
+        let position = to_logical((0.5, 1.0), (800, 600), (400, 400));
+        assert_eq!(position, (200.0, 400.0));
+    }
+}
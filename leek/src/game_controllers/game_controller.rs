@@ -9,11 +9,6 @@ use sdl2::sys::SDL_GameControllerGetAxis;
 use sdl2::sys::SDL_GameControllerGetButton;
 use sdl2::sys::SDL_GameControllerGetJoystick;
 use sdl2::sys::SDL_GameControllerOpen;
-use sdl2::sys::SDL_Haptic;
-use sdl2::sys::SDL_HapticClose;
-use sdl2::sys::SDL_HapticOpenFromJoystick;
-use sdl2::sys::SDL_HapticRumbleInit;
-use sdl2::sys::SDL_HapticRumblePlay;
 use sdl2::sys::SDL_IsGameController;
 use sdl2::sys::SDL_Joystick;
 use sdl2::sys::SDL_JoystickClose;
@@ -25,12 +20,19 @@ use sdl2::sys::SDL_bool;
 
 use sdl2::controller::{Axis, Button};
 
+use super::haptic::{rumble_controller, rumble_triggers, Haptic, HapticEffect, HapticSettings};
+use super::mapping::{guid_to_string, sdl_string, take_sdl_string};
+use super::state::{ControllerState, Deadzones, ALL_BUTTONS};
+
 #[derive(Clone, Debug)]
 pub struct GameController {
     raw_game_controller: *mut SDL_GameController,
     raw_joystick: *mut SDL_Joystick,
     joystick_id: u32,
     haptic: Option<Haptic>,
+    deadzones: Deadzones,
+    state: ControllerState,
+    haptic_settings: HapticSettings,
 }
 
 impl GameController {
@@ -55,6 +57,9 @@ impl GameController {
                         raw_joystick: joystick,
                         joystick_id: SDL_JoystickInstanceID(joystick) as u32, // TODO check cast
                         haptic,
+                        deadzones: Deadzones::default(),
+                        state: ControllerState::default(),
+                        haptic_settings: HapticSettings::default(),
                     })
                 }
             } else {
@@ -67,12 +72,114 @@ impl GameController {
         self.joystick_id
     }
 
+    /// Name of the controller, as reported by its mapping.
+    pub fn name(&self) -> String {
+        unsafe { sdl_string(sys::SDL_GameControllerName(self.raw_game_controller)) }
+    }
+
+    /// GUID of the underlying joystick, as used in gamecontrollerdb.txt.
+    pub fn guid(&self) -> String {
+        unsafe { guid_to_string(sys::SDL_JoystickGetGUID(self.raw_joystick)) }
+    }
+
+    /// The mapping used by SDL for this controller.
+    pub fn mapping(&self) -> Option<String> {
+        unsafe { take_sdl_string(sys::SDL_GameControllerMapping(self.raw_game_controller)) }
+    }
+
     pub fn play_rumble(&self, strenght: f32, duration: u32) {
         if let Some(ref haptic) = self.haptic {
-            haptic.play(strenght, duration)
+            haptic.play(strenght * self.haptic_settings.scale(), duration)
+        }
+    }
+
+    pub fn set_haptic_settings(&mut self, settings: HapticSettings) {
+        self.haptic_settings = settings;
+        if !settings.enabled {
+            self.stop_haptics();
+        }
+    }
+
+    /// Play a rumble using the two motors of the controller, falls back to
+    /// the haptic device when the controller rumble is not supported.
+    pub fn rumble(&self, low_frequency: f32, high_frequency: f32, duration: u32) {
+        let scale = self.haptic_settings.scale();
+        let (low_frequency, high_frequency) = (low_frequency * scale, high_frequency * scale);
+
+        if !rumble_controller(
+            self.raw_game_controller,
+            low_frequency,
+            high_frequency,
+            duration,
+        ) {
+            if let Some(ref haptic) = self.haptic {
+                haptic.play(low_frequency.max(high_frequency), duration)
+            }
+        }
+    }
+
+    /// Play `effect`, replacing the effect currently running.
+    /// Returns `false` if the controller does not support it.
+    pub fn play_effect(&self, effect: &HapticEffect) -> bool {
+        let scale = self.haptic_settings.scale();
+        match *effect {
+            HapticEffect::Rumble {
+                low_frequency,
+                high_frequency,
+                duration,
+            } => {
+                self.rumble(low_frequency, high_frequency, duration);
+                true
+            }
+            HapticEffect::TriggerRumble {
+                left,
+                right,
+                duration,
+            } => rumble_triggers(
+                self.raw_game_controller,
+                left * scale,
+                right * scale,
+                duration,
+            ),
+            _ => match self.haptic {
+                Some(ref haptic) => haptic.play_effect(effect, scale),
+                None => false,
+            },
+        }
+    }
+
+    pub fn stop_haptics(&self) {
+        rumble_controller(self.raw_game_controller, 0.0, 0.0, 0);
+        if let Some(ref haptic) = self.haptic {
+            haptic.stop();
         }
     }
 
+    pub fn deadzones(&self) -> &Deadzones {
+        &self.deadzones
+    }
+
+    pub fn set_deadzones(&mut self, deadzones: Deadzones) {
+        self.deadzones = deadzones;
+    }
+
+    /// State of the controller in the current frame, with deadzones applied.
+    pub fn state(&self) -> &ControllerState {
+        &self.state
+    }
+
+    /// Read the current buttons and axes, to be called once per frame.
+    pub fn update(&mut self) {
+        let buttons_down = ALL_BUTTONS
+            .iter()
+            .cloned()
+            .filter(|button| self.button(*button))
+            .collect();
+        self.state = self
+            .state
+            .next(buttons_down, |axis| self.axis(axis), &self.deadzones);
+    }
+
     /// Get the position of the given `axis`
     pub fn axis(&self, axis: Axis) -> i16 {
         let raw_axis = match axis {
@@ -121,38 +228,13 @@ unsafe impl Send for GameController {
 unsafe impl Sync for GameController {
     // TODO make sure this does not cause problems
 }
-#[derive(Clone, Debug)]
-struct Haptic {
-    raw: *mut SDL_Haptic,
-}
-
-impl Haptic {
-    fn from_joystick(joystick: *mut SDL_Joystick) -> Haptic {
-        unsafe {
-            //            use sdl2::sys::haptic::*;
-
-            let haptic = SDL_HapticOpenFromJoystick(joystick);
-            SDL_HapticRumbleInit(haptic);
-
-            Haptic { raw: haptic }
-        }
-    }
-
-    fn play(&self, strenght: f32, duration: u32) {
-        unsafe {
-            //            use sdl2::sys::haptic::*;
-            SDL_HapticRumblePlay(self.raw, strenght, duration)
-        };
-    }
-}
-
 pub fn close_controller(controller: GameController) {
     unsafe {
         //        use sdl2::sys::joystick::*;
         //        use sdl2::sys::haptic::*;
 
         if let Some(haptic) = controller.haptic {
-            SDL_HapticClose(haptic.raw);
+            haptic.close();
         }
         SDL_GameControllerClose(controller.raw_game_controller);
         SDL_JoystickClose(controller.raw_joystick)
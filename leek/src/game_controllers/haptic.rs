@@ -0,0 +1,432 @@
+use sdl2::sys;
+use sdl2::sys::{SDL_GameController, SDL_Haptic, SDL_HapticEffect, SDL_Joystick};
+
+use std::collections::VecDeque;
+use std::mem;
+use std::os::raw::c_int;
+use std::sync::atomic::{AtomicIsize, Ordering};
+use std::sync::{Arc, Once};
+
+use math::lerp;
+use sdl2_utils::load_sdl_function;
+
+type RumbleFunction = unsafe extern "C" fn(*mut SDL_GameController, u16, u16, u32) -> c_int;
+
+/// `SDL_GameControllerRumble` (SDL 2.0.9) and `SDL_GameControllerRumbleTriggers`
+/// (SDL 2.0.14), missing from the sdl2 bindings and looked up at runtime.
+struct RumbleFunctions {
+    rumble: Option<RumbleFunction>,
+    rumble_triggers: Option<RumbleFunction>,
+}
+
+fn rumble_functions() -> &'static RumbleFunctions {
+    static INIT: Once = Once::new();
+    static mut FUNCTIONS: RumbleFunctions = RumbleFunctions {
+        rumble: None,
+        rumble_triggers: None,
+    };
+    unsafe {
+        INIT.call_once(|| {
+            let load = |name, since| {
+                load_sdl_function(name, since).map(|f| mem::transmute::<_, RumbleFunction>(f))
+            };
+            FUNCTIONS.rumble = load("SDL_GameControllerRumble", (2, 0, 9));
+            FUNCTIONS.rumble_triggers = load("SDL_GameControllerRumbleTriggers", (2, 0, 14));
+        });
+        &FUNCTIONS
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Waveform {
+    Sine,
+    Triangle,
+    SawtoothUp,
+    SawtoothDown,
+}
+
+/// A force feedback effect, strengths are in the 0..1 range and durations in milliseconds.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HapticEffect {
+    Constant {
+        level: f32,
+        duration: u32,
+    },
+    Periodic {
+        waveform: Waveform,
+        period: u16,
+        magnitude: f32,
+        duration: u32,
+    },
+    Ramp {
+        start: f32,
+        end: f32,
+        duration: u32,
+    },
+    /// Left (low frequency) and right (high frequency) motors of the controller.
+    Rumble {
+        low_frequency: f32,
+        high_frequency: f32,
+        duration: u32,
+    },
+    /// Motors inside the triggers (ex: Xbox One controllers).
+    TriggerRumble {
+        left: f32,
+        right: f32,
+        duration: u32,
+    },
+}
+
+impl HapticEffect {
+    fn to_sdl(&self, scale: f32) -> Option<SDL_HapticEffect> {
+        let level = |value: f32| (value * scale).max(-1.0).min(1.0) * 32_767.0;
+
+        unsafe {
+            let mut effect: SDL_HapticEffect = mem::zeroed();
+            match *self {
+                HapticEffect::Constant {
+                    level: value,
+                    duration,
+                } => {
+                    effect.type_ = sys::SDL_HAPTIC_CONSTANT as u16;
+                    effect.constant.direction.type_ = sys::SDL_HAPTIC_POLAR as u8;
+                    effect.constant.length = duration;
+                    effect.constant.level = level(value) as i16;
+                }
+                HapticEffect::Periodic {
+                    waveform,
+                    period,
+                    magnitude,
+                    duration,
+                } => {
+                    effect.type_ = match waveform {
+                        Waveform::Sine => sys::SDL_HAPTIC_SINE,
+                        Waveform::Triangle => sys::SDL_HAPTIC_TRIANGLE,
+                        Waveform::SawtoothUp => sys::SDL_HAPTIC_SAWTOOTHUP,
+                        Waveform::SawtoothDown => sys::SDL_HAPTIC_SAWTOOTHDOWN,
+                    } as u16;
+                    effect.periodic.direction.type_ = sys::SDL_HAPTIC_POLAR as u8;
+                    effect.periodic.length = duration;
+                    effect.periodic.period = period;
+                    effect.periodic.magnitude = level(magnitude) as i16;
+                }
+                HapticEffect::Ramp {
+                    start,
+                    end,
+                    duration,
+                } => {
+                    effect.type_ = sys::SDL_HAPTIC_RAMP as u16;
+                    effect.ramp.direction.type_ = sys::SDL_HAPTIC_POLAR as u8;
+                    effect.ramp.length = duration;
+                    effect.ramp.start = level(start) as i16;
+                    effect.ramp.end = level(end) as i16;
+                }
+                HapticEffect::Rumble { .. } | HapticEffect::TriggerRumble { .. } => return None,
+            }
+            Some(effect)
+        }
+    }
+}
+
+/// Global haptic settings (ex: to reduce or disable vibrations for accessibility).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HapticSettings {
+    pub enabled: bool,
+    /// Multiplier applied to every effect, in the 0..1 range.
+    pub intensity: f32,
+}
+
+impl Default for HapticSettings {
+    fn default() -> Self {
+        HapticSettings {
+            enabled: true,
+            intensity: 1.0,
+        }
+    }
+}
+
+impl HapticSettings {
+    pub fn scale(&self) -> f32 {
+        if self.enabled {
+            self.intensity.max(0.0).min(1.0)
+        } else {
+            0.0
+        }
+    }
+}
+
+fn motor_speed(value: f32) -> u16 {
+    (value.max(0.0).min(1.0) * 65_535.0) as u16
+}
+
+/// Play a rumble on the two motors of a controller, returns `false` if not supported.
+pub fn rumble_controller(
+    controller: *mut SDL_GameController,
+    low_frequency: f32,
+    high_frequency: f32,
+    duration: u32,
+) -> bool {
+    match rumble_functions().rumble {
+        Some(rumble) => unsafe {
+            rumble(
+                controller,
+                motor_speed(low_frequency),
+                motor_speed(high_frequency),
+                duration,
+            ) == 0
+        },
+        None => false,
+    }
+}
+
+/// Play a rumble on the trigger motors of a controller, returns `false` if not supported.
+pub fn rumble_triggers(
+    controller: *mut SDL_GameController,
+    left: f32,
+    right: f32,
+    duration: u32,
+) -> bool {
+    match rumble_functions().rumble_triggers {
+        Some(rumble_triggers) => unsafe {
+            rumble_triggers(controller, motor_speed(left), motor_speed(right), duration) == 0
+        },
+        None => false,
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Haptic {
+    raw: *mut SDL_Haptic,
+    // Shared between the clones of the controller, -1 when no effect is loaded
+    effect_id: Arc<AtomicIsize>,
+}
+
+impl Haptic {
+    pub fn from_joystick(joystick: *mut SDL_Joystick) -> Haptic {
+        unsafe {
+            let haptic = sys::SDL_HapticOpenFromJoystick(joystick);
+            sys::SDL_HapticRumbleInit(haptic);
+
+            Haptic {
+                raw: haptic,
+                effect_id: Arc::new(AtomicIsize::new(-1)),
+            }
+        }
+    }
+
+    pub fn play(&self, strenght: f32, duration: u32) {
+        unsafe {
+            sys::SDL_HapticRumblePlay(self.raw, strenght, duration);
+        }
+    }
+
+    /// Replace the running effect with `effect`, returns `false` if not supported.
+    pub fn play_effect(&self, effect: &HapticEffect, scale: f32) -> bool {
+        self.destroy_effect();
+
+        let mut raw_effect = match effect.to_sdl(scale) {
+            Some(raw_effect) => raw_effect,
+            None => return false,
+        };
+        unsafe {
+            if sys::SDL_HapticEffectSupported(self.raw, &mut raw_effect) != 1 {
+                return false;
+            }
+            let id = sys::SDL_HapticNewEffect(self.raw, &mut raw_effect);
+            if id < 0 {
+                return false;
+            }
+            sys::SDL_HapticRunEffect(self.raw, id, 1);
+            self.effect_id.store(id as isize, Ordering::SeqCst);
+        }
+        true
+    }
+
+    pub fn stop(&self) {
+        unsafe {
+            sys::SDL_HapticStopAll(self.raw);
+        }
+        self.destroy_effect();
+    }
+
+    fn destroy_effect(&self) {
+        let previous = self.effect_id.swap(-1, Ordering::SeqCst);
+        if previous >= 0 {
+            unsafe { sys::SDL_HapticDestroyEffect(self.raw, previous as c_int) };
+        }
+    }
+
+    pub fn close(&self) {
+        self.destroy_effect();
+        unsafe {
+            sys::SDL_HapticClose(self.raw);
+        }
+    }
+}
+
+/// A single step of a rumble pattern.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RumbleStep {
+    pub low_frequency: f32,
+    pub high_frequency: f32,
+    pub duration: u32,
+}
+
+/// A sequence of rumbles, played one after the other.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RumblePattern {
+    steps: Vec<RumbleStep>,
+}
+
+impl RumblePattern {
+    pub fn new() -> RumblePattern {
+        RumblePattern::default()
+    }
+
+    pub fn then(mut self, low_frequency: f32, high_frequency: f32, duration: u32) -> Self {
+        self.steps.push(RumbleStep {
+            low_frequency,
+            high_frequency,
+            duration,
+        });
+        self
+    }
+
+    pub fn pause(self, duration: u32) -> Self {
+        self.then(0.0, 0.0, duration)
+    }
+
+    /// Strong hit that fades out in `steps` steps, like an explosion.
+    /// The high frequency motor fades out faster than the low frequency one.
+    pub fn falloff(strength: f32, duration: u32, steps: u32) -> RumblePattern {
+        let steps = steps.max(1);
+        let step_duration = duration / steps;
+
+        (0..steps).fold(RumblePattern::new(), |pattern, step| {
+            let value = lerp(strength, 0.0, step as f32 / steps as f32);
+            pattern.then(value, value * value, step_duration)
+        })
+    }
+
+    /// `count` pulses of `on` milliseconds separated by `off` milliseconds.
+    pub fn pulses(strength: f32, on: u32, off: u32, count: u32) -> RumblePattern {
+        (0..count).fold(RumblePattern::new(), |pattern, pulse| {
+            let pattern = pattern.then(strength, strength, on);
+            if pulse + 1 < count {
+                pattern.pause(off)
+            } else {
+                pattern
+            }
+        })
+    }
+
+    pub fn steps(&self) -> &[RumbleStep] {
+        &self.steps
+    }
+
+    pub fn duration(&self) -> u32 {
+        self.steps.iter().map(|step| step.duration).sum()
+    }
+}
+
+/// Plays the rumble patterns queued on a controller.
+#[derive(Clone, Debug, Default)]
+pub struct PatternPlayer {
+    steps: VecDeque<RumbleStep>,
+    remaining: u32,
+}
+
+impl PatternPlayer {
+    pub fn queue(&mut self, pattern: &RumblePattern) {
+        self.steps.extend(pattern.steps.iter().cloned());
+    }
+
+    /// Advance by `delta_time` milliseconds, returns the step that starts now.
+    ///
+    /// The time past the end of a step is taken from the next ones, the steps that fit
+    /// entirely in `delta_time` are skipped.
+    pub fn advance(&mut self, delta_time: u32) -> Option<RumbleStep> {
+        if self.remaining > delta_time {
+            self.remaining -= delta_time;
+            return None;
+        }
+        let mut overshoot = delta_time - self.remaining;
+        self.remaining = 0;
+
+        while let Some(step) = self.steps.pop_front() {
+            if step.duration > overshoot {
+                self.remaining = step.duration - overshoot;
+                return Some(step);
+            }
+            overshoot -= step.duration;
+        }
+        None
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.steps.is_empty() && self.remaining == 0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_falloff() {
+        let pattern = RumblePattern::falloff(1.0, 400, 4);
+        assert_eq!(pattern.steps().len(), 4);
+        assert_eq!(pattern.duration(), 400);
+        assert_eq!(pattern.steps()[0].low_frequency, 1.0);
+        assert_eq!(pattern.steps()[2].low_frequency, 0.5);
+        assert_eq!(pattern.steps()[2].high_frequency, 0.25);
+    }
+
+    #[test]
+    fn test_pattern_player() {
+        let mut player = PatternPlayer::default();
+        player.queue(&RumblePattern::pulses(1.0, 100, 50, 2));
+
+        assert_eq!(player.advance(0).map(|step| step.low_frequency), Some(1.0));
+        assert_eq!(player.advance(60), None);
+        assert_eq!(player.advance(60).map(|step| step.low_frequency), Some(0.0));
+        assert_eq!(player.advance(50).map(|step| step.duration), Some(100));
+        assert!(!player.is_finished());
+        assert_eq!(player.advance(100), None);
+        assert!(player.is_finished());
+    }
+
+    #[test]
+    fn test_pattern_player_carry_over() {
+        let mut player = PatternPlayer::default();
+        player.queue(&RumblePattern::pulses(1.0, 100, 50, 2));
+
+        assert!(player.advance(0).is_some());
+        // 16 ms frames end the first pulse 12 ms late, the pause is shortened to match
+        for _ in 0..6 {
+            assert_eq!(player.advance(16), None);
+        }
+        assert_eq!(player.advance(16).map(|step| step.low_frequency), Some(0.0));
+        assert_eq!(player.advance(37), None);
+        assert_eq!(player.advance(1).map(|step| step.low_frequency), Some(1.0));
+        // A long frame skips the steps that ended during it
+        player.queue(&RumblePattern::pulses(0.5, 100, 50, 2));
+        assert_eq!(
+            player.advance(230).map(|step| step.low_frequency),
+            Some(0.0)
+        );
+        assert_eq!(player.advance(19), None);
+        assert_eq!(player.advance(1).map(|step| step.low_frequency), Some(0.5));
+        assert_eq!(player.advance(100), None);
+        assert!(player.is_finished());
+    }
+
+    #[test]
+    fn test_settings_scale() {
+        let mut settings = HapticSettings::default();
+        settings.intensity = 0.5;
+        assert_eq!(settings.scale(), 0.5);
+        settings.enabled = false;
+        assert_eq!(settings.scale(), 0.0);
+    }
+}
@@ -0,0 +1,103 @@
+use sdl2::controller::MappingStatus;
+use sdl2::joystick::Guid;
+use sdl2::sys;
+use sdl2::sys::SDL_JoystickGUID;
+use sdl2::{GameControllerSubsystem, JoystickSubsystem};
+
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::path::Path;
+
+/// Name of the mapping files (in the gamecontrollerdb.txt format) loaded at startup.
+pub const MAPPINGS_FILE_NAME: &str = "gamecontrollerdb.txt";
+
+/// A joystick connected to the system, even if SDL does not know how to map it
+/// to a game controller.
+#[derive(Clone, Debug)]
+pub struct JoystickInfo {
+    pub joystick_index: u32,
+    pub instance_id: u32,
+    pub name: String,
+    pub guid: String,
+    pub is_game_controller: bool,
+    pub mapping: Option<String>,
+}
+
+/// Load all the mappings contained in a gamecontrollerdb-style file.
+/// Returns the number of mappings added.
+pub fn load_mappings_from_file(
+    subsystem: &GameControllerSubsystem,
+    path: &Path,
+) -> Result<u32, String> {
+    let added = subsystem.load_mappings(path).map_err(|e| e.to_string())?;
+    Ok(added as u32)
+}
+
+/// Add a single mapping, in the same format used by gamecontrollerdb.txt.
+pub fn add_mapping(
+    subsystem: &GameControllerSubsystem,
+    mapping: &str,
+) -> Result<MappingStatus, String> {
+    subsystem.add_mapping(mapping).map_err(|e| e.to_string())
+}
+
+/// Returns the mapping for the given GUID, if SDL knows one.
+pub fn mapping_for_guid(subsystem: &GameControllerSubsystem, guid: &str) -> Option<String> {
+    let guid = Guid::from_string(guid).ok()?;
+    subsystem.mapping_for_guid(guid).ok()
+}
+
+/// List every joystick currently connected.
+pub fn connected_joysticks(
+    subsystem: &GameControllerSubsystem,
+    joysticks: &JoystickSubsystem,
+) -> Vec<JoystickInfo> {
+    let num_joysticks = joysticks.num_joysticks().unwrap_or(0);
+
+    (0..num_joysticks)
+        .map(|index| {
+            let guid = joysticks
+                .device_guid(index)
+                .map(|guid| guid.string())
+                .unwrap_or_default();
+            JoystickInfo {
+                joystick_index: index,
+                // Not wrapped by sdl2 0.31
+                instance_id: unsafe { sys::SDL_JoystickGetDeviceInstanceID(index as i32) as u32 },
+                name: joysticks.name_for_index(index).unwrap_or_default(),
+                is_game_controller: subsystem.is_game_controller(index),
+                mapping: mapping_for_guid(subsystem, &guid),
+                guid,
+            }
+        })
+        .collect()
+}
+
+/// GUID of a raw joystick, `sdl2::joystick::Guid` cannot be built from one.
+pub fn guid_to_string(guid: SDL_JoystickGUID) -> String {
+    let mut buffer = [0 as c_char; 33];
+    unsafe {
+        sys::SDL_JoystickGetGUIDString(guid, buffer.as_mut_ptr(), buffer.len() as i32);
+        sdl_string(buffer.as_ptr())
+    }
+}
+
+/// Copy a string owned by SDL.
+pub unsafe fn sdl_string(raw: *const c_char) -> String {
+    if raw.is_null() {
+        String::new()
+    } else {
+        CStr::from_ptr(raw).to_string_lossy().into_owned()
+    }
+}
+
+/// Copy a string allocated by SDL and free it.
+pub unsafe fn take_sdl_string(raw: *mut c_char) -> Option<String> {
+    if raw.is_null() {
+        None
+    } else {
+        let string = sdl_string(raw);
+        sys::SDL_free(raw as *mut _);
+        Some(string)
+    }
+}
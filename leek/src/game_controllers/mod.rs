@@ -1,59 +1,373 @@
 mod game_controller;
+mod haptic;
+mod mapping;
+mod slots;
+mod state;
+mod virtual_gamepad;
 pub use self::game_controller::GameController;
+use self::haptic::PatternPlayer;
+pub use self::haptic::{HapticEffect, HapticSettings, RumblePattern, RumbleStep, Waveform};
+pub use self::mapping::JoystickInfo;
+pub use self::slots::{JoinPolicy, LeavePolicy, PlayerSlots, MAX_PLAYERS};
+pub use self::state::{ControllerState, Deadzones};
+pub use self::virtual_gamepad::{OnScreenButton, OnScreenGamepad, OnScreenStick, VirtualGamepad};
+pub use sdl2::controller::MappingStatus;
+use sdl2::controller::{Axis, Button};
+use sdl2::{GameControllerSubsystem, JoystickSubsystem, Sdl};
 use std::collections::HashMap;
+use std::path::Path;
 
-#[derive(Debug, Default)]
+/// Connection changes, forwarded to the active scene by the engine.
+#[derive(Clone, Copy, Debug)]
+pub enum ControllerEvent {
+    Connected {
+        instance_id: u32,
+        player: Option<usize>,
+    },
+    Disconnected {
+        instance_id: u32,
+        player: Option<usize>,
+    },
+}
+
+#[derive(Debug)]
 pub struct GameControllerManager {
+    subsystem: GameControllerSubsystem,
+    joysticks: JoystickSubsystem,
     controllers: HashMap<u32, GameController>,
+    default_deadzones: Deadzones,
+    players: PlayerSlots,
+    events: Vec<ControllerEvent>,
+    haptic_settings: HapticSettings,
+    patterns: HashMap<u32, PatternPlayer>,
+    virtual_gamepads: HashMap<u32, VirtualGamepad>,
 }
 
 impl GameControllerManager {
-    pub fn new() -> GameControllerManager {
+    pub fn new(sdl: &Sdl) -> Result<GameControllerManager, String> {
         unsafe {
             use sdl2::sys as ll;
-            ll::SDL_InitSubSystem(ll::SDL_INIT_GAMECONTROLLER | ll::SDL_INIT_HAPTIC);
+            ll::SDL_InitSubSystem(ll::SDL_INIT_HAPTIC);
         };
-        GameControllerManager {
-            controllers: GameControllerManager::load_all_connected_devices(),
-        }
+        let mut manager = GameControllerManager {
+            subsystem: sdl.game_controller()?,
+            joysticks: sdl.joystick()?,
+            controllers: HashMap::new(),
+            default_deadzones: Deadzones::default(),
+            players: PlayerSlots::default(),
+            events: Vec::new(),
+            haptic_settings: HapticSettings::default(),
+            patterns: HashMap::new(),
+            virtual_gamepads: HashMap::new(),
+        };
+        manager.open_new_controllers();
+
+        #[cfg(debug_assertions)]
+        println!("map {:#?}", manager.controllers);
+        Ok(manager)
     }
 
-    fn load_all_connected_devices() -> HashMap<u32, GameController> {
-        unsafe {
-            use sdl2::sys::SDL_NumJoysticks;
+    /// Open the controller at the given joystick index (`which` of ControllerDeviceAdded).
+    pub fn added_controller(&mut self, which: u32) {
+        let instance_id = unsafe { sdl2::sys::SDL_JoystickGetDeviceInstanceID(which as i32) };
+        if self.controllers.contains_key(&(instance_id as u32)) {
+            // SDL also sends an added event for the devices opened at startup
+            return;
+        }
+
+        println!("Added controller {:?}", which);
+        if let Some(mut controller) = GameController::from_joystick_index(which) {
+            let instance_id = controller.instance_id();
+            controller.set_deadzones(self.default_deadzones);
+            controller.set_haptic_settings(self.haptic_settings);
 
-            // TODO check conversion
-            let num_joysticks: u32 = SDL_NumJoysticks() as u32;
+            let player = self.players.connected(instance_id, &controller.guid());
+            self.events.push(ControllerEvent::Connected {
+                instance_id,
+                player,
+            });
+            self.controllers.insert(instance_id, controller);
+        }
+    }
 
-            let mut map = HashMap::with_capacity(num_joysticks as usize);
+    /// Open the devices that became game controllers (ex: after adding a mapping).
+    fn open_new_controllers(&mut self) {
+        for joystick in mapping::connected_joysticks(&self.subsystem, &self.joysticks) {
+            let already_open = self
+                .controllers
+                .values()
+                .any(|controller| controller.instance_id() == joystick.instance_id);
 
-            for joystick_index in 0..num_joysticks {
-                if let Some(controller) = GameController::from_joystick_index(joystick_index) {
-                    map.insert(joystick_index, controller);
-                }
+            if joystick.is_game_controller && !already_open {
+                self.added_controller(joystick.joystick_index);
             }
-            #[cfg(debug_assertions)]
-            println!("map {:#?}", map);
-            map
         }
     }
 
-    pub fn added_controller(&mut self, which: u32) {
-        println!("Added controller {:?}", which);
-        if let Some(controller) = GameController::from_joystick_index(which) {
-            self.controllers
-                .insert(controller.instance_id(), controller);
+    /// Load the mapping files found in the assets folder and in the settings directory.
+    pub fn load_default_mappings(&mut self, settings_dir: Option<&Path>) {
+        let assets_file = Path::new("assets").join(mapping::MAPPINGS_FILE_NAME);
+        let settings_file = settings_dir.map(|dir| dir.join(mapping::MAPPINGS_FILE_NAME));
+
+        for file in Some(assets_file).into_iter().chain(settings_file) {
+            if !file.exists() {
+                continue;
+            }
+            match self.load_mappings_from_file(&file) {
+                Ok(added) => println!("Loaded {} controller mappings from {:?}", added, file),
+                Err(error) => println!("Error loading controller mappings {:?}: {}", file, error),
+            }
         }
     }
 
+    /// Load a gamecontrollerdb-style mapping file, returns the number of mappings added.
+    pub fn load_mappings_from_file<P: AsRef<Path>>(&mut self, path: P) -> Result<u32, String> {
+        let added = mapping::load_mappings_from_file(&self.subsystem, path.as_ref())?;
+        self.open_new_controllers();
+        Ok(added)
+    }
+
+    /// Add a single mapping (same format as gamecontrollerdb.txt) at runtime.
+    pub fn add_mapping(&mut self, mapping: &str) -> Result<MappingStatus, String> {
+        let status = mapping::add_mapping(&self.subsystem, mapping)?;
+        self.open_new_controllers();
+        Ok(status)
+    }
+
+    /// Returns the mapping known for a joystick GUID.
+    pub fn mapping_for_guid(&self, guid: &str) -> Option<String> {
+        mapping::mapping_for_guid(&self.subsystem, guid)
+    }
+
+    /// List every connected joystick, including the ones without a mapping.
+    pub fn joysticks(&self) -> Vec<JoystickInfo> {
+        mapping::connected_joysticks(&self.subsystem, &self.joysticks)
+    }
+
+    /// Close the controller with the given instance id (`which` of ControllerDeviceRemoved).
     pub fn removed_controller(&mut self, which: u32) {
         println!("Disconnected controller {:?}", which);
         if let Some(controller) = self.controllers.remove(&which) {
+            self.patterns.remove(&which);
+            let player = self.players.disconnected(which);
+            self.events.push(ControllerEvent::Disconnected {
+                instance_id: which,
+                player,
+            });
             self::game_controller::close_controller(controller)
         }
     }
 
+    /// Attach a virtual gamepad and open it as a controller, returns its instance id.
+    pub fn attach_virtual_gamepad(&mut self) -> Result<u32, String> {
+        let gamepad = VirtualGamepad::attach(&self.subsystem)?;
+        let instance_id = gamepad.instance_id();
+        self.added_controller(gamepad.joystick_index());
+        self.virtual_gamepads.insert(instance_id, gamepad);
+        Ok(instance_id)
+    }
+
+    pub fn detach_virtual_gamepad(&mut self, instance_id: u32) {
+        if let Some(gamepad) = self.virtual_gamepads.remove(&instance_id) {
+            self.removed_controller(instance_id);
+            gamepad.detach();
+        }
+    }
+
+    pub fn virtual_gamepad(&self, instance_id: u32) -> Option<&VirtualGamepad> {
+        self.virtual_gamepads.get(&instance_id)
+    }
+
+    /// Press or release a button of a virtual gamepad, visible after the next `update`.
+    pub fn set_virtual_button(&self, instance_id: u32, button: Button, down: bool) {
+        if let Some(gamepad) = self.virtual_gamepads.get(&instance_id) {
+            gamepad.set_button(button, down);
+        }
+    }
+
+    /// Move an axis of a virtual gamepad (-1..1), visible after the next `update`.
+    pub fn set_virtual_axis(&self, instance_id: u32, axis: Axis, value: f32) {
+        if let Some(gamepad) = self.virtual_gamepads.get(&instance_id) {
+            gamepad.set_axis(axis, value);
+        }
+    }
+
+    /// Returns the connection events since the last call.
+    pub fn drain_events(&mut self) -> Vec<ControllerEvent> {
+        self.events.drain(..).collect()
+    }
+
+    pub fn set_join_policy(&mut self, policy: JoinPolicy) {
+        self.players.join_policy = policy;
+    }
+
+    pub fn set_leave_policy(&mut self, policy: LeavePolicy) {
+        self.players.leave_policy = policy;
+    }
+
+    /// Give the slot `player` (0 is P1) to the controller with the given instance id.
+    pub fn assign_player(&mut self, player: usize, instance_id: u32) {
+        if let Some(controller) = self.controllers.get(&instance_id) {
+            self.players.assign(player, instance_id, &controller.guid());
+        }
+    }
+
+    /// Free the slot `player`, the controller that was using it no longer has a slot.
+    pub fn release_player(&mut self, player: usize) {
+        self.players.release(player);
+    }
+
+    /// Returns the slot of the controller with the given instance id.
+    pub fn player_of(&self, instance_id: u32) -> Option<usize> {
+        self.players.player_of(instance_id)
+    }
+
+    /// Returns the controller used by `player`, if connected.
+    pub fn player_controller(&self, player: usize) -> Option<&GameController> {
+        self.players
+            .instance_of(player)
+            .and_then(|instance_id| self.controllers.get(&instance_id))
+    }
+
+    /// Instance id of the controller of each player slot.
+    pub fn player_instance_ids(&self) -> [Option<u32>; MAX_PLAYERS] {
+        self.players.instance_ids()
+    }
+
+    /// Set the deadzones of a single controller.
+    pub fn set_deadzones(&mut self, instance_id: u32, deadzones: Deadzones) {
+        if let Some(controller) = self.controllers.get_mut(&instance_id) {
+            controller.set_deadzones(deadzones);
+        }
+    }
+
+    /// Set the deadzones of every controller, including the ones connected later.
+    pub fn set_default_deadzones(&mut self, deadzones: Deadzones) {
+        self.default_deadzones = deadzones;
+        for controller in self.controllers.values_mut() {
+            controller.set_deadzones(deadzones);
+        }
+    }
+
+    pub fn haptic_settings(&self) -> &HapticSettings {
+        &self.haptic_settings
+    }
+
+    /// Set the global haptic intensity and on/off switch, for every controller.
+    pub fn set_haptic_settings(&mut self, settings: HapticSettings) {
+        self.haptic_settings = settings;
+        if !settings.enabled {
+            self.patterns.clear();
+        }
+        for controller in self.controllers.values_mut() {
+            controller.set_haptic_settings(settings);
+        }
+    }
+
+    /// Play `effect` on a controller, returns `false` if it is not supported.
+    pub fn play_effect(&mut self, instance_id: u32, effect: &HapticEffect) -> bool {
+        self.patterns.remove(&instance_id);
+        match self.controllers.get(&instance_id) {
+            Some(controller) => controller.play_effect(effect),
+            None => false,
+        }
+    }
+
+    /// Queue `pattern` after the patterns already playing on a controller.
+    pub fn queue_pattern(&mut self, instance_id: u32, pattern: &RumblePattern) {
+        if !self.haptic_settings.enabled {
+            return;
+        }
+        if let Some(controller) = self.controllers.get(&instance_id) {
+            let player = self
+                .patterns
+                .entry(instance_id)
+                .or_insert_with(PatternPlayer::default);
+            player.queue(pattern);
+
+            if let Some(step) = player.advance(0) {
+                controller.rumble(step.low_frequency, step.high_frequency, step.duration);
+            }
+        }
+    }
+
+    /// Stop every effect and pattern playing on a controller.
+    pub fn stop_haptics(&mut self, instance_id: u32) {
+        self.patterns.remove(&instance_id);
+        if let Some(controller) = self.controllers.get(&instance_id) {
+            controller.stop_haptics();
+        }
+    }
+
+    /// Update the state of every controller, to be called once per frame.
+    pub fn update(&mut self, delta_time: u32) {
+        if !self.virtual_gamepads.is_empty() {
+            // Apply the virtual values even when no event has been polled (ex: in tests)
+            unsafe { sdl2::sys::SDL_JoystickUpdate() };
+        }
+        for controller in self.controllers.values_mut() {
+            controller.update();
+        }
+
+        for (instance_id, player) in &mut self.patterns {
+            if let (Some(step), Some(controller)) = (
+                player.advance(delta_time),
+                self.controllers.get(instance_id),
+            ) {
+                controller.rumble(step.low_frequency, step.high_frequency, step.duration);
+            }
+        }
+        self.patterns.retain(|_, player| !player.is_finished());
+
+        if let JoinPolicy::OnButtonPress(button) = self.players.join_policy {
+            for (instance_id, controller) in &self.controllers {
+                if controller.state().is_button_pressed(button) {
+                    self.players.join(*instance_id, &controller.guid());
+                }
+            }
+        }
+    }
+
     pub fn snapshot(&self) -> HashMap<u32, GameController> {
         self.controllers.clone()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_virtual_gamepad() {
+        let sdl = sdl2::init().unwrap();
+        let mut manager = GameControllerManager::new(&sdl).unwrap();
+        let instance_id = match manager.attach_virtual_gamepad() {
+            Ok(instance_id) => instance_id,
+            Err(error) => {
+                // Virtual gamepads are not supported by the SDL in use
+                println!("Skipping the virtual gamepad test: {}", error);
+                return;
+            }
+        };
+        assert!(manager.controllers.contains_key(&instance_id));
+
+        manager.set_virtual_button(instance_id, Button::A, true);
+        manager.set_virtual_axis(instance_id, Axis::LeftX, 1.0);
+        manager.update(16);
+        {
+            let state = manager.controllers[&instance_id].state();
+            assert!(state.is_button_pressed(Button::A));
+            assert!(state.left_stick().0 > 0.9);
+        }
+
+        manager.set_virtual_button(instance_id, Button::A, false);
+        manager.update(16);
+        assert!(manager.controllers[&instance_id]
+            .state()
+            .is_button_released(Button::A));
+
+        manager.detach_virtual_gamepad(instance_id);
+        assert!(manager.virtual_gamepad(instance_id).is_none());
+        assert!(!manager.controllers.contains_key(&instance_id));
+    }
+}
@@ -0,0 +1,170 @@
+use sdl2::controller::Button;
+
+/// Number of player slots (P1 to P4), slots are indexed from 0.
+pub const MAX_PLAYERS: usize = 4;
+
+/// When a controller gets a player slot.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum JoinPolicy {
+    /// As soon as it is connected.
+    Automatic,
+    /// When the given button is pressed on it.
+    OnButtonPress(Button),
+    /// Only through `GameControllerManager::assign_player`.
+    Manual,
+}
+
+/// What happens to the slot of a controller that gets disconnected.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LeavePolicy {
+    /// The slot stays reserved until the same controller is connected again.
+    KeepSlot,
+    /// The slot becomes free for other controllers.
+    FreeSlot,
+}
+
+#[derive(Clone, Debug)]
+struct Slot {
+    guid: String,
+    instance_id: Option<u32>,
+}
+
+#[derive(Clone, Debug)]
+pub struct PlayerSlots {
+    slots: [Option<Slot>; MAX_PLAYERS],
+    pub join_policy: JoinPolicy,
+    pub leave_policy: LeavePolicy,
+}
+
+impl Default for PlayerSlots {
+    fn default() -> Self {
+        PlayerSlots {
+            slots: [None, None, None, None],
+            join_policy: JoinPolicy::Automatic,
+            leave_policy: LeavePolicy::KeepSlot,
+        }
+    }
+}
+
+impl PlayerSlots {
+    /// Called when a controller is connected, returns its slot.
+    /// A controller that was disconnected gets back its old slot.
+    pub fn connected(&mut self, instance_id: u32, guid: &str) -> Option<usize> {
+        let old_slot = self.slots.iter().position(|slot| match *slot {
+            Some(ref slot) => slot.instance_id.is_none() && slot.guid == guid,
+            None => false,
+        });
+
+        if let Some(player) = old_slot {
+            self.assign(player, instance_id, guid);
+            Some(player)
+        } else if self.join_policy == JoinPolicy::Automatic {
+            self.join(instance_id, guid)
+        } else {
+            None
+        }
+    }
+
+    /// Called when a controller is disconnected, returns the slot it had.
+    pub fn disconnected(&mut self, instance_id: u32) -> Option<usize> {
+        let player = self.player_of(instance_id)?;
+        match self.leave_policy {
+            LeavePolicy::KeepSlot => {
+                if let Some(ref mut slot) = self.slots[player] {
+                    slot.instance_id = None;
+                }
+            }
+            LeavePolicy::FreeSlot => self.slots[player] = None,
+        }
+        Some(player)
+    }
+
+    /// Put the controller in the first free slot.
+    pub fn join(&mut self, instance_id: u32, guid: &str) -> Option<usize> {
+        if let Some(player) = self.player_of(instance_id) {
+            return Some(player);
+        }
+        let player = self.slots.iter().position(Option::is_none)?;
+        self.assign(player, instance_id, guid);
+        Some(player)
+    }
+
+    /// Put the controller in the given slot, replacing the previous one.
+    pub fn assign(&mut self, player: usize, instance_id: u32, guid: &str) {
+        if let Some(previous) = self.player_of(instance_id) {
+            self.slots[previous] = None;
+        }
+        self.slots[player] = Some(Slot {
+            guid: guid.to_string(),
+            instance_id: Some(instance_id),
+        });
+    }
+
+    pub fn release(&mut self, player: usize) {
+        self.slots[player] = None;
+    }
+
+    pub fn player_of(&self, instance_id: u32) -> Option<usize> {
+        self.slots.iter().position(|slot| match *slot {
+            Some(ref slot) => slot.instance_id == Some(instance_id),
+            None => false,
+        })
+    }
+
+    /// Instance id of the controller used by `player`, if it is connected.
+    pub fn instance_of(&self, player: usize) -> Option<u32> {
+        self.slots
+            .get(player)
+            .and_then(|slot| slot.as_ref())
+            .and_then(|slot| slot.instance_id)
+    }
+
+    pub fn instance_ids(&self) -> [Option<u32>; MAX_PLAYERS] {
+        let mut ids = [None; MAX_PLAYERS];
+        for (player, id) in ids.iter_mut().enumerate() {
+            *id = self.instance_of(player);
+        }
+        ids
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_reconnect_keeps_slot() {
+        let mut slots = PlayerSlots::default();
+        assert_eq!(slots.connected(10, "pad_a"), Some(0));
+        assert_eq!(slots.connected(11, "pad_b"), Some(1));
+
+        assert_eq!(slots.disconnected(10), Some(0));
+        assert_eq!(slots.instance_of(0), None);
+
+        // A new pad does not steal the reserved slot
+        assert_eq!(slots.connected(12, "pad_c"), Some(2));
+        // The old pad gets a new instance id but the same slot
+        assert_eq!(slots.connected(13, "pad_a"), Some(0));
+        assert_eq!(slots.instance_ids(), [Some(13), Some(11), Some(12), None]);
+    }
+
+    #[test]
+    fn test_free_slot_policy() {
+        let mut slots = PlayerSlots::default();
+        slots.leave_policy = LeavePolicy::FreeSlot;
+        slots.connected(10, "pad_a");
+        slots.disconnected(10);
+
+        assert_eq!(slots.connected(11, "pad_b"), Some(0));
+    }
+
+    #[test]
+    fn test_manual_join() {
+        let mut slots = PlayerSlots::default();
+        slots.join_policy = JoinPolicy::Manual;
+        assert_eq!(slots.connected(10, "pad_a"), None);
+
+        slots.assign(3, 10, "pad_a");
+        assert_eq!(slots.player_of(10), Some(3));
+    }
+}
@@ -0,0 +1,208 @@
+use sdl2::controller::{Axis, Button};
+use std::collections::HashSet;
+
+const AXIS_MAX: f32 = 32_767.0;
+
+pub const ALL_BUTTONS: [Button; 15] = [
+    Button::A,
+    Button::B,
+    Button::X,
+    Button::Y,
+    Button::Back,
+    Button::Guide,
+    Button::Start,
+    Button::LeftStick,
+    Button::RightStick,
+    Button::LeftShoulder,
+    Button::RightShoulder,
+    Button::DPadUp,
+    Button::DPadDown,
+    Button::DPadLeft,
+    Button::DPadRight,
+];
+
+/// Deadzones applied to the axes of a controller, values are in the 0..1 range.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Deadzones {
+    /// Stick movements below this radius are ignored.
+    pub inner: f32,
+    /// Stick movements above this radius are considered full tilt.
+    pub outer: f32,
+    /// Trigger values below this threshold are ignored.
+    pub trigger_threshold: f32,
+}
+
+impl Default for Deadzones {
+    fn default() -> Self {
+        Deadzones {
+            inner: 0.2,
+            outer: 0.95,
+            trigger_threshold: 0.1,
+        }
+    }
+}
+
+impl Deadzones {
+    /// Apply the radial deadzone to a stick, the result is rescaled to the -1..1 range.
+    pub fn apply_to_stick(&self, x: f32, y: f32) -> (f32, f32) {
+        let magnitude = (x * x + y * y).sqrt();
+        if magnitude <= self.inner || magnitude == 0.0 {
+            return (0.0, 0.0);
+        }
+        let range = (self.outer - self.inner).max(::std::f32::EPSILON);
+        let scaled = ((magnitude - self.inner) / range).min(1.0);
+
+        (x / magnitude * scaled, y / magnitude * scaled)
+    }
+
+    /// Apply the threshold to a trigger, the result is rescaled to the 0..1 range.
+    pub fn apply_to_trigger(&self, value: f32) -> f32 {
+        if value <= self.trigger_threshold {
+            return 0.0;
+        }
+        let range = (1.0 - self.trigger_threshold).max(::std::f32::EPSILON);
+        ((value - self.trigger_threshold) / range).min(1.0)
+    }
+}
+
+/// Normalize a raw SDL axis value to the -1..1 range.
+pub fn normalize_axis(value: i16) -> f32 {
+    (f32::from(value) / AXIS_MAX).max(-1.0)
+}
+
+/// State of a controller during a single frame, with deadzones already applied.
+#[derive(Clone, Debug, Default)]
+pub struct ControllerState {
+    buttons_down: HashSet<Button>,
+    buttons_pressed: HashSet<Button>,
+    buttons_released: HashSet<Button>,
+    left_stick: (f32, f32),
+    right_stick: (f32, f32),
+    left_trigger: f32,
+    right_trigger: f32,
+}
+
+impl ControllerState {
+    /// Compute the new state given the previous one, the raw buttons and the raw axes.
+    pub fn next<A>(
+        &self,
+        buttons_down: HashSet<Button>,
+        raw_axis: A,
+        deadzones: &Deadzones,
+    ) -> ControllerState
+    where
+        A: Fn(Axis) -> i16,
+    {
+        let axis = |axis| normalize_axis(raw_axis(axis));
+
+        ControllerState {
+            buttons_pressed: &buttons_down - &self.buttons_down,
+            buttons_released: &self.buttons_down - &buttons_down,
+            buttons_down,
+            left_stick: deadzones.apply_to_stick(axis(Axis::LeftX), axis(Axis::LeftY)),
+            right_stick: deadzones.apply_to_stick(axis(Axis::RightX), axis(Axis::RightY)),
+            left_trigger: deadzones.apply_to_trigger(axis(Axis::TriggerLeft)),
+            right_trigger: deadzones.apply_to_trigger(axis(Axis::TriggerRight)),
+        }
+    }
+
+    pub fn is_button_down(&self, button: Button) -> bool {
+        self.buttons_down.contains(&button)
+    }
+    pub fn is_button_pressed(&self, button: Button) -> bool {
+        self.buttons_pressed.contains(&button)
+    }
+    pub fn is_button_released(&self, button: Button) -> bool {
+        self.buttons_released.contains(&button)
+    }
+
+    pub fn buttons_down(&self) -> &HashSet<Button> {
+        &self.buttons_down
+    }
+    pub fn buttons_pressed(&self) -> &HashSet<Button> {
+        &self.buttons_pressed
+    }
+    pub fn buttons_released(&self) -> &HashSet<Button> {
+        &self.buttons_released
+    }
+
+    pub fn left_stick(&self) -> (f32, f32) {
+        self.left_stick
+    }
+    pub fn right_stick(&self) -> (f32, f32) {
+        self.right_stick
+    }
+    pub fn left_trigger(&self) -> f32 {
+        self.left_trigger
+    }
+    pub fn right_trigger(&self) -> f32 {
+        self.right_trigger
+    }
+
+    /// Get the normalized value of `axis` (-1..1 for sticks, 0..1 for triggers).
+    pub fn axis(&self, axis: Axis) -> f32 {
+        match axis {
+            Axis::LeftX => self.left_stick.0,
+            Axis::LeftY => self.left_stick.1,
+            Axis::RightX => self.right_stick.0,
+            Axis::RightY => self.right_stick.1,
+            Axis::TriggerLeft => self.left_trigger,
+            Axis::TriggerRight => self.right_trigger,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_radial_deadzone() {
+        let deadzones = Deadzones {
+            inner: 0.2,
+            outer: 0.8,
+            trigger_threshold: 0.5,
+        };
+        assert_eq!(deadzones.apply_to_stick(0.1, 0.1), (0.0, 0.0));
+        assert_eq!(deadzones.apply_to_stick(0.9, 0.0), (1.0, 0.0));
+
+        let (x, y) = deadzones.apply_to_stick(0.0, -0.5);
+        assert_eq!(x, 0.0);
+        assert!((y + 0.5).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_trigger_threshold() {
+        let deadzones = Deadzones {
+            inner: 0.2,
+            outer: 0.8,
+            trigger_threshold: 0.5,
+        };
+        assert_eq!(deadzones.apply_to_trigger(0.4), 0.0);
+        assert_eq!(deadzones.apply_to_trigger(0.75), 0.5);
+        assert_eq!(deadzones.apply_to_trigger(1.0), 1.0);
+    }
+
+    #[test]
+    fn test_button_edges() {
+        let deadzones = Deadzones::default();
+        let first = ControllerState::default().next(
+            [Button::A].iter().cloned().collect(),
+            |_| 0,
+            &deadzones,
+        );
+        assert!(first.is_button_pressed(Button::A));
+
+        let second = first.next([Button::B].iter().cloned().collect(), |_| 0, &deadzones);
+        assert!(second.is_button_released(Button::A));
+        assert!(second.is_button_pressed(Button::B));
+        assert!(!second.is_button_down(Button::A));
+    }
+
+    #[test]
+    fn test_normalize_axis() {
+        assert_eq!(normalize_axis(i16::min_value()), -1.0);
+        assert_eq!(normalize_axis(i16::max_value()), 1.0);
+        assert_eq!(normalize_axis(0), 0.0);
+    }
+}
@@ -0,0 +1,341 @@
+use sdl2::controller::{Axis, Button};
+use sdl2::get_error;
+use sdl2::pixels::Color;
+use sdl2::rect::{Point, Rect};
+use sdl2::render::WindowCanvas;
+use sdl2::sys;
+use sdl2::sys::SDL_Joystick;
+use sdl2::GameControllerSubsystem;
+
+use std::mem;
+use std::os::raw::c_int;
+use std::sync::Once;
+
+use super::mapping::{add_mapping, guid_to_string};
+use super::state::ALL_BUTTONS;
+use super::GameControllerManager;
+use sdl2_utils::load_sdl_function;
+use EngineContext;
+
+type AttachVirtualFunction = unsafe extern "C" fn(c_int, c_int, c_int, c_int) -> c_int;
+type DetachVirtualFunction = unsafe extern "C" fn(c_int) -> c_int;
+type SetVirtualAxisFunction = unsafe extern "C" fn(*mut SDL_Joystick, c_int, i16) -> c_int;
+type SetVirtualButtonFunction = unsafe extern "C" fn(*mut SDL_Joystick, c_int, u8) -> c_int;
+
+/// The virtual joystick functions of SDL 2.0.14, missing from the sdl2 bindings and looked
+/// up at runtime.
+#[derive(Debug)]
+struct VirtualJoystickFunctions {
+    attach: AttachVirtualFunction,
+    detach: DetachVirtualFunction,
+    set_axis: SetVirtualAxisFunction,
+    set_button: SetVirtualButtonFunction,
+}
+
+fn load_virtual_joystick_functions() -> Option<VirtualJoystickFunctions> {
+    let since = (2, 0, 14);
+    unsafe {
+        Some(VirtualJoystickFunctions {
+            attach: mem::transmute(load_sdl_function("SDL_JoystickAttachVirtual", since)?),
+            detach: mem::transmute(load_sdl_function("SDL_JoystickDetachVirtual", since)?),
+            set_axis: mem::transmute(load_sdl_function("SDL_JoystickSetVirtualAxis", since)?),
+            set_button: mem::transmute(load_sdl_function("SDL_JoystickSetVirtualButton", since)?),
+        })
+    }
+}
+
+/// `None` when the SDL in use does not support virtual joysticks.
+fn virtual_joystick_functions() -> Option<&'static VirtualJoystickFunctions> {
+    static INIT: Once = Once::new();
+    static mut FUNCTIONS: Option<VirtualJoystickFunctions> = None;
+    unsafe {
+        INIT.call_once(|| FUNCTIONS = load_virtual_joystick_functions());
+        FUNCTIONS.as_ref()
+    }
+}
+
+// SDL_JOYSTICK_TYPE_GAMECONTROLLER
+const JOYSTICK_TYPE_GAMECONTROLLER: c_int = 1;
+
+const NUM_AXES: c_int = 6;
+
+/// Buttons and axes of the virtual joystick follow the SDL_GameControllerButton
+/// and SDL_GameControllerAxis order.
+const VIRTUAL_MAPPING: &str = "Leek Virtual Gamepad,a:b0,b:b1,x:b2,y:b3,back:b4,guide:b5,\
+start:b6,leftstick:b7,rightstick:b8,leftshoulder:b9,rightshoulder:b10,dpup:b11,dpdown:b12,\
+dpleft:b13,dpright:b14,leftx:a0,lefty:a1,rightx:a2,righty:a3,lefttrigger:a4,righttrigger:a5";
+
+fn button_index(button: Button) -> c_int {
+    ALL_BUTTONS.iter().position(|b| *b == button).unwrap_or(0) as c_int
+}
+
+fn axis_index(axis: Axis) -> c_int {
+    match axis {
+        Axis::LeftX => 0,
+        Axis::LeftY => 1,
+        Axis::RightX => 2,
+        Axis::RightY => 3,
+        Axis::TriggerLeft => 4,
+        Axis::TriggerRight => 5,
+    }
+}
+
+/// A controller emulated by SDL, driven from code (tests, replays, on-screen controls).
+///
+/// `GameControllerManager` opens it like any other game controller.
+#[derive(Debug)]
+pub struct VirtualGamepad {
+    functions: &'static VirtualJoystickFunctions,
+    joystick: *mut SDL_Joystick,
+    joystick_index: u32,
+    instance_id: u32,
+}
+
+impl VirtualGamepad {
+    pub fn attach(subsystem: &GameControllerSubsystem) -> Result<VirtualGamepad, String> {
+        let functions = virtual_joystick_functions()
+            .ok_or_else(|| "Virtual gamepads require SDL 2.0.14 or later".to_string())?;
+        unsafe {
+            let index = (functions.attach)(
+                JOYSTICK_TYPE_GAMECONTROLLER,
+                NUM_AXES,
+                ALL_BUTTONS.len() as c_int,
+                0,
+            );
+            if index < 0 {
+                return Err(get_error());
+            }
+
+            let guid = guid_to_string(sys::SDL_JoystickGetDeviceGUID(index));
+            if let Err(error) = add_mapping(subsystem, &format!("{},{}", guid, VIRTUAL_MAPPING)) {
+                (functions.detach)(index);
+                return Err(error);
+            }
+
+            // The values can only be set on an open joystick
+            let joystick = sys::SDL_JoystickOpen(index);
+            if joystick.is_null() {
+                (functions.detach)(index);
+                return Err(get_error());
+            }
+
+            Ok(VirtualGamepad {
+                functions,
+                joystick,
+                joystick_index: index as u32,
+                instance_id: sys::SDL_JoystickInstanceID(joystick) as u32,
+            })
+        }
+    }
+
+    pub fn joystick_index(&self) -> u32 {
+        self.joystick_index
+    }
+
+    pub fn instance_id(&self) -> u32 {
+        self.instance_id
+    }
+
+    pub fn set_button(&self, button: Button, down: bool) {
+        unsafe {
+            (self.functions.set_button)(self.joystick, button_index(button), down as u8);
+        }
+    }
+
+    /// Set an axis from a value in the -1..1 range (0..1 for the triggers).
+    pub fn set_axis(&self, axis: Axis, value: f32) {
+        let value = (value.max(-1.0).min(1.0) * 32_767.0) as i16;
+        unsafe {
+            (self.functions.set_axis)(self.joystick, axis_index(axis), value);
+        }
+    }
+
+    /// Release every button and center every axis.
+    pub fn reset(&self) {
+        for button in &ALL_BUTTONS {
+            self.set_button(*button, false);
+        }
+        for axis in 0..NUM_AXES {
+            unsafe {
+                (self.functions.set_axis)(self.joystick, axis, 0);
+            }
+        }
+    }
+
+    pub fn detach(self) {
+        unsafe {
+            sys::SDL_JoystickClose(self.joystick);
+            // The device index may have changed since the gamepad was attached
+            for index in 0..sys::SDL_NumJoysticks() {
+                if sys::SDL_JoystickGetDeviceInstanceID(index) as u32 == self.instance_id {
+                    (self.functions.detach)(index);
+                    break;
+                }
+            }
+        }
+    }
+}
+
+unsafe impl Send for VirtualGamepad {}
+unsafe impl Sync for VirtualGamepad {}
+
+/// A button of the on-screen gamepad.
+#[derive(Clone, Copy, Debug)]
+pub struct OnScreenButton {
+    pub button: Button,
+    pub rect: Rect,
+}
+
+/// A stick of the on-screen gamepad, pushed by dragging inside its circle.
+#[derive(Clone, Copy, Debug)]
+pub struct OnScreenStick {
+    pub x_axis: Axis,
+    pub y_axis: Axis,
+    pub center: Point,
+    pub radius: u32,
+}
+
+impl OnScreenStick {
+    /// Returns the stick position in the -1..1 range, `None` if `point` is outside.
+    fn position(&self, point: Point) -> Option<(f32, f32)> {
+        let radius = self.radius.max(1) as f32;
+        let x = (point.x() - self.center.x()) as f32 / radius;
+        let y = (point.y() - self.center.y()) as f32 / radius;
+        if x * x + y * y > 1.0 {
+            None
+        } else {
+            Some((x, y))
+        }
+    }
+}
+
+/// Touch or mouse controls drawn over the game, backed by a virtual gamepad.
+#[derive(Debug)]
+pub struct OnScreenGamepad {
+    instance_id: u32,
+    pub buttons: Vec<OnScreenButton>,
+    pub sticks: Vec<OnScreenStick>,
+    pub color: Color,
+    pub pressed_color: Color,
+    pressed: Vec<Button>,
+    stick_positions: Vec<(f32, f32)>,
+}
+
+impl OnScreenGamepad {
+    /// Attach a virtual gamepad to `manager` and create empty controls for it.
+    pub fn new(manager: &mut GameControllerManager) -> Result<OnScreenGamepad, String> {
+        let instance_id = manager.attach_virtual_gamepad()?;
+        Ok(OnScreenGamepad {
+            instance_id,
+            buttons: Vec::new(),
+            sticks: Vec::new(),
+            color: Color::RGBA(255, 255, 255, 96),
+            pressed_color: Color::RGBA(255, 255, 255, 192),
+            pressed: Vec::new(),
+            stick_positions: Vec::new(),
+        })
+    }
+
+    /// Instance id of the virtual gamepad.
+    pub fn instance_id(&self) -> u32 {
+        self.instance_id
+    }
+
+    pub fn add_button(&mut self, button: Button, rect: Rect) {
+        self.buttons.push(OnScreenButton { button, rect });
+    }
+
+    pub fn add_stick(&mut self, x_axis: Axis, y_axis: Axis, center: Point, radius: u32) {
+        self.sticks.push(OnScreenStick {
+            x_axis,
+            y_axis,
+            center,
+            radius,
+        });
+    }
+
+    /// Press the controls under `pointers` (touches or mouse) and release the others.
+    pub fn update(&mut self, pointers: &[Point], manager: &GameControllerManager) {
+        self.pressed = self
+            .buttons
+            .iter()
+            .filter(|b| pointers.iter().any(|p| b.rect.contains_point(*p)))
+            .map(|b| b.button)
+            .collect();
+        self.stick_positions = self
+            .sticks
+            .iter()
+            .map(|stick| {
+                pointers
+                    .iter()
+                    .filter_map(|p| stick.position(*p))
+                    .next()
+                    .unwrap_or((0.0, 0.0))
+            })
+            .collect();
+
+        for on_screen in &self.buttons {
+            let down = self.pressed.contains(&on_screen.button);
+            manager.set_virtual_button(self.instance_id, on_screen.button, down);
+        }
+        for (stick, &(x, y)) in self.sticks.iter().zip(&self.stick_positions) {
+            manager.set_virtual_axis(self.instance_id, stick.x_axis, x);
+            manager.set_virtual_axis(self.instance_id, stick.y_axis, y);
+        }
+    }
+
+    /// Use the mouse as the only pointer, while its left button is down.
+    pub fn update_with_mouse(&mut self, context: &EngineContext, manager: &GameControllerManager) {
+        if context.mouse_state().left() {
+            let (x, y) = context.mouse_position();
+            self.update(&[Point::new(x as i32, y as i32)], manager);
+        } else {
+            self.update(&[], manager);
+        }
+    }
+
+    /// Use every finger touching the screen as a pointer.
+    pub fn update_with_touches(
+        &mut self,
+        context: &EngineContext,
+        manager: &GameControllerManager,
+    ) {
+        let pointers: Vec<Point> = context
+            .touches()
+            .iter()
+            .map(|touch| Point::new(touch.x as i32, touch.y as i32))
+            .collect();
+        self.update(&pointers, manager);
+    }
+
+    pub fn render(&self, renderer: &mut WindowCanvas) -> Result<(), String> {
+        for on_screen in &self.buttons {
+            let pressed = self.pressed.contains(&on_screen.button);
+            renderer.set_draw_color(if pressed {
+                self.pressed_color
+            } else {
+                self.color
+            });
+            renderer.fill_rect(on_screen.rect)?;
+        }
+
+        renderer.set_draw_color(self.color);
+        for (index, stick) in self.sticks.iter().enumerate() {
+            let radius = stick.radius as i32;
+            let area = Rect::from_center(stick.center, stick.radius * 2, stick.radius * 2);
+            renderer.draw_rect(area)?;
+
+            let (x, y) = self
+                .stick_positions
+                .get(index)
+                .cloned()
+                .unwrap_or((0.0, 0.0));
+            let knob = Point::new(
+                stick.center.x() + (x * radius as f32) as i32,
+                stick.center.y() + (y * radius as f32) as i32,
+            );
+            renderer.fill_rect(Rect::from_center(knob, stick.radius, stick.radius))?;
+        }
+        Ok(())
+    }
+}
@@ -70,13 +70,9 @@ impl Renderer {
             gl::Enable(gl::TEXTURE_2D);
             gl::ActiveTexture(gl::TEXTURE0);
 
-            gl::UniformMatrix4fv(
-                self.device_objects.u_projmat.into(),
-                1,
-                0,
-                matrix.as_ptr() as *const f32,
-            );
-            gl::Uniform1i(self.device_objects.u_texture.into(), 0);
+            let program = &self.device_objects.program;
+            program.set_uniform("ProjMtx", matrix);
+            program.set_uniform("Texture", Sampler(0));
 
             gl::BindVertexArray(self.device_objects.vao);
 
@@ -148,11 +144,9 @@ pub struct DeviceObjects {
     index_buffer: GLuint,
     program: Shader,
     texture: Texture,
-    u_texture: UniformLocation,
-    u_projmat: UniformLocation,
 }
 
-fn compile_default_program() -> Result<Shader, String> {
+fn compile_default_program() -> Result<Shader, ShaderError> {
     Shader::new(
         include_str!("shaders/glsl_130.vert"),
         include_str!("shaders/glsl_130.frag"),
@@ -168,8 +162,6 @@ impl DeviceObjects {
 
             program.activate();
 
-            let g_attrib_location_tex = program.get_uniform_location("Texture");
-            let g_attrib_location_proj_mtx = program.get_uniform_location("ProjMtx");
             let g_attrib_location_position = program.get_attrib_location("Position");
             let g_attrib_location_uv = program.get_attrib_location("UV");
             let g_attrib_location_color = program.get_attrib_location("Color");
@@ -224,8 +216,6 @@ impl DeviceObjects {
                 index_buffer: ebo,
                 program,
                 texture,
-                u_texture: g_attrib_location_tex,
-                u_projmat: g_attrib_location_proj_mtx,
             })
         }
     }
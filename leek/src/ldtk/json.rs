@@ -0,0 +1,457 @@
+use sdl2::rect::Rect;
+use serde_json::{self, Value};
+
+use super::{
+    EntityInstance, FieldValue, Fields, LayerKind, LdtkLayer, LdtkLevel, LdtkProject, LdtkTile,
+    LdtkTileset, WorldLayout,
+};
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_opacity() -> f32 {
+    1.0
+}
+
+#[derive(Deserialize)]
+struct JsonField {
+    #[serde(rename = "__identifier")]
+    identifier: String,
+    #[serde(rename = "__type")]
+    kind: String,
+    #[serde(rename = "__value")]
+    value: Value,
+}
+
+fn parse_field(kind: &str, value: Value) -> Result<FieldValue, String> {
+    if value.is_null() {
+        return Ok(FieldValue::Null);
+    }
+    if kind.starts_with("Array<") && kind.ends_with('>') {
+        let item_kind = &kind["Array<".len()..kind.len() - 1];
+        return match value {
+            Value::Array(values) => values
+                .into_iter()
+                .map(|value| parse_field(item_kind, value))
+                .collect::<Result<_, String>>()
+                .map(FieldValue::Array),
+            value => Err(format!("Invalid {} field: {}", kind, value)),
+        };
+    }
+
+    let string = |value: &Value| value.as_str().map(str::to_string);
+    let parsed = match kind {
+        "Int" => value.as_i64().map(FieldValue::Int),
+        "Float" => value.as_f64().map(FieldValue::Float),
+        "Bool" => value.as_bool().map(FieldValue::Bool),
+        "String" | "Multilines" => string(&value).map(FieldValue::String),
+        "Color" => string(&value).map(FieldValue::Color),
+        "FilePath" => string(&value).map(FieldValue::FilePath),
+        "Point" => match (value["cx"].as_i64(), value["cy"].as_i64()) {
+            (Some(x), Some(y)) => Some(FieldValue::Point(x as i32, y as i32)),
+            _ => None,
+        },
+        "EntityRef" => string(&value["entityIid"]).map(FieldValue::EntityRef),
+        "Tile" => match (
+            value["tilesetUid"].as_i64(),
+            value["x"].as_i64(),
+            value["y"].as_i64(),
+            value["w"].as_u64(),
+            value["h"].as_u64(),
+        ) {
+            (Some(tileset_uid), Some(x), Some(y), Some(w), Some(h)) => Some(FieldValue::Tile {
+                tileset_uid,
+                rect: Rect::new(x as i32, y as i32, w as u32, h as u32),
+            }),
+            _ => None,
+        },
+        // LocalEnum.Name, ExternEnum.Name or Enum(Name) depending on the version
+        _ if kind.contains("Enum") => string(&value).map(FieldValue::Enum),
+        _ => return Err(format!("Unknown field type {}", kind)),
+    };
+    parsed.ok_or_else(|| format!("Invalid {} field: {}", kind, value))
+}
+
+fn parse_fields(fields: Vec<JsonField>) -> Result<Fields, String> {
+    fields
+        .into_iter()
+        .map(|field| Ok((field.identifier, parse_field(&field.kind, field.value)?)))
+        .collect()
+}
+
+#[derive(Deserialize)]
+struct JsonTilesetDef {
+    uid: i64,
+    identifier: String,
+    #[serde(rename = "relPath")]
+    rel_path: Option<String>,
+    #[serde(rename = "tileGridSize")]
+    tile_grid_size: u32,
+    #[serde(default)]
+    spacing: u32,
+    #[serde(default)]
+    padding: u32,
+}
+
+#[derive(Deserialize)]
+struct JsonDefs {
+    #[serde(default)]
+    tilesets: Vec<JsonTilesetDef>,
+}
+
+#[derive(Deserialize)]
+struct JsonTile {
+    px: (i32, i32),
+    src: (i32, i32),
+    /// Bit 0 flips on X, bit 1 on Y.
+    #[serde(default)]
+    f: u8,
+}
+
+impl JsonTile {
+    fn into_tile(self) -> LdtkTile {
+        LdtkTile {
+            position: self.px,
+            source: self.src,
+            flip_x: self.f & 1 != 0,
+            flip_y: self.f & 2 != 0,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct JsonEntity {
+    #[serde(rename = "__identifier")]
+    identifier: String,
+    #[serde(default)]
+    iid: String,
+    #[serde(rename = "__grid")]
+    grid: (i32, i32),
+    #[serde(rename = "__pivot")]
+    pivot: (f32, f32),
+    #[serde(rename = "__tags", default)]
+    tags: Vec<String>,
+    px: (i32, i32),
+    width: u32,
+    height: u32,
+    #[serde(rename = "fieldInstances", default)]
+    field_instances: Vec<JsonField>,
+}
+
+#[derive(Deserialize)]
+struct JsonLayer {
+    #[serde(rename = "__identifier")]
+    identifier: String,
+    #[serde(rename = "__type")]
+    kind: String,
+    #[serde(rename = "__cWid")]
+    width: u32,
+    #[serde(rename = "__cHei")]
+    height: u32,
+    #[serde(rename = "__gridSize")]
+    grid_size: u32,
+    #[serde(rename = "__opacity", default = "default_opacity")]
+    opacity: f32,
+    #[serde(rename = "__pxTotalOffsetX", default)]
+    offset_x: i32,
+    #[serde(rename = "__pxTotalOffsetY", default)]
+    offset_y: i32,
+    #[serde(rename = "__tilesetDefUid")]
+    tileset_uid: Option<i64>,
+    #[serde(default = "default_true")]
+    visible: bool,
+    #[serde(rename = "intGridCsv", default)]
+    int_grid: Vec<i32>,
+    #[serde(rename = "autoLayerTiles", default)]
+    auto_layer_tiles: Vec<JsonTile>,
+    #[serde(rename = "gridTiles", default)]
+    grid_tiles: Vec<JsonTile>,
+    #[serde(rename = "entityInstances", default)]
+    entity_instances: Vec<JsonEntity>,
+}
+
+impl JsonLayer {
+    fn into_layer(self) -> Result<LdtkLayer, String> {
+        let kind = match self.kind.as_str() {
+            "IntGrid" => LayerKind::IntGrid,
+            "Entities" => LayerKind::Entities,
+            "Tiles" => LayerKind::Tiles,
+            "AutoLayer" => LayerKind::AutoLayer,
+            kind => return Err(format!("Unknown layer type {}", kind)),
+        };
+
+        let entities = self
+            .entity_instances
+            .into_iter()
+            .map(|entity| {
+                Ok(EntityInstance {
+                    identifier: entity.identifier,
+                    iid: entity.iid,
+                    grid: entity.grid,
+                    position: entity.px,
+                    size: (entity.width, entity.height),
+                    pivot: entity.pivot,
+                    tags: entity.tags,
+                    fields: parse_fields(entity.field_instances)?,
+                })
+            })
+            .collect::<Result<_, String>>()?;
+
+        Ok(LdtkLayer {
+            identifier: self.identifier,
+            kind,
+            grid_size: self.grid_size,
+            size: (self.width, self.height),
+            offset: (self.offset_x, self.offset_y),
+            opacity: self.opacity,
+            visible: self.visible,
+            tileset_uid: self.tileset_uid,
+            int_grid: self.int_grid,
+            // IntGrid layers with rules have auto tiles too
+            tiles: self
+                .auto_layer_tiles
+                .into_iter()
+                .chain(self.grid_tiles)
+                .map(JsonTile::into_tile)
+                .collect(),
+            entities,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct JsonNeighbour {
+    #[serde(rename = "levelIid")]
+    level_iid: String,
+    dir: String,
+}
+
+#[derive(Deserialize)]
+struct JsonLevel {
+    identifier: String,
+    #[serde(default)]
+    iid: String,
+    uid: i64,
+    #[serde(rename = "worldX")]
+    world_x: i32,
+    #[serde(rename = "worldY")]
+    world_y: i32,
+    #[serde(rename = "pxWid")]
+    width: u32,
+    #[serde(rename = "pxHei")]
+    height: u32,
+    #[serde(rename = "__bgColor")]
+    background_color: Option<String>,
+    #[serde(rename = "fieldInstances", default)]
+    field_instances: Vec<JsonField>,
+    /// Missing when the level is saved in a separate file.
+    #[serde(rename = "layerInstances")]
+    layer_instances: Option<Vec<JsonLayer>>,
+    #[serde(rename = "__neighbours", default)]
+    neighbours: Vec<JsonNeighbour>,
+    #[serde(rename = "externalRelPath")]
+    external_rel_path: Option<String>,
+}
+
+impl JsonLevel {
+    fn into_level(self) -> Result<LdtkLevel, String> {
+        Ok(LdtkLevel {
+            identifier: self.identifier,
+            iid: self.iid,
+            uid: self.uid,
+            world_position: (self.world_x, self.world_y),
+            size: (self.width, self.height),
+            background_color: self.background_color,
+            fields: parse_fields(self.field_instances)?,
+            layers: self
+                .layer_instances
+                .unwrap_or_default()
+                .into_iter()
+                .map(JsonLayer::into_layer)
+                .collect::<Result<_, String>>()?,
+            neighbours: self
+                .neighbours
+                .into_iter()
+                .map(|n| (n.level_iid, n.dir.chars().next().unwrap_or('?')))
+                .collect(),
+            external: self.external_rel_path,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct JsonWorld {
+    #[serde(rename = "worldLayout")]
+    world_layout: Option<String>,
+    #[serde(default)]
+    levels: Vec<JsonLevel>,
+}
+
+#[derive(Deserialize)]
+struct JsonProject {
+    defs: JsonDefs,
+    #[serde(rename = "worldLayout")]
+    world_layout: Option<String>,
+    #[serde(default)]
+    levels: Vec<JsonLevel>,
+    /// Projects with multiple worlds store their levels here.
+    #[serde(default)]
+    worlds: Vec<JsonWorld>,
+}
+
+fn parse_world_layout(layout: Option<&str>) -> Result<WorldLayout, String> {
+    Ok(match layout {
+        Some("Free") | None => WorldLayout::Free,
+        Some("GridVania") => WorldLayout::GridVania,
+        Some("LinearHorizontal") => WorldLayout::LinearHorizontal,
+        Some("LinearVertical") => WorldLayout::LinearVertical,
+        Some(layout) => return Err(format!("Unknown world layout {}", layout)),
+    })
+}
+
+/// Parse an LDtk project, levels saved in separate files are left to be loaded
+/// (see `LdtkLevel::external`).
+pub fn parse_ldtk_project(json: &str) -> Result<LdtkProject, String> {
+    let project: JsonProject = serde_json::from_str(json).map_err(|e| format!("{}", e))?;
+
+    let mut world_layout = project.world_layout;
+    let mut levels = project.levels;
+    for world in project.worlds {
+        world_layout = world_layout.or(world.world_layout);
+        levels.extend(world.levels);
+    }
+
+    let tilesets = project
+        .defs
+        .tilesets
+        .into_iter()
+        // Embedded tilesets (like the LDtk icons) have no image
+        .filter_map(|tileset| {
+            let image = tileset.rel_path?;
+            Some(LdtkTileset {
+                uid: tileset.uid,
+                identifier: tileset.identifier,
+                image,
+                tile_size: tileset.tile_grid_size,
+                spacing: tileset.spacing,
+                padding: tileset.padding,
+                texture: None,
+            })
+        })
+        .collect();
+
+    Ok(LdtkProject {
+        world_layout: parse_world_layout(world_layout.as_ref().map(String::as_str))?,
+        tilesets,
+        levels: levels
+            .into_iter()
+            .map(JsonLevel::into_level)
+            .collect::<Result<_, String>>()?,
+    })
+}
+
+/// Parse a level saved in a separate file (.ldtkl).
+pub fn parse_ldtk_level(json: &str) -> Result<LdtkLevel, String> {
+    let level: JsonLevel = serde_json::from_str(json).map_err(|e| format!("{}", e))?;
+    level.into_level()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const PROJECT: &str = r##"{
+        "jsonVersion": "1.1.3",
+        "worldLayout": "GridVania",
+        "defs": {
+            "tilesets": [
+                {"uid": 1, "identifier": "Cavernas", "relPath": "atlas/cavernas.png",
+                 "tileGridSize": 8, "spacing": 0, "padding": 0},
+                {"uid": 2, "identifier": "Internal_Icons", "relPath": null, "tileGridSize": 16}
+            ]
+        },
+        "levels": [{
+            "identifier": "Entrance", "iid": "a1", "uid": 0,
+            "worldX": 0, "worldY": 256, "pxWid": 64, "pxHei": 32, "__bgColor": "#40465B",
+            "fieldInstances": [{"__identifier": "music", "__type": "LocalEnum.Music", "__value": "Calm"}],
+            "__neighbours": [{"levelIid": "b2", "dir": "e"}],
+            "layerInstances": [
+                {"__identifier": "Entities", "__type": "Entities", "__cWid": 8, "__cHei": 4,
+                 "__gridSize": 8, "__tilesetDefUid": null,
+                 "entityInstances": [{
+                    "__identifier": "Door", "iid": "d1", "__grid": [2, 3], "__pivot": [0.5, 1],
+                    "__tags": ["interactive"], "px": [20, 32], "width": 8, "height": 16,
+                    "fieldInstances": [
+                        {"__identifier": "locked", "__type": "Bool", "__value": true},
+                        {"__identifier": "target", "__type": "EntityRef",
+                         "__value": {"entityIid": "d2", "layerIid": "l", "levelIid": "b2", "worldIid": "w"}},
+                        {"__identifier": "path", "__type": "Array<Point>",
+                         "__value": [{"cx": 1, "cy": 2}, {"cx": 3, "cy": 2}]},
+                        {"__identifier": "key", "__type": "String", "__value": null}
+                    ]
+                 }]},
+                {"__identifier": "Walls", "__type": "IntGrid", "__cWid": 2, "__cHei": 1,
+                 "__gridSize": 8, "__pxTotalOffsetX": 0, "__pxTotalOffsetY": 0,
+                 "__tilesetDefUid": 1, "intGridCsv": [1, 0],
+                 "autoLayerTiles": [{"px": [0, 0], "src": [16, 8], "f": 2, "t": 12}]}
+            ]
+        }, {
+            "identifier": "Hall", "iid": "b2", "uid": 1, "worldX": 64, "worldY": 256,
+            "pxWid": 64, "pxHei": 32, "__bgColor": "#40465B", "layerInstances": null,
+            "externalRelPath": "world/Hall.ldtkl"
+        }]
+    }"##;
+
+    #[test]
+    fn test_parse_project() {
+        let project = parse_ldtk_project(PROJECT).unwrap();
+        assert_eq!(project.world_layout, WorldLayout::GridVania);
+        assert_eq!(project.tilesets.len(), 1);
+
+        let entrance = project.level("Entrance").unwrap();
+        assert_eq!(entrance.world_rect(), Rect::new(0, 256, 64, 32));
+        assert_eq!(entrance.neighbours, vec![("b2".to_string(), 'e')]);
+        assert_eq!(
+            entrance.fields["music"],
+            FieldValue::Enum("Calm".to_string())
+        );
+
+        let walls = entrance.layer("Walls").unwrap();
+        assert_eq!(walls.kind, LayerKind::IntGrid);
+        assert_eq!(walls.int_value(0, 0), 1);
+        assert_eq!(
+            walls.tiles,
+            vec![LdtkTile {
+                position: (0, 0),
+                source: (16, 8),
+                flip_x: false,
+                flip_y: true,
+            }]
+        );
+
+        let door = entrance.entities("Door").next().unwrap();
+        assert_eq!(door.position, (20, 32));
+        assert_eq!(door.fields["locked"].as_bool(), Some(true));
+        assert_eq!(door.fields["target"].as_str(), Some("d2"));
+        assert_eq!(
+            door.fields["path"],
+            FieldValue::Array(vec![FieldValue::Point(1, 2), FieldValue::Point(3, 2)])
+        );
+        assert_eq!(door.fields["key"], FieldValue::Null);
+
+        let hall = project.level_by_iid("b2").unwrap();
+        assert_eq!(hall.external, Some("world/Hall.ldtkl".to_string()));
+        assert!(hall.layers.is_empty());
+    }
+
+    #[test]
+    fn test_invalid_field() {
+        assert!(parse_field("Int", Value::from("three")).is_err());
+        assert!(parse_field("Unknown", Value::from(3)).is_err());
+        assert_eq!(
+            parse_field("Float", Value::from(2.5)),
+            Ok(FieldValue::Float(2.5))
+        );
+    }
+}
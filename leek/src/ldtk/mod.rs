@@ -0,0 +1,382 @@
+use sdl2::rect::Rect;
+use sdl2::render::{Texture, WindowCanvas};
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use camera::CameraView;
+
+mod json;
+
+pub use self::json::{parse_ldtk_level, parse_ldtk_project};
+
+/// Value of an entity or level field.
+#[derive(Clone, Debug, PartialEq)]
+pub enum FieldValue {
+    Null,
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    String(String),
+    /// Color as written by LDtk (#RRGGBB).
+    Color(String),
+    /// Position in grid cells.
+    Point(i32, i32),
+    Enum(String),
+    /// Path relative to the project.
+    FilePath(String),
+    /// Iid of the referenced entity.
+    EntityRef(String),
+    Tile {
+        tileset_uid: i64,
+        rect: Rect,
+    },
+    Array(Vec<FieldValue>),
+}
+
+impl FieldValue {
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            FieldValue::Bool(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_int(&self) -> Option<i64> {
+        match *self {
+            FieldValue::Int(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Int fields are converted too.
+    pub fn as_float(&self) -> Option<f64> {
+        match *self {
+            FieldValue::Float(value) => Some(value),
+            FieldValue::Int(value) => Some(value as f64),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            FieldValue::String(ref value)
+            | FieldValue::Color(ref value)
+            | FieldValue::Enum(ref value)
+            | FieldValue::FilePath(ref value)
+            | FieldValue::EntityRef(ref value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+pub type Fields = HashMap<String, FieldValue>;
+
+#[derive(Clone)]
+pub struct LdtkTileset {
+    pub uid: i64,
+    pub identifier: String,
+    /// Path of the image, relative to the project until loaded by `Resources`.
+    pub image: String,
+    pub tile_size: u32,
+    pub spacing: u32,
+    pub padding: u32,
+    /// Set by `Resources` when loading the project.
+    pub texture: Option<Arc<Texture>>,
+}
+
+/// A tile placed in a layer.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LdtkTile {
+    /// Position in pixels, relative to the layer.
+    pub position: (i32, i32),
+    /// Position of the tile in the tileset image.
+    pub source: (i32, i32),
+    pub flip_x: bool,
+    pub flip_y: bool,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LayerKind {
+    IntGrid,
+    Entities,
+    Tiles,
+    AutoLayer,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EntityInstance {
+    pub identifier: String,
+    pub iid: String,
+    /// Cell of the pivot.
+    pub grid: (i32, i32),
+    /// Position of the pivot in pixels, relative to the layer.
+    pub position: (i32, i32),
+    pub size: (u32, u32),
+    /// Normalized position of the pivot inside the entity.
+    pub pivot: (f32, f32),
+    pub tags: Vec<String>,
+    pub fields: Fields,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LdtkLayer {
+    pub identifier: String,
+    pub kind: LayerKind,
+    pub grid_size: u32,
+    /// Size in cells.
+    pub size: (u32, u32),
+    /// Offset in pixels.
+    pub offset: (i32, i32),
+    pub opacity: f32,
+    pub visible: bool,
+    pub tileset_uid: Option<i64>,
+    /// Values of an IntGrid layer row by row, 0 for empty cells.
+    pub int_grid: Vec<i32>,
+    /// Tiles of a Tiles or auto layer, in drawing order.
+    pub tiles: Vec<LdtkTile>,
+    pub entities: Vec<EntityInstance>,
+}
+
+impl LdtkLayer {
+    /// Value of an IntGrid cell, 0 when empty or outside of the layer.
+    pub fn int_value(&self, x: i32, y: i32) -> i32 {
+        if x < 0 || y < 0 || x as u32 >= self.size.0 || y as u32 >= self.size.1 {
+            return 0;
+        }
+        self.int_grid
+            .get((y as u32 * self.size.0 + x as u32) as usize)
+            .cloned()
+            .unwrap_or(0)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LdtkLevel {
+    pub identifier: String,
+    pub iid: String,
+    pub uid: i64,
+    /// Position of the top left corner in the world, in pixels.
+    pub world_position: (i32, i32),
+    /// Size in pixels.
+    pub size: (u32, u32),
+    pub background_color: Option<String>,
+    pub fields: Fields,
+    /// Layers from the top one to the bottom one, like in LDtk.
+    pub layers: Vec<LdtkLayer>,
+    /// Iids of the touching levels, with their direction ('n', 's', 'e' or 'w').
+    pub neighbours: Vec<(String, char)>,
+    /// Path of the level file when saved separately from the project.
+    pub external: Option<String>,
+}
+
+impl LdtkLevel {
+    /// Area of the level in the world.
+    pub fn world_rect(&self) -> Rect {
+        Rect::new(
+            self.world_position.0,
+            self.world_position.1,
+            self.size.0,
+            self.size.1,
+        )
+    }
+
+    pub fn layer(&self, identifier: &str) -> Option<&LdtkLayer> {
+        self.layers
+            .iter()
+            .find(|layer| layer.identifier == identifier)
+    }
+
+    /// Entities of every layer with the given identifier.
+    pub fn entities<'a>(
+        &'a self,
+        identifier: &'a str,
+    ) -> impl Iterator<Item = &'a EntityInstance> + 'a {
+        self.layers
+            .iter()
+            .flat_map(|layer| layer.entities.iter())
+            .filter(move |entity| entity.identifier == identifier)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum WorldLayout {
+    Free,
+    GridVania,
+    LinearHorizontal,
+    LinearVertical,
+}
+
+/// An LDtk project with its levels.
+#[derive(Clone)]
+pub struct LdtkProject {
+    pub world_layout: WorldLayout,
+    pub tilesets: Vec<LdtkTileset>,
+    pub levels: Vec<LdtkLevel>,
+}
+
+impl LdtkProject {
+    pub fn level(&self, identifier: &str) -> Option<&LdtkLevel> {
+        self.levels
+            .iter()
+            .find(|level| level.identifier == identifier)
+    }
+
+    pub fn level_by_iid(&self, iid: &str) -> Option<&LdtkLevel> {
+        self.levels.iter().find(|level| level.iid == iid)
+    }
+
+    pub fn tileset(&self, uid: i64) -> Option<&LdtkTileset> {
+        self.tilesets.iter().find(|tileset| tileset.uid == uid)
+    }
+
+    /// Levels that can be seen in the world rect `view`.
+    pub fn visible_levels<'a>(&'a self, view: Rect) -> impl Iterator<Item = &'a LdtkLevel> + 'a {
+        self.levels
+            .iter()
+            .filter(move |level| level.world_rect().has_intersection(view))
+    }
+
+    pub fn vram_size(&self) -> usize {
+        self.tilesets
+            .iter()
+            .filter_map(|tileset| tileset.texture.as_ref())
+            .map(|texture| {
+                let tex_query = texture.query();
+
+                let pixels = tex_query.width * tex_query.height;
+                tex_query.format.byte_size_of_pixels(pixels as usize)
+            })
+            .sum()
+    }
+
+    /// Call `draw_tile` for the tiles of `level` inside the world rect `view`, bottom layer first.
+    fn visit_tiles<F>(&self, level: &LdtkLevel, view: Rect, mut draw_tile: F) -> Result<(), String>
+    where
+        F: FnMut(&Texture, Rect, Rect, &LdtkTile) -> Result<(), String>,
+    {
+        for layer in level.layers.iter().rev().filter(|layer| layer.visible) {
+            let tileset = match layer.tileset_uid.and_then(|uid| self.tileset(uid)) {
+                Some(tileset) => tileset,
+                None => continue,
+            };
+            let texture = match tileset.texture {
+                Some(ref texture) => texture,
+                None => continue,
+            };
+            let size = tileset.tile_size;
+            let origin = (
+                level.world_position.0 + layer.offset.0,
+                level.world_position.1 + layer.offset.1,
+            );
+
+            for tile in &layer.tiles {
+                let dest = Rect::new(
+                    origin.0 + tile.position.0,
+                    origin.1 + tile.position.1,
+                    size,
+                    size,
+                );
+                if dest.has_intersection(view) {
+                    let source = Rect::new(tile.source.0, tile.source.1, size, size);
+                    draw_tile(texture, source, dest, tile)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Draw the part of a level inside the world rect `view`,
+    /// the top left corner of `view` is drawn at (0, 0).
+    pub fn draw_level(
+        &self,
+        renderer: &mut WindowCanvas,
+        level: &LdtkLevel,
+        view: Rect,
+    ) -> Result<(), String> {
+        self.visit_tiles(level, view, |texture, source, dest, tile| {
+            let dest = Rect::new(
+                dest.x() - view.x(),
+                dest.y() - view.y(),
+                dest.width(),
+                dest.height(),
+            );
+            renderer.copy_ex(texture, source, dest, 0.0, None, tile.flip_x, tile.flip_y)
+        })
+    }
+
+    /// Draw every level that can be seen in `view`, see `draw_level`.
+    pub fn draw(&self, renderer: &mut WindowCanvas, view: Rect) -> Result<(), String> {
+        for level in self.visible_levels(view) {
+            self.draw_level(renderer, level, view)?;
+        }
+        Ok(())
+    }
+
+    /// Draw every level through a camera, only the tiles it can see are drawn.
+    pub fn draw_with_camera(&self, view: &mut CameraView) -> Result<(), String> {
+        let visible = view.camera().visible_bounds();
+        for level in self.visible_levels(visible) {
+            self.visit_tiles(level, visible, |texture, source, dest, tile| {
+                view.copy_ex(texture, source, dest, 0.0, None, tile.flip_x, tile.flip_y)
+            })?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn level(identifier: &str, world_position: (i32, i32)) -> LdtkLevel {
+        LdtkLevel {
+            identifier: identifier.to_string(),
+            iid: identifier.to_lowercase(),
+            uid: 0,
+            world_position,
+            size: (256, 128),
+            background_color: None,
+            fields: Fields::new(),
+            layers: Vec::new(),
+            neighbours: Vec::new(),
+            external: None,
+        }
+    }
+
+    #[test]
+    fn test_visible_levels() {
+        let project = LdtkProject {
+            world_layout: WorldLayout::LinearHorizontal,
+            tilesets: Vec::new(),
+            levels: vec![level("Start", (0, 0)), level("Cave", (256, 0))],
+        };
+        let visible: Vec<&str> = project
+            .visible_levels(Rect::new(300, 0, 100, 100))
+            .map(|level| level.identifier.as_str())
+            .collect();
+        assert_eq!(visible, vec!["Cave"]);
+        assert_eq!(project.level_by_iid("start").unwrap().identifier, "Start");
+    }
+
+    #[test]
+    fn test_int_grid() {
+        let layer = LdtkLayer {
+            identifier: "Collisions".to_string(),
+            kind: LayerKind::IntGrid,
+            grid_size: 16,
+            size: (3, 2),
+            offset: (0, 0),
+            opacity: 1.0,
+            visible: true,
+            tileset_uid: None,
+            int_grid: vec![0, 1, 0, 2, 2, 0],
+            tiles: Vec::new(),
+            entities: Vec::new(),
+        };
+        assert_eq!(layer.int_value(1, 0), 1);
+        assert_eq!(layer.int_value(1, 1), 2);
+        assert_eq!(layer.int_value(3, 0), 0);
+        assert_eq!(layer.int_value(-1, 0), 0);
+    }
+}
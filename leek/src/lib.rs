@@ -33,6 +33,11 @@ pub extern crate alto;
 extern crate failure;
 pub extern crate lewton;
 extern crate notify;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+extern crate xml;
 
 #[macro_use]
 pub extern crate imgui;
@@ -40,34 +45,52 @@ pub extern crate imgui;
 use sdl2::pixels::Color;
 
 pub mod alto_utils;
+pub mod animation;
+pub mod atlas;
+pub mod camera;
+pub mod draw_queue;
 mod engine;
 mod fps_counter;
 mod game_controllers;
 mod imgui_backend;
 mod opengl;
+pub mod pixel_perfect;
 mod post_processing;
 pub mod prelude;
 mod sdl2_utils;
+pub mod shapes;
 
 mod debug;
+pub mod ldtk;
+pub mod nine_slice;
+pub mod particles;
 pub mod resources;
+pub mod tiled;
 
 #[macro_use]
 mod common_macros;
 
-pub use post_processing::PostProcessEffect as PostProcessingEffect;
+pub use post_processing::{effects, PassParam, PostProcessPass, PostProcessPipeline,
+                          ShaderSource};
+pub use opengl::{GlslSource, ShaderDiagnostic, ShaderError, ShaderStage};
 
 pub mod math;
 pub use engine::game::{AnyGameScene, FromEngine, GameScene};
-pub use game_controllers::{GameController, GameControllerManager};
+pub use game_controllers::{ControllerState, Deadzones, GameController, GameControllerManager,
+                           HapticEffect, HapticSettings, JoinPolicy, JoystickInfo, LeavePolicy,
+                           MappingStatus, OnScreenButton, OnScreenGamepad, OnScreenStick,
+                           RumblePattern, RumbleStep, VirtualGamepad, Waveform, MAX_PLAYERS};
 
 pub use engine::action::EngineAction;
-pub use engine::context::EngineContext;
+pub use engine::combo::{Combo, ComboDetector, ComboInput, ComboMatch, Direction};
+pub use engine::context::{EngineContext, FrameInput};
+pub use engine::touch::{Gesture, TouchPoint};
 pub use engine::Engine;
 
 pub use sdl2_utils::log_system_info;
 
 pub mod font;
+pub mod text_input;
 
 const WINDOW_SIZE: (u32, u32) = (800, 600);
 const CLEAR_COLOR: Color = Color {
@@ -79,11 +102,16 @@ const CLEAR_COLOR: Color = Color {
 
 pub struct EngineBuilder<'window> {
     window_title: &'window str,
+    organization: &'window str,
     window_size: (u32, u32),
     logical_size: Option<(u32, u32)>,
+    pixel_perfect: Option<(u32, u32)>,
+    letterbox_color: Color,
+    post_process_passes: Vec<Box<Fn() -> Result<PostProcessPass, String> + 'window>>,
     fullscreen: bool,
     hide_cursor: bool,
     relative_cursor: bool,
+    touch_mouse_events: bool,
     clear_color: Color,
     imgui_font_scale: f32,
 }
@@ -92,12 +120,17 @@ impl Engine {
     pub fn new(window_title: &str) -> EngineBuilder {
         EngineBuilder {
             window_title,
+            organization: "leek",
             window_size: WINDOW_SIZE,
             logical_size: None,
+            pixel_perfect: None,
+            letterbox_color: Color::RGB(0, 0, 0),
+            post_process_passes: Vec::new(),
             clear_color: CLEAR_COLOR,
             fullscreen: false,
             hide_cursor: false,
             relative_cursor: false,
+            touch_mouse_events: true,
             imgui_font_scale: 1.5,
         }
     }
@@ -116,6 +149,45 @@ impl<'window> EngineBuilder<'window> {
         self
     }
 
+    /// Render at this size in an offscreen texture, drawn at the largest integer scale that
+    /// fits the window. Keeps pixel art sharp, replaces `with_logical_size`.
+    pub fn with_pixel_perfect(&mut self, width: u32, height: u32) -> &mut Self {
+        self.pixel_perfect = Some((width, height));
+        self
+    }
+
+    /// Color around the game in pixel perfect mode, see `PixelPerfect` for border art.
+    pub fn with_letterbox_color(&mut self, color: Color) -> &mut Self {
+        self.letterbox_color = color;
+        self
+    }
+
+    /// Add a pass to `Engine::post_processing`, see `PostProcessPass` for the fragment shader.
+    pub fn with_post_process_pass(
+        &mut self,
+        name: &'window str,
+        fragment_source: &'window str,
+    ) -> &mut Self {
+        self.post_process_passes
+            .push(Box::new(move || PostProcessPass::new(name, fragment_source)));
+        self
+    }
+
+    /// Add a pass from `post_processing::effects`, like `effects::crt`.
+    pub fn with_post_process_effect(
+        &mut self,
+        effect: fn() -> Result<PostProcessPass, String>,
+    ) -> &mut Self {
+        self.post_process_passes.push(Box::new(effect));
+        self
+    }
+
+    /// Set the organization name, used together with the window title to locate the settings directory.
+    pub fn with_organization(&mut self, organization: &'window str) -> &mut Self {
+        self.organization = organization;
+        self
+    }
+
     pub fn with_clear_color(&mut self, color: Color) -> &mut Self {
         self.clear_color = color;
         self
@@ -141,6 +213,12 @@ impl<'window> EngineBuilder<'window> {
         self
     }
 
+    /// Let SDL emulate the mouse with the primary touch (enabled by default).
+    pub fn with_touch_mouse_events(&mut self, touch_mouse_events: bool) -> &mut Self {
+        self.touch_mouse_events = touch_mouse_events;
+        self
+    }
+
     /// Start the engine.
     pub fn start<Scene: 'static>(&mut self)
     where
@@ -155,5 +233,5 @@ impl<'window> EngineBuilder<'window> {
 // RE-EXPORTS
 
 pub mod keyboard {
-    pub use sdl2::keyboard::Scancode;
+    pub use sdl2::keyboard::{Keycode, Mod, Scancode};
 }
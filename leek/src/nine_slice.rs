@@ -0,0 +1,234 @@
+use sdl2::rect::Rect;
+use sdl2::render::{Texture, WindowCanvas};
+
+use std::sync::Arc;
+
+use camera::CameraView;
+
+/// Size of the borders of a nine-slice, in pixels of the texture.
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, PartialOrd)]
+pub struct Insets {
+    pub left: u32,
+    pub top: u32,
+    pub right: u32,
+    pub bottom: u32,
+}
+
+impl Insets {
+    pub fn new(left: u32, top: u32, right: u32, bottom: u32) -> Self {
+        Insets {
+            left,
+            top,
+            right,
+            bottom,
+        }
+    }
+
+    pub fn uniform(size: u32) -> Self {
+        Insets::new(size, size, size, size)
+    }
+}
+
+/// How the edges and the center fill the space between the corners.
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, PartialOrd)]
+pub enum EdgeMode {
+    Stretch,
+    /// Repeat the texture, the last repetition is cut.
+    Tile,
+}
+
+/// A texture split in nine parts to draw frames of any size: the corners keep their size,
+/// the edges and the center fill the rest.
+pub struct NineSlice {
+    texture: Arc<Texture>,
+    texture_path: String,
+    size: (u32, u32),
+    insets: Insets,
+    mode: EdgeMode,
+}
+
+impl NineSlice {
+    pub fn new(texture: Arc<Texture>, texture_path: &str, insets: Insets, mode: EdgeMode) -> Self {
+        let query = texture.query();
+        NineSlice {
+            texture,
+            texture_path: texture_path.to_string(),
+            size: (query.width, query.height),
+            insets,
+            mode,
+        }
+    }
+
+    pub fn texture_path(&self) -> &str {
+        &self.texture_path
+    }
+
+    pub fn insets(&self) -> Insets {
+        self.insets
+    }
+
+    pub fn mode(&self) -> EdgeMode {
+        self.mode
+    }
+
+    /// Smallest size drawn without shrinking the corners.
+    pub fn min_size(&self) -> (u32, u32) {
+        (
+            self.insets.left + self.insets.right,
+            self.insets.top + self.insets.bottom,
+        )
+    }
+
+    pub fn vram_size(&self) -> usize {
+        let tex_query = self.texture.query();
+
+        let pixels = tex_query.width * tex_query.height;
+        tex_query.format.byte_size_of_pixels(pixels as usize)
+    }
+
+    /// Source and destination rect of every piece needed to fill `dest`.
+    pub fn patches(&self, dest: Rect) -> Vec<(Rect, Rect)> {
+        patches(self.size, self.insets, self.mode, dest)
+    }
+
+    pub fn draw(&self, renderer: &mut WindowCanvas, dest: Rect) -> Result<(), String> {
+        for (src, dst) in self.patches(dest) {
+            renderer.copy(&self.texture, src, dst)?;
+        }
+        Ok(())
+    }
+
+    /// Draw with `dest` in world coordinates.
+    pub fn draw_with_camera(&self, view: &mut CameraView, dest: Rect) -> Result<(), String> {
+        if !view.camera().is_visible(dest) {
+            return Ok(());
+        }
+        for (src, dst) in self.patches(dest) {
+            view.copy(&self.texture, src, dst)?;
+        }
+        Ok(())
+    }
+}
+
+/// Part of one axis, as (source start, source length, destination start, destination length).
+type Span = (i32, u32, i32, u32);
+
+/// Split one axis in its start, middle and end parts.
+fn axis_spans(size: u32, start: u32, end: u32, dest: i32, dest_len: u32, tile: bool) -> Vec<Span> {
+    let (start, end) = if start + end > size {
+        (size / 2, size - size / 2)
+    } else {
+        (start, end)
+    };
+    // Shrink the corners when the destination is too small for them
+    let (dest_start, dest_end) = if start + end > dest_len {
+        let dest_start = dest_len * start / (start + end);
+        (dest_start, dest_len - dest_start)
+    } else {
+        (start, end)
+    };
+    let middle = size - start - end;
+    let dest_middle = dest_len - dest_start - dest_end;
+
+    let mut spans = vec![(0, start, dest, dest_start)];
+    let middle_dest = dest + dest_start as i32;
+    if tile && middle > 0 {
+        let mut offset = 0;
+        while offset < dest_middle {
+            let len = middle.min(dest_middle - offset);
+            spans.push((start as i32, len, middle_dest + offset as i32, len));
+            offset += len;
+        }
+    } else {
+        spans.push((start as i32, middle, middle_dest, dest_middle));
+    }
+    spans.push((
+        (size - end) as i32,
+        end,
+        middle_dest + dest_middle as i32,
+        dest_end,
+    ));
+
+    spans.retain(|&(_, len, _, dest_len)| len > 0 && dest_len > 0);
+    spans
+}
+
+fn patches(size: (u32, u32), insets: Insets, mode: EdgeMode, dest: Rect) -> Vec<(Rect, Rect)> {
+    let tile = mode == EdgeMode::Tile;
+    let columns = axis_spans(
+        size.0,
+        insets.left,
+        insets.right,
+        dest.x(),
+        dest.width(),
+        tile,
+    );
+    let rows = axis_spans(
+        size.1,
+        insets.top,
+        insets.bottom,
+        dest.y(),
+        dest.height(),
+        tile,
+    );
+
+    let mut patches = Vec::with_capacity(columns.len() * rows.len());
+    for &(src_y, src_h, dst_y, dst_h) in &rows {
+        for &(src_x, src_w, dst_x, dst_w) in &columns {
+            patches.push((
+                Rect::new(src_x, src_y, src_w, src_h),
+                Rect::new(dst_x, dst_y, dst_w, dst_h),
+            ));
+        }
+    }
+    patches
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_stretch() {
+        let patches = patches(
+            (24, 24),
+            Insets::uniform(8),
+            EdgeMode::Stretch,
+            Rect::new(10, 10, 100, 50),
+        );
+        assert_eq!(patches.len(), 9);
+        // Corners keep their size
+        assert_eq!(patches[0], (Rect::new(0, 0, 8, 8), Rect::new(10, 10, 8, 8)));
+        assert_eq!(
+            patches[8],
+            (Rect::new(16, 16, 8, 8), Rect::new(102, 52, 8, 8))
+        );
+        // The center fills the rest
+        assert_eq!(
+            patches[4],
+            (Rect::new(8, 8, 8, 8), Rect::new(18, 18, 84, 34))
+        );
+    }
+
+    #[test]
+    fn test_tile() {
+        let spans = axis_spans(24, 8, 8, 0, 36, true);
+        assert_eq!(
+            spans,
+            vec![
+                (0, 8, 0, 8),
+                (8, 8, 8, 8),
+                (8, 8, 16, 8),
+                (8, 4, 24, 4),
+                (16, 8, 28, 8)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_small_destination() {
+        // The corners shrink and there is no middle
+        let spans = axis_spans(24, 8, 8, 0, 10, false);
+        assert_eq!(spans, vec![(0, 8, 0, 5), (16, 8, 5, 5)]);
+    }
+}
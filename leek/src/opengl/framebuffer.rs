@@ -0,0 +1,85 @@
+use gl;
+use gl::types::*;
+
+use std::ptr;
+
+/// Framebuffer object rendering into its own RGBA texture.
+#[derive(Debug)]
+pub struct Framebuffer {
+    fbo: GLuint,
+    texture: GLuint,
+    size: (u32, u32),
+}
+
+impl Framebuffer {
+    pub fn new(width: u32, height: u32) -> Result<Framebuffer, String> {
+        unsafe {
+            let mut texture = 0;
+            gl::GenTextures(1, &mut texture);
+            gl::BindTexture(gl::TEXTURE_2D, texture);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::RGBA as i32,
+                width as i32,
+                height as i32,
+                0,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                ptr::null(),
+            );
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+
+            let mut fbo = 0;
+            gl::GenFramebuffers(1, &mut fbo);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, fbo);
+            gl::FramebufferTexture2D(
+                gl::FRAMEBUFFER,
+                gl::COLOR_ATTACHMENT0,
+                gl::TEXTURE_2D,
+                texture,
+                0,
+            );
+            let status = gl::CheckFramebufferStatus(gl::FRAMEBUFFER);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+
+            let framebuffer = Framebuffer {
+                fbo,
+                texture,
+                size: (width, height),
+            };
+            if status != gl::FRAMEBUFFER_COMPLETE {
+                return Err(format!("Incomplete framebuffer {}: status {}", fbo, status));
+            }
+            Ok(framebuffer)
+        }
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        self.size
+    }
+
+    /// Draw into this framebuffer, the viewport covers all of it.
+    pub unsafe fn bind(&self) {
+        gl::BindFramebuffer(gl::FRAMEBUFFER, self.fbo);
+        gl::Viewport(0, 0, self.size.0 as i32, self.size.1 as i32);
+    }
+
+    /// Bind the texture to the active texture unit.
+    pub unsafe fn bind_texture(&self) {
+        gl::BindTexture(gl::TEXTURE_2D, self.texture);
+    }
+}
+
+impl Drop for Framebuffer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteFramebuffers(1, &self.fbo);
+            gl::DeleteTextures(1, &self.texture);
+        }
+    }
+}
@@ -5,15 +5,23 @@ use gl::types::GLfloat;
 use std::mem;
 
 mod enums;
+mod framebuffer;
+mod preprocessor;
 mod shader;
+mod shader_error;
 mod state;
 mod texture;
+mod uniform;
 mod vertex_attrib;
 mod vertex_buffer;
 pub use self::enums::*;
+pub use self::framebuffer::*;
+pub use self::preprocessor::*;
 pub use self::shader::*;
+pub use self::shader_error::*;
 pub use self::state::*;
 pub use self::texture::*;
+pub use self::uniform::*;
 pub use self::vertex_attrib::*;
 pub use self::vertex_buffer::*;
 
@@ -0,0 +1,203 @@
+/// Folder of the paths given to `#include`.
+pub const INCLUDE_ROOT: &str = "assets";
+
+/// GLSL source with its `#include "file"` lines replaced by the files, relative to `assets/`.
+///
+/// Each file is included once, even when several files include it. The source remembers the
+/// file and line of each of its lines, see `origin`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GlslSource {
+    source: String,
+    /// The root file followed by the included ones.
+    files: Vec<String>,
+    /// Index in `files` and line of each line of `source`, `None` for the injected defines.
+    lines: Vec<Option<(usize, usize)>>,
+}
+
+impl GlslSource {
+    /// Expand the includes of `source`, the file at `path`, `read` gives the content of an
+    /// included file from its path like `assets/shaders/noise.glsl`.
+    pub fn preprocess<F>(path: &str, source: &str, mut read: F) -> Result<GlslSource, String>
+    where
+        F: FnMut(&str) -> Result<String, String>,
+    {
+        let mut glsl = GlslSource {
+            source: String::with_capacity(source.len()),
+            files: vec![path.to_string()],
+            lines: Vec::new(),
+        };
+        let mut stack = vec![path.to_string()];
+        glsl.expand(0, source, &mut stack, &mut read)?;
+        Ok(glsl)
+    }
+
+    fn expand<F>(
+        &mut self,
+        file: usize,
+        source: &str,
+        stack: &mut Vec<String>,
+        read: &mut F,
+    ) -> Result<(), String>
+    where
+        F: FnMut(&str) -> Result<String, String>,
+    {
+        for (index, line) in source.lines().enumerate() {
+            let location = format!("{}:{}", self.files[file], index + 1);
+            let name = match include_name(line) {
+                Some(Ok(name)) => name,
+                Some(Err(())) => return Err(format!("{}: expected #include \"file\"", location)),
+                None => {
+                    self.source.push_str(line);
+                    self.source.push('\n');
+                    self.lines.push(Some((file, index + 1)));
+                    continue;
+                }
+            };
+
+            let path = format!("{}/{}", INCLUDE_ROOT, name);
+            if stack.contains(&path) {
+                return Err(format!(
+                    "{}: include cycle {} -> {}",
+                    location,
+                    stack.join(" -> "),
+                    path
+                ));
+            }
+            if self.files.contains(&path) {
+                continue;
+            }
+            let included = read(&path).map_err(|e| format!("{}: {}", location, e))?;
+            self.files.push(path.clone());
+            let included_file = self.files.len() - 1;
+            stack.push(path);
+            self.expand(included_file, &included, stack, read)?;
+            stack.pop();
+        }
+        Ok(())
+    }
+
+    /// Copy of the source with a `#define name value` for each of `defines`, after `#version`.
+    pub fn with_defines(&self, defines: &[(&str, &str)]) -> GlslSource {
+        let lines: Vec<&str> = self.source.lines().collect();
+        let at = lines
+            .iter()
+            .position(|line| line.trim().starts_with("#version"))
+            .map_or(0, |index| index + 1);
+
+        let mut source = String::with_capacity(self.source.len());
+        for line in &lines[..at] {
+            source.push_str(line);
+            source.push('\n');
+        }
+        for &(name, value) in defines {
+            source.push_str(&format!("#define {} {}\n", name, value));
+        }
+        for line in &lines[at..] {
+            source.push_str(line);
+            source.push('\n');
+        }
+
+        let mut line_map = self.lines[..at].to_vec();
+        line_map.extend(defines.iter().map(|_| None));
+        line_map.extend_from_slice(&self.lines[at..]);
+        GlslSource {
+            source,
+            files: self.files.clone(),
+            lines: line_map,
+        }
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn path(&self) -> &str {
+        &self.files[0]
+    }
+
+    /// Paths of the included files, the source must be expanded again when they change.
+    pub fn includes(&self) -> &[String] {
+        &self.files[1..]
+    }
+
+    /// File and line of the line `line` of the expanded source, starting at 1.
+    pub fn origin(&self, line: usize) -> Option<(&str, usize)> {
+        let (file, line) = (*self.lines.get(line.checked_sub(1)?)?)?;
+        Some((self.files[file].as_str(), line))
+    }
+}
+
+/// `Some(Ok(name))` for `#include "name"`, `Some(Err(()))` for another `#include`.
+fn include_name(line: &str) -> Option<Result<&str, ()>> {
+    let line = line.trim();
+    if !line.starts_with("#include") {
+        return None;
+    }
+    let name = line["#include".len()..].trim();
+    if name.len() > 2 && name.starts_with('"') && name.ends_with('"') {
+        Some(Ok(&name[1..name.len() - 1]))
+    } else {
+        Some(Err(()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn read(path: &str) -> Result<String, String> {
+        match path {
+            "assets/shaders/color.glsl" => {
+                Ok("#include \"shaders/math.glsl\"\nvec3 tint;\n".into())
+            }
+            "assets/shaders/math.glsl" => Ok("float pi;\n".into()),
+            "assets/shaders/loop.glsl" => Ok("#include \"shaders/loop.glsl\"\n".into()),
+            _ => Err(format!("{}: not found", path)),
+        }
+    }
+
+    #[test]
+    fn test_includes() {
+        let source = "#version 130\n#include \"shaders/color.glsl\"\n\
+                      #include \"shaders/math.glsl\"\nvoid main() {}\n";
+        let glsl = GlslSource::preprocess("assets/shaders/a.frag", source, read).unwrap();
+        assert_eq!(
+            glsl.source(),
+            "#version 130\nfloat pi;\nvec3 tint;\nvoid main() {}\n"
+        );
+        assert_eq!(
+            glsl.includes(),
+            ["assets/shaders/color.glsl", "assets/shaders/math.glsl"]
+        );
+        assert_eq!(glsl.origin(2), Some(("assets/shaders/math.glsl", 1)));
+        assert_eq!(glsl.origin(3), Some(("assets/shaders/color.glsl", 2)));
+        assert_eq!(glsl.origin(4), Some(("assets/shaders/a.frag", 4)));
+        assert_eq!(glsl.origin(5), None);
+    }
+
+    #[test]
+    fn test_include_errors() {
+        let cycle = GlslSource::preprocess("a.frag", "#include \"shaders/loop.glsl\"", read);
+        assert!(cycle.unwrap_err().contains("include cycle"));
+        let missing = GlslSource::preprocess("a.frag", "\n#include \"nope.glsl\"", read);
+        assert_eq!(
+            missing.unwrap_err(),
+            "a.frag:2: assets/nope.glsl: not found"
+        );
+        assert!(GlslSource::preprocess("a.frag", "#include <x>", read).is_err());
+    }
+
+    #[test]
+    fn test_defines() {
+        let glsl = GlslSource::preprocess("a.frag", "#version 130\nvoid main() {}\n", read)
+            .unwrap()
+            .with_defines(&[("SAMPLES", "8"), ("HDR", "1")]);
+        assert_eq!(
+            glsl.source(),
+            "#version 130\n#define SAMPLES 8\n#define HDR 1\nvoid main() {}\n"
+        );
+        assert_eq!(glsl.origin(1), Some(("a.frag", 1)));
+        assert_eq!(glsl.origin(2), None);
+        assert_eq!(glsl.origin(4), Some(("a.frag", 2)));
+    }
+}
@@ -1,7 +1,12 @@
 use gl;
 use gl::types::*;
 
-use std::{ffi, ptr};
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::ffi;
+
+use super::shader_error::{ShaderError, ShaderStage};
+use super::uniform::{glsl_type_name, Uniform};
 
 #[derive(Clone, Copy, Debug)]
 pub struct UniformLocation(GLuint);
@@ -29,48 +34,168 @@ impl AttribLocation {
     }
 }
 
+/// Active uniform of a linked program.
+#[derive(Clone, Debug)]
+pub struct UniformInfo {
+    /// Name without the `[0]` of arrays.
+    pub name: String,
+    pub location: UniformLocation,
+    /// GLSL type, like `gl::FLOAT_VEC2`.
+    pub gl_type: GLenum,
+    /// Number of elements, more than 1 for arrays.
+    pub size: usize,
+}
+
+/// Active attribute of a linked program.
+#[derive(Clone, Debug)]
+pub struct AttribInfo {
+    pub name: String,
+    pub location: AttribLocation,
+    pub gl_type: GLenum,
+    pub size: usize,
+}
+
 #[derive(Debug)]
 pub struct Shader {
     raw_program: GLuint,
-    raw_vertex: GLuint,
-    raw_fragment: GLuint,
+    raw_shaders: Vec<GLuint>,
+    uniforms: Vec<UniformInfo>,
+    attributes: Vec<AttribInfo>,
+    /// Messages of `set_uniform` already printed, each is printed once.
+    warnings: RefCell<HashSet<String>>,
 }
 
 impl Drop for Shader {
     fn drop(&mut self) {
         unsafe {
-            gl::DetachShader(self.raw_program, self.raw_vertex);
-            gl::DetachShader(self.raw_program, self.raw_fragment);
+            for &shader in &self.raw_shaders {
+                gl::DetachShader(self.raw_program, shader);
+                gl::DeleteShader(shader);
+            }
             gl::DeleteProgram(self.raw_program);
-            gl::DeleteShader(self.raw_vertex);
-            gl::DeleteShader(self.raw_fragment);
         }
     }
 }
 
 impl Shader {
-    pub fn new(vertex_source: &str, fragment_source: &str) -> Result<Shader, String> {
-        unsafe {
-            let vertex_source =
-                ffi::CString::new(vertex_source.as_bytes()).map_err(|_| "CString Null error")?;
-            let fragment_source =
-                ffi::CString::new(fragment_source.as_bytes()).map_err(|_| "CString Null error")?;
+    pub fn new(vertex_source: &str, fragment_source: &str) -> Result<Shader, ShaderError> {
+        Shader::from_stages(&[
+            (ShaderStage::Vertex, &[vertex_source]),
+            (ShaderStage::Fragment, &[fragment_source]),
+        ])
+    }
 
-            let vertex_shader = create_shader(&vertex_source, gl::VERTEX_SHADER)?;
-            let fragment_shader = create_shader(&fragment_source, gl::FRAGMENT_SHADER)?;
+    pub fn with_geometry(
+        vertex_source: &str,
+        geometry_source: &str,
+        fragment_source: &str,
+    ) -> Result<Shader, ShaderError> {
+        Shader::from_stages(&[
+            (ShaderStage::Vertex, &[vertex_source]),
+            (ShaderStage::Geometry, &[geometry_source]),
+            (ShaderStage::Fragment, &[fragment_source]),
+        ])
+    }
 
-            let program = compile_program(vertex_shader, fragment_shader)?;
+    /// Link a program from the source strings of each stage, the strings of a stage are put
+    /// end to end, like a shared header followed by the main code.
+    pub fn from_stages(stages: &[(ShaderStage, &[&str])]) -> Result<Shader, ShaderError> {
+        unsafe {
+            let mut shaders = Vec::with_capacity(stages.len());
+            for &(stage, parts) in stages {
+                match create_shader(stage, parts) {
+                    Ok(shader) => shaders.push(shader),
+                    Err(error) => {
+                        delete_shaders(&shaders);
+                        return Err(error);
+                    }
+                }
+            }
+            let program = compile_program(&shaders).map_err(|error| {
+                delete_shaders(&shaders);
+                error
+            })?;
             Ok(Shader {
                 raw_program: program,
-                raw_vertex: vertex_shader,
-                raw_fragment: fragment_shader,
+                raw_shaders: shaders,
+                uniforms: active_uniforms(program),
+                attributes: active_attributes(program),
+                warnings: RefCell::new(HashSet::new()),
             })
         }
     }
+
     pub fn raw(&self) -> GLuint {
         self.raw_program
     }
 
+    /// Uniforms used by the program, the ones optimized out by the driver are missing.
+    pub fn uniforms(&self) -> &[UniformInfo] {
+        &self.uniforms
+    }
+
+    /// Attributes used by the program.
+    pub fn attributes(&self) -> &[AttribInfo] {
+        &self.attributes
+    }
+
+    pub fn uniform(&self, name: &str) -> Option<&UniformInfo> {
+        self.uniforms.iter().find(|uniform| uniform.name == name)
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&AttribInfo> {
+        self.attributes
+            .iter()
+            .find(|attribute| attribute.name == name)
+    }
+
+    pub fn has_uniform(&self, name: &str) -> bool {
+        self.uniform(name).is_some()
+    }
+
+    /// Set the uniform `name` of the program, which must be active.
+    ///
+    /// Returns `false` when the uniform is not used by the program or its type does not match
+    /// `value`, debug builds print a warning the first time.
+    pub unsafe fn set_uniform<U: Uniform>(&self, name: &str, value: U) -> bool {
+        let uniform = match self.uniform(name) {
+            Some(uniform) => uniform,
+            None => {
+                self.warn(format!(
+                    "No active uniform {} in program {}",
+                    name, self.raw_program
+                ));
+                return false;
+            }
+        };
+        if !value.matches(uniform.gl_type) {
+            self.warn(format!(
+                "Wrong type for uniform {} in program {}, expected {}",
+                name,
+                self.raw_program,
+                glsl_type_name(uniform.gl_type)
+            ));
+            return false;
+        }
+        if value.count() > uniform.size {
+            self.warn(format!(
+                "{} values for uniform {}[{}] in program {}, the extra ones are ignored",
+                value.count(),
+                name,
+                uniform.size,
+                self.raw_program
+            ));
+        }
+        value.set(uniform.location.into());
+        true
+    }
+
+    fn warn(&self, message: String) {
+        if cfg!(debug_assertions) && self.warnings.borrow_mut().insert(message.clone()) {
+            println!("{}", message);
+        }
+    }
+
     pub unsafe fn get_attrib_location(&self, name: &str) -> AttribLocation {
         let cname = ffi::CString::new(name.as_bytes()).unwrap();
         let v = gl::GetAttribLocation(self.raw_program, cname.as_ptr() as *const i8) as u32;
@@ -87,65 +212,158 @@ impl Shader {
     }
 }
 
-unsafe fn compile_program(
-    vertex_shader: GLuint,
-    fragment_shader: GLuint,
-) -> Result<GLuint, String> {
-    let program: GLuint = gl::CreateProgram();
-
-    gl::AttachShader(program, vertex_shader);
-    gl::AttachShader(program, fragment_shader);
-
-    // Link program
+unsafe fn compile_program(shaders: &[GLuint]) -> Result<GLuint, ShaderError> {
+    let program = gl::CreateProgram();
+    for &shader in shaders {
+        gl::AttachShader(program, shader);
+    }
     gl::LinkProgram(program);
-    // Check for errors
-    let mut program_success: GLint = i32::from(gl::TRUE);
-    gl::GetProgramiv(program, gl::LINK_STATUS, &mut program_success);
-    if program_success != i32::from(gl::TRUE) {
-        return Err(format!("Error linking program {}!\n", program));
+
+    let mut linked = GLint::from(gl::FALSE);
+    gl::GetProgramiv(program, gl::LINK_STATUS, &mut linked);
+    if linked != GLint::from(gl::TRUE) {
+        let log = info_log(program, gl::GetProgramiv, gl::GetProgramInfoLog);
+        for &shader in shaders {
+            gl::DetachShader(program, shader);
+        }
+        gl::DeleteProgram(program);
+        return Err(ShaderError::Link { log });
     }
     Ok(program)
 }
 
-unsafe fn create_shader(source: &ffi::CString, shader_type: GLuint) -> Result<GLuint, String> {
-    // Create vertex shader
-    let shader: GLuint = gl::CreateShader(shader_type);
-    // Get vertex source
-    // Set vertex source
-    gl::ShaderSource(shader, 1, [source.as_ptr()].as_ptr(), ptr::null());
-    // Compile vertex source
+/// Name of an active uniform or attribute, `[0]` is removed from the arrays.
+fn active_name(buffer: &[u8], length: GLsizei) -> String {
+    let name = String::from_utf8_lossy(&buffer[..length.max(0) as usize]);
+    if name.ends_with("[0]") {
+        name[..name.len() - 3].to_string()
+    } else {
+        name.into_owned()
+    }
+}
+
+unsafe fn active_uniforms(program: GLuint) -> Vec<UniformInfo> {
+    let mut count = 0;
+    let mut max_length = 0;
+    gl::GetProgramiv(program, gl::ACTIVE_UNIFORMS, &mut count);
+    gl::GetProgramiv(program, gl::ACTIVE_UNIFORM_MAX_LENGTH, &mut max_length);
+
+    let mut buffer = vec![0u8; max_length.max(1) as usize];
+    let mut uniforms = Vec::with_capacity(count.max(0) as usize);
+    for index in 0..count.max(0) as GLuint {
+        let mut length = 0;
+        let mut size = 0;
+        let mut gl_type = 0;
+        gl::GetActiveUniform(
+            program,
+            index,
+            buffer.len() as GLsizei,
+            &mut length,
+            &mut size,
+            &mut gl_type,
+            buffer.as_mut_ptr() as *mut GLchar,
+        );
+        let location = gl::GetUniformLocation(program, buffer.as_ptr() as *const GLchar) as GLuint;
+        uniforms.push(UniformInfo {
+            name: active_name(&buffer, length),
+            location: UniformLocation(location),
+            gl_type,
+            size: size as usize,
+        });
+    }
+    uniforms
+}
+
+unsafe fn active_attributes(program: GLuint) -> Vec<AttribInfo> {
+    let mut count = 0;
+    let mut max_length = 0;
+    gl::GetProgramiv(program, gl::ACTIVE_ATTRIBUTES, &mut count);
+    gl::GetProgramiv(program, gl::ACTIVE_ATTRIBUTE_MAX_LENGTH, &mut max_length);
+
+    let mut buffer = vec![0u8; max_length.max(1) as usize];
+    let mut attributes = Vec::with_capacity(count.max(0) as usize);
+    for index in 0..count.max(0) as GLuint {
+        let mut length = 0;
+        let mut size = 0;
+        let mut gl_type = 0;
+        gl::GetActiveAttrib(
+            program,
+            index,
+            buffer.len() as GLsizei,
+            &mut length,
+            &mut size,
+            &mut gl_type,
+            buffer.as_mut_ptr() as *mut GLchar,
+        );
+        let location = gl::GetAttribLocation(program, buffer.as_ptr() as *const GLchar) as GLuint;
+        attributes.push(AttribInfo {
+            name: active_name(&buffer, length),
+            location: AttribLocation(location),
+            gl_type,
+            size: size as usize,
+        });
+    }
+    attributes
+}
+
+unsafe fn delete_shaders(shaders: &[GLuint]) {
+    for &shader in shaders {
+        gl::DeleteShader(shader);
+    }
+}
+
+/// Compile the source strings `parts` put end to end.
+unsafe fn create_shader(stage: ShaderStage, parts: &[&str]) -> Result<GLuint, ShaderError> {
+    let pointers: Vec<*const GLchar> = parts.iter().map(|part| part.as_ptr() as _).collect();
+    let lengths: Vec<GLint> = parts.iter().map(|part| part.len() as GLint).collect();
+
+    let shader = gl::CreateShader(stage.gl_type());
+    gl::ShaderSource(
+        shader,
+        parts.len() as GLsizei,
+        pointers.as_ptr(),
+        lengths.as_ptr(),
+    );
     gl::CompileShader(shader);
-    // Check vertex shader for errors
-    let mut shader_compiled: GLint = i32::from(gl::FALSE);
-    gl::GetShaderiv(shader, gl::COMPILE_STATUS, &mut shader_compiled);
-    if shader_compiled != i32::from(gl::TRUE) {
-        return Err(format!(
-            "Unable to compile shader: {:?}\n{}",
-            source,
-            print_shader_log(shader)
-        ));
+
+    let mut compiled = GLint::from(gl::FALSE);
+    gl::GetShaderiv(shader, gl::COMPILE_STATUS, &mut compiled);
+    if compiled != GLint::from(gl::TRUE) {
+        let log = info_log(shader, gl::GetShaderiv, gl::GetShaderInfoLog);
+        gl::DeleteShader(shader);
+        return Err(ShaderError::compile(stage, log, parts));
     }
     Ok(shader)
 }
 
-unsafe fn print_shader_log(shader: GLuint) -> String {
-    // Make sure name is shader
-    if gl::IsShader(shader) > 0 {
-        // Shader log length
-        let mut info_log_length = 0;
-        let mut max_length = info_log_length;
-        // Get info string length
-        gl::GetShaderiv(shader, gl::INFO_LOG_LENGTH, &mut max_length);
-        // Allocate string
-        let info_log =
-            ffi::CString::from_vec_unchecked(Vec::with_capacity(max_length as usize)).into_raw();
-        // Get info log
-        gl::GetShaderInfoLog(shader, max_length, &mut info_log_length, info_log);
-        if info_log_length > 0 {
-            // Print Log
-            let info_string = ffi::CString::from_raw(info_log);
-            return format!("{}\n", info_string.into_string().unwrap());
-        }
+/// Info log of a shader or a program.
+unsafe fn info_log(
+    object: GLuint,
+    get_parameter: unsafe fn(GLuint, GLenum, *mut GLint),
+    get_log: unsafe fn(GLuint, GLsizei, *mut GLsizei, *mut GLchar),
+) -> String {
+    let mut length = 0;
+    get_parameter(object, gl::INFO_LOG_LENGTH, &mut length);
+    let mut buffer = vec![0u8; length.max(1) as usize];
+    let mut written = 0;
+    get_log(
+        object,
+        buffer.len() as GLsizei,
+        &mut written,
+        buffer.as_mut_ptr() as *mut GLchar,
+    );
+    buffer.truncate(written.max(0) as usize);
+    String::from_utf8_lossy(&buffer).trim().to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_active_name() {
+        assert_eq!(active_name(b"lights[0]\0", 9), "lights");
+        assert_eq!(active_name(b"time\0\0", 4), "time");
+        assert_eq!(active_name(b"a[1]\0", 4), "a[1]");
     }
-    format!("Name {} is not a shader\n", shader)
 }
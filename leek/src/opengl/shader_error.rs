@@ -0,0 +1,239 @@
+use gl;
+use gl::types::*;
+
+use std::fmt;
+
+use super::preprocessor::GlslSource;
+
+/// Lines of source shown before and after the line of a diagnostic.
+const EXCERPT_CONTEXT: usize = 2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShaderStage {
+    Vertex,
+    Geometry,
+    Fragment,
+}
+
+impl ShaderStage {
+    pub fn gl_type(self) -> GLenum {
+        match self {
+            ShaderStage::Vertex => gl::VERTEX_SHADER,
+            ShaderStage::Geometry => gl::GEOMETRY_SHADER,
+            ShaderStage::Fragment => gl::FRAGMENT_SHADER,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ShaderStage::Vertex => "vertex",
+            ShaderStage::Geometry => "geometry",
+            ShaderStage::Fragment => "fragment",
+        }
+    }
+}
+
+/// Line of the driver log pointing to a line of the source.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShaderDiagnostic {
+    /// Line of the log.
+    pub message: String,
+    /// Line in all the source strings put end to end, starting at 1.
+    pub line: usize,
+    /// Index of the source string containing the line.
+    pub part: usize,
+    /// Line in this source string, starting at 1.
+    pub part_line: usize,
+    /// Numbered lines around `line`, which is marked with `>`.
+    pub excerpt: String,
+    /// File and line before the preprocessing, see `ShaderError::with_origins`.
+    pub origin: Option<(String, usize)>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ShaderError {
+    Compile {
+        stage: ShaderStage,
+        /// Info log of the driver.
+        log: String,
+        /// Log lines with a line number of the source, in the order of the log.
+        diagnostics: Vec<ShaderDiagnostic>,
+    },
+    Link {
+        log: String,
+    },
+}
+
+impl ShaderError {
+    /// Compile error of `stage`, with the log lines matched to the source strings `parts`.
+    pub fn compile(stage: ShaderStage, log: String, parts: &[&str]) -> ShaderError {
+        let source = parts.concat();
+        let lines: Vec<&str> = source.lines().collect();
+        let diagnostics = log
+            .lines()
+            .filter_map(|message| {
+                let line = log_line_number(message)?;
+                let (part, part_line) = part_line(parts, line);
+                Some(ShaderDiagnostic {
+                    message: message.trim().to_string(),
+                    line,
+                    part,
+                    part_line,
+                    excerpt: excerpt(&lines, line),
+                    origin: None,
+                })
+            })
+            .collect();
+        ShaderError::Compile {
+            stage,
+            log,
+            diagnostics,
+        }
+    }
+
+    /// Point the diagnostics in the source string `part` of `stage` to the files of `glsl`.
+    pub fn with_origins(mut self, stage: ShaderStage, part: usize, glsl: &GlslSource) -> Self {
+        if let ShaderError::Compile {
+            stage: error_stage,
+            ref mut diagnostics,
+            ..
+        } = self
+        {
+            if error_stage == stage {
+                for diagnostic in diagnostics.iter_mut().filter(|d| d.part == part) {
+                    diagnostic.origin = glsl
+                        .origin(diagnostic.part_line)
+                        .map(|(file, line)| (file.to_string(), line));
+                }
+            }
+        }
+        self
+    }
+
+    pub fn log(&self) -> &str {
+        match *self {
+            ShaderError::Compile { ref log, .. } | ShaderError::Link { ref log } => log,
+        }
+    }
+}
+
+impl fmt::Display for ShaderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ShaderError::Compile {
+                stage,
+                ref log,
+                ref diagnostics,
+            } => {
+                write!(f, "Unable to compile {} shader:", stage.name())?;
+                if diagnostics.is_empty() {
+                    return write!(f, "\n{}", log);
+                }
+                for diagnostic in diagnostics {
+                    match diagnostic.origin {
+                        Some((ref file, line)) => write!(f, "\n{}:{}: ", file, line)?,
+                        None => write!(f, "\n")?,
+                    }
+                    write!(f, "{}\n{}", diagnostic.message, diagnostic.excerpt)?;
+                }
+                Ok(())
+            }
+            ShaderError::Link { ref log } => write!(f, "Unable to link program:\n{}", log),
+        }
+    }
+}
+
+impl From<ShaderError> for String {
+    fn from(error: ShaderError) -> String {
+        error.to_string()
+    }
+}
+
+/// Source line of a log line, in the formats of Mesa `0:12(5): error`,
+/// NVIDIA `0(12) : error` and AMD `ERROR: 0:12: message`.
+fn log_line_number(message: &str) -> Option<usize> {
+    let mut message = message.trim();
+    for prefix in &["ERROR:", "WARNING:"] {
+        if message.starts_with(prefix) {
+            message = message[prefix.len()..].trim();
+        }
+    }
+    let string_end = message.find(|c: char| !c.is_ascii_digit())?;
+    if string_end == 0 {
+        return None;
+    }
+    let rest = &message[string_end..];
+    if !rest.starts_with(':') && !rest.starts_with('(') {
+        return None;
+    }
+    let rest = &rest[1..];
+    let line_end = rest
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(rest.len());
+    rest[..line_end].parse().ok()
+}
+
+/// Index of the source string containing `line` and the line number in this string.
+fn part_line(parts: &[&str], line: usize) -> (usize, usize) {
+    let mut start = 1;
+    for (index, part) in parts.iter().enumerate() {
+        let end = start + part.matches('\n').count();
+        if line < end || index + 1 == parts.len() {
+            return (index, line + 1 - start);
+        }
+        start = end;
+    }
+    (0, line)
+}
+
+fn excerpt(lines: &[&str], line: usize) -> String {
+    let first = line.saturating_sub(EXCERPT_CONTEXT).max(1);
+    let last = (line + EXCERPT_CONTEXT).min(lines.len());
+    let width = last.to_string().len();
+    (first..=last)
+        .map(|number| {
+            let marker = if number == line { '>' } else { ' ' };
+            format!(
+                "{} {:>width$} | {}",
+                marker,
+                number,
+                lines[number - 1],
+                width = width
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_log_line_numbers() {
+        assert_eq!(log_line_number("0:12(5): error: `x' undeclared"), Some(12));
+        assert_eq!(log_line_number("0(7) : error C1008: undefined"), Some(7));
+        assert_eq!(log_line_number("ERROR: 0:3: 'x' : undeclared"), Some(3));
+        assert_eq!(log_line_number("error: linking failed"), None);
+        assert_eq!(log_line_number("ERROR: 1 compilation errors."), None);
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let parts = ["#version 130\n", "void main() {\n    x = 1;\n}\n"];
+        let log = "0:3(5): error: `x' undeclared\n".to_string();
+        match ShaderError::compile(ShaderStage::Fragment, log, &parts) {
+            ShaderError::Compile { diagnostics, .. } => {
+                assert_eq!(diagnostics.len(), 1);
+                let diagnostic = &diagnostics[0];
+                assert_eq!((diagnostic.line, diagnostic.part), (3, 1));
+                assert_eq!(diagnostic.part_line, 2);
+                assert_eq!(
+                    diagnostic.excerpt,
+                    "  1 | #version 130\n  2 | void main() {\n> 3 |     x = 1;\n  4 | }"
+                );
+            }
+            error => panic!("unexpected error {:?}", error),
+        }
+    }
+}
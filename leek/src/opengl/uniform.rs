@@ -0,0 +1,220 @@
+use gl;
+use gl::types::*;
+
+/// Value that can be given to `Shader::set_uniform`.
+pub trait Uniform {
+    /// `true` when it can be set to a uniform of the GLSL type `gl_type`, like `gl::FLOAT_VEC2`.
+    fn matches(&self, gl_type: GLenum) -> bool;
+
+    /// Number of elements, more than 1 for arrays.
+    fn count(&self) -> usize {
+        1
+    }
+
+    /// Set the uniform at `location` of the active program.
+    unsafe fn set(&self, location: GLint);
+}
+
+/// Texture unit read by a sampler uniform.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sampler(pub u32);
+
+const SAMPLER_TYPES: [GLenum; 7] = [
+    gl::SAMPLER_1D,
+    gl::SAMPLER_2D,
+    gl::SAMPLER_3D,
+    gl::SAMPLER_CUBE,
+    gl::SAMPLER_2D_RECT,
+    gl::SAMPLER_2D_ARRAY,
+    gl::SAMPLER_BUFFER,
+];
+
+impl Uniform for Sampler {
+    fn matches(&self, gl_type: GLenum) -> bool {
+        SAMPLER_TYPES.contains(&gl_type)
+    }
+
+    unsafe fn set(&self, location: GLint) {
+        gl::Uniform1i(location, self.0 as GLint);
+    }
+}
+
+impl Uniform for bool {
+    fn matches(&self, gl_type: GLenum) -> bool {
+        gl_type == gl::BOOL
+    }
+
+    unsafe fn set(&self, location: GLint) {
+        gl::Uniform1i(location, *self as GLint);
+    }
+}
+
+impl Uniform for i32 {
+    fn matches(&self, gl_type: GLenum) -> bool {
+        gl_type == gl::INT || gl_type == gl::BOOL
+    }
+
+    unsafe fn set(&self, location: GLint) {
+        gl::Uniform1i(location, *self);
+    }
+}
+
+impl Uniform for u32 {
+    fn matches(&self, gl_type: GLenum) -> bool {
+        gl_type == gl::UNSIGNED_INT
+    }
+
+    unsafe fn set(&self, location: GLint) {
+        gl::Uniform1ui(location, *self);
+    }
+}
+
+impl Uniform for f32 {
+    fn matches(&self, gl_type: GLenum) -> bool {
+        gl_type == gl::FLOAT
+    }
+
+    unsafe fn set(&self, location: GLint) {
+        gl::Uniform1f(location, *self);
+    }
+}
+
+impl Uniform for (f32, f32) {
+    fn matches(&self, gl_type: GLenum) -> bool {
+        gl_type == gl::FLOAT_VEC2
+    }
+
+    unsafe fn set(&self, location: GLint) {
+        gl::Uniform2f(location, self.0, self.1);
+    }
+}
+
+impl Uniform for (f32, f32, f32) {
+    fn matches(&self, gl_type: GLenum) -> bool {
+        gl_type == gl::FLOAT_VEC3
+    }
+
+    unsafe fn set(&self, location: GLint) {
+        gl::Uniform3f(location, self.0, self.1, self.2);
+    }
+}
+
+impl Uniform for (f32, f32, f32, f32) {
+    fn matches(&self, gl_type: GLenum) -> bool {
+        gl_type == gl::FLOAT_VEC4
+    }
+
+    unsafe fn set(&self, location: GLint) {
+        gl::Uniform4f(location, self.0, self.1, self.2, self.3);
+    }
+}
+
+/// Arrays of values, and fixed size arrays for vectors and matrices.
+macro_rules! impl_uniform_arrays {
+    ($($element:ty, $gl_type:expr, $setter:expr;)*) => {
+        $(
+            impl Uniform for $element {
+                fn matches(&self, gl_type: GLenum) -> bool {
+                    gl_type == $gl_type
+                }
+
+                unsafe fn set(&self, location: GLint) {
+                    $setter(location, 1, self as *const $element as *const _);
+                }
+            }
+
+            impl<'a> Uniform for &'a [$element] {
+                fn matches(&self, gl_type: GLenum) -> bool {
+                    gl_type == $gl_type
+                }
+
+                fn count(&self) -> usize {
+                    self.len()
+                }
+
+                unsafe fn set(&self, location: GLint) {
+                    $setter(location, self.len() as GLsizei, self.as_ptr() as *const _);
+                }
+            }
+        )*
+    };
+}
+
+unsafe fn uniform_matrix_2fv(location: GLint, count: GLsizei, value: *const GLfloat) {
+    gl::UniformMatrix2fv(location, count, gl::FALSE, value);
+}
+
+unsafe fn uniform_matrix_3fv(location: GLint, count: GLsizei, value: *const GLfloat) {
+    gl::UniformMatrix3fv(location, count, gl::FALSE, value);
+}
+
+unsafe fn uniform_matrix_4fv(location: GLint, count: GLsizei, value: *const GLfloat) {
+    gl::UniformMatrix4fv(location, count, gl::FALSE, value);
+}
+
+impl_uniform_arrays! {
+    [f32; 2], gl::FLOAT_VEC2, gl::Uniform2fv;
+    [f32; 3], gl::FLOAT_VEC3, gl::Uniform3fv;
+    [f32; 4], gl::FLOAT_VEC4, gl::Uniform4fv;
+    [i32; 2], gl::INT_VEC2, gl::Uniform2iv;
+    [i32; 3], gl::INT_VEC3, gl::Uniform3iv;
+    [i32; 4], gl::INT_VEC4, gl::Uniform4iv;
+    [[f32; 2]; 2], gl::FLOAT_MAT2, uniform_matrix_2fv;
+    [[f32; 3]; 3], gl::FLOAT_MAT3, uniform_matrix_3fv;
+    [[f32; 4]; 4], gl::FLOAT_MAT4, uniform_matrix_4fv;
+}
+
+impl<'a> Uniform for &'a [f32] {
+    fn matches(&self, gl_type: GLenum) -> bool {
+        gl_type == gl::FLOAT
+    }
+
+    fn count(&self) -> usize {
+        self.len()
+    }
+
+    unsafe fn set(&self, location: GLint) {
+        gl::Uniform1fv(location, self.len() as GLsizei, self.as_ptr());
+    }
+}
+
+impl<'a> Uniform for &'a [i32] {
+    fn matches(&self, gl_type: GLenum) -> bool {
+        gl_type == gl::INT || gl_type == gl::BOOL
+    }
+
+    fn count(&self) -> usize {
+        self.len()
+    }
+
+    unsafe fn set(&self, location: GLint) {
+        gl::Uniform1iv(location, self.len() as GLsizei, self.as_ptr());
+    }
+}
+
+/// Name of a GLSL type, for the messages.
+pub fn glsl_type_name(gl_type: GLenum) -> &'static str {
+    match gl_type {
+        gl::FLOAT => "float",
+        gl::FLOAT_VEC2 => "vec2",
+        gl::FLOAT_VEC3 => "vec3",
+        gl::FLOAT_VEC4 => "vec4",
+        gl::INT => "int",
+        gl::INT_VEC2 => "ivec2",
+        gl::INT_VEC3 => "ivec3",
+        gl::INT_VEC4 => "ivec4",
+        gl::UNSIGNED_INT => "uint",
+        gl::BOOL => "bool",
+        gl::FLOAT_MAT2 => "mat2",
+        gl::FLOAT_MAT3 => "mat3",
+        gl::FLOAT_MAT4 => "mat4",
+        gl::SAMPLER_1D => "sampler1D",
+        gl::SAMPLER_2D => "sampler2D",
+        gl::SAMPLER_3D => "sampler3D",
+        gl::SAMPLER_CUBE => "samplerCube",
+        gl::SAMPLER_2D_RECT => "sampler2DRect",
+        gl::SAMPLER_2D_ARRAY => "sampler2DArray",
+        gl::SAMPLER_BUFFER => "samplerBuffer",
+        _ => "unknown type",
+    }
+}
@@ -0,0 +1,290 @@
+use imgui::{ImStr, Ui};
+use serde_json;
+
+use std::fs;
+
+use super::{Burst, Curve, EmitterShape, ParticleBlend, ParticleEmitter};
+
+const SHAPES: [&str; 4] = ["Point", "Line", "Circle", "Rect"];
+
+/// ImGui window to tune an emitter while the game runs.
+///
+/// Changes are applied to the emitter directly, saving writes the definition back to its
+/// file so that `Resources` reloads it for the other emitters too.
+pub struct ParticleEditor {
+    window_open: bool,
+    path: String,
+    status: String,
+}
+
+impl ParticleEditor {
+    /// `path` is the file the definition was loaded from.
+    pub fn new(path: &str) -> Self {
+        ParticleEditor {
+            window_open: false,
+            path: path.to_string(),
+            status: String::new(),
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.window_open = !self.window_open;
+    }
+
+    pub fn save(&mut self, emitter: &ParticleEmitter) {
+        let result = serde_json::to_string_pretty(emitter.def())
+            .map_err(|e| format!("{}", e))
+            .and_then(|json| fs::write(&self.path, json).map_err(|e| format!("{}", e)));
+        self.status = match result {
+            Ok(()) => format!("Saved {}", self.path),
+            Err(e) => format!("Error saving {}: {}", self.path, e),
+        };
+    }
+
+    pub fn build(&mut self, ui: &Ui, emitter: &mut ParticleEmitter) {
+        let mut opened = self.window_open;
+        let mut save = false;
+        if opened {
+            ui.window(im_str!("Particle editor"))
+                .opened(&mut opened)
+                .build(|| {
+                    ui.text(im_str!("{}", self.path));
+                    ui.text(im_str!("{} particles", emitter.particle_count()));
+                    if ui.small_button(im_str!("Restart")) {
+                        emitter.clear();
+                        emitter.start();
+                    }
+                    ui.same_line(0.0);
+                    if ui.small_button(im_str!("Stop")) {
+                        emitter.stop();
+                    }
+                    ui.same_line(0.0);
+                    if ui.small_button(im_str!("Burst")) {
+                        emitter.burst(10);
+                    }
+                    ui.same_line(0.0);
+                    save = ui.small_button(im_str!("Save"));
+                    ui.text(im_str!("{}", self.status));
+                    ui.separator();
+
+                    edit_emission(ui, emitter);
+                    edit_motion(ui, emitter);
+                    edit_curves(ui, emitter);
+                });
+        }
+        self.window_open = opened;
+
+        if save {
+            self.save(emitter);
+        }
+    }
+}
+
+fn edit_emission(ui: &Ui, emitter: &mut ParticleEmitter) {
+    if !ui.collapsing_header(im_str!("Emission")).build() {
+        return;
+    }
+    let def = emitter.def_mut();
+
+    let mut blend = def.blend as i32;
+    if ui.combo(
+        im_str!("Blend"),
+        &mut blend,
+        &[im_str!("Alpha"), im_str!("Additive")],
+        2,
+    ) {
+        def.blend = if blend == 0 {
+            ParticleBlend::Alpha
+        } else {
+            ParticleBlend::Additive
+        };
+    }
+
+    let mut shape = match def.shape {
+        EmitterShape::Point => 0,
+        EmitterShape::Line { .. } => 1,
+        EmitterShape::Circle { .. } => 2,
+        EmitterShape::Rect { .. } => 3,
+    };
+    let items: Vec<ImStr> = SHAPES.iter().map(|&shape| ImStr::from(shape)).collect();
+    if ui.combo(im_str!("Shape"), &mut shape, &items, 4) {
+        def.shape = match shape {
+            1 => EmitterShape::Line { to: (32.0, 0.0) },
+            2 => EmitterShape::Circle { radius: 16.0 },
+            3 => EmitterShape::Rect {
+                width: 32.0,
+                height: 32.0,
+            },
+            _ => EmitterShape::Point,
+        };
+    }
+    match def.shape {
+        EmitterShape::Point => {}
+        EmitterShape::Line { ref mut to } => edit_pair(ui, im_str!("To"), to, -512.0, 512.0),
+        EmitterShape::Circle { ref mut radius } => {
+            ui.slider_float(im_str!("Radius"), radius, 0.0, 256.0)
+                .build();
+        }
+        EmitterShape::Rect {
+            ref mut width,
+            ref mut height,
+        } => {
+            ui.slider_float(im_str!("Width"), width, 0.0, 512.0).build();
+            ui.slider_float(im_str!("Height"), height, 0.0, 512.0)
+                .build();
+        }
+    }
+
+    ui.slider_float(im_str!("Rate"), &mut def.rate, 0.0, 1000.0)
+        .display_format(im_str!("%.0f/s"))
+        .build();
+
+    let mut max_particles = def.max_particles as i32;
+    if ui
+        .slider_int(im_str!("Max particles"), &mut max_particles, 1, 5000)
+        .build()
+    {
+        def.max_particles = max_particles as usize;
+    }
+
+    let mut limited = def.duration.is_some();
+    if ui.checkbox(im_str!("Limited duration"), &mut limited) {
+        def.duration = if limited { Some(1000) } else { None };
+    }
+    if let Some(ref mut duration) = def.duration {
+        let mut value = *duration as i32;
+        if ui
+            .slider_int(im_str!("Duration (ms)"), &mut value, 0, 10_000)
+            .build()
+        {
+            *duration = value as u32;
+        }
+        ui.checkbox(im_str!("Looping"), &mut def.looping);
+    }
+
+    let mut remove = None;
+    for (index, burst) in def.bursts.iter_mut().enumerate() {
+        ui.push_id(index as i32);
+        let mut values = [burst.time as i32, burst.count as i32];
+        if ui
+            .input_int2(im_str!("Burst time, count"), &mut values)
+            .build()
+        {
+            burst.time = values[0].max(0) as u32;
+            burst.count = values[1].max(0) as u32;
+        }
+        ui.same_line(0.0);
+        if ui.small_button(im_str!("x")) {
+            remove = Some(index);
+        }
+        ui.pop_id();
+    }
+    if let Some(index) = remove {
+        def.bursts.remove(index);
+    }
+    if ui.small_button(im_str!("Add burst")) {
+        def.bursts.push(Burst { time: 0, count: 10 });
+    }
+    def.bursts.sort_by_key(|burst| burst.time);
+}
+
+fn edit_motion(ui: &Ui, emitter: &mut ParticleEmitter) {
+    if !ui.collapsing_header(im_str!("Motion")).build() {
+        return;
+    }
+    let def = emitter.def_mut();
+
+    let mut lifetime = [def.lifetime.0 as i32, def.lifetime.1 as i32];
+    if ui
+        .slider_int2(im_str!("Lifetime (ms)"), &mut lifetime, 1, 10_000)
+        .build()
+    {
+        def.lifetime = (lifetime[0] as u32, lifetime[1] as u32);
+    }
+    edit_pair(ui, im_str!("Speed"), &mut def.speed, 0.0, 1000.0);
+    ui.slider_float(im_str!("Direction"), &mut def.direction, -180.0, 180.0)
+        .build();
+    ui.slider_float(im_str!("Spread"), &mut def.spread, 0.0, 360.0)
+        .build();
+    edit_pair(ui, im_str!("Gravity"), &mut def.gravity, -1000.0, 1000.0);
+    edit_pair(ui, im_str!("Rotation"), &mut def.rotation, -180.0, 180.0);
+    edit_pair(ui, im_str!("Spin"), &mut def.spin, -720.0, 720.0);
+}
+
+fn edit_curves(ui: &Ui, emitter: &mut ParticleEmitter) {
+    if !ui.collapsing_header(im_str!("Curves")).build() {
+        return;
+    }
+    let def = emitter.def_mut();
+
+    edit_curve(
+        ui,
+        "Color",
+        &mut def.color_curve,
+        (255, 255, 255, 255),
+        |ui, color| {
+            let mut value = [
+                f32::from(color.0) / 255.0,
+                f32::from(color.1) / 255.0,
+                f32::from(color.2) / 255.0,
+                f32::from(color.3) / 255.0,
+            ];
+            if ui.color_edit4(im_str!("Value"), &mut value).build() {
+                let channel = |v: f32| (v * 255.0).round() as u8;
+                *color = (
+                    channel(value[0]),
+                    channel(value[1]),
+                    channel(value[2]),
+                    channel(value[3]),
+                );
+            }
+        },
+    );
+    edit_curve(ui, "Size", &mut def.size_curve, 8.0, |ui, size| {
+        ui.slider_float(im_str!("Value"), size, 0.0, 256.0).build();
+    });
+    edit_curve(ui, "Velocity", &mut def.velocity_curve, 1.0, |ui, scale| {
+        ui.slider_float(im_str!("Value"), scale, -2.0, 2.0).build();
+    });
+    edit_curve(ui, "Rotation", &mut def.rotation_curve, 1.0, |ui, scale| {
+        ui.slider_float(im_str!("Value"), scale, -2.0, 2.0).build();
+    });
+}
+
+/// Edit the keys of a curve, `new_key` is the value of the added keys.
+fn edit_curve<T, F>(ui: &Ui, name: &str, curve: &mut Curve<T>, new_key: T, edit_value: F)
+where
+    T: Copy,
+    F: Fn(&Ui, &mut T),
+{
+    ui.tree_node(im_str!("{}", name)).build(|| {
+        let mut remove = None;
+        for (index, &mut (ref mut time, ref mut value)) in curve.0.iter_mut().enumerate() {
+            ui.push_id(index as i32);
+            ui.slider_float(im_str!("Time"), time, 0.0, 1.0).build();
+            edit_value(ui, value);
+            if ui.small_button(im_str!("Remove key")) {
+                remove = Some(index);
+            }
+            ui.separator();
+            ui.pop_id();
+        }
+        if let Some(index) = remove {
+            curve.0.remove(index);
+        }
+        if ui.small_button(im_str!("Add key")) {
+            curve.0.push((1.0, new_key));
+        }
+        curve
+            .0
+            .sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(::std::cmp::Ordering::Equal));
+    });
+}
+
+/// Edit a (min, max) range or a 2D vector.
+fn edit_pair(ui: &Ui, label: ImStr, value: &mut (f32, f32), min: f32, max: f32) {
+    let mut values = [value.0, value.1];
+    if ui.slider_float2(label, &mut values, min, max).build() {
+        *value = (values[0], values[1]);
+    }
+}
@@ -0,0 +1,642 @@
+use sdl2::rect::{Point, Rect};
+use sdl2::render::{Texture, WindowCanvas};
+use sdl2::sys::SDL_BlendMode;
+use serde_json;
+
+use std::f32::consts::PI;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use camera::CameraView;
+use math::{lerp, lerp_color};
+use EngineContext;
+
+mod editor;
+
+pub use self::editor::ParticleEditor;
+
+pub type Color = (u8, u8, u8, u8);
+
+/// Values that can be blended by a `Curve`.
+pub trait Interpolate: Copy {
+    fn interpolate(from: Self, to: Self, t: f32) -> Self;
+}
+
+impl Interpolate for f32 {
+    fn interpolate(from: f32, to: f32, t: f32) -> f32 {
+        lerp(from, to, t)
+    }
+}
+
+impl Interpolate for Color {
+    fn interpolate(from: Color, to: Color, t: f32) -> Color {
+        lerp_color(from, to, t)
+    }
+}
+
+/// Keys sorted by their time in the 0..1 range of a particle life.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Curve<T>(pub Vec<(f32, T)>);
+
+impl<T: Interpolate> Curve<T> {
+    pub fn constant(value: T) -> Self {
+        Curve(vec![(0.0, value)])
+    }
+
+    pub fn linear(from: T, to: T) -> Self {
+        Curve(vec![(0.0, from), (1.0, to)])
+    }
+
+    /// Value at time `t`, `None` without keys.
+    pub fn sample(&self, t: f32) -> Option<T> {
+        let keys = &self.0;
+        let next = keys.iter().position(|&(time, _)| time > t);
+        match next {
+            Some(0) => keys.first().map(|&(_, value)| value),
+            Some(index) => {
+                let (from_time, from) = keys[index - 1];
+                let (to_time, to) = keys[index];
+                Some(T::interpolate(
+                    from,
+                    to,
+                    (t - from_time) / (to_time - from_time),
+                ))
+            }
+            None => keys.last().map(|&(_, value)| value),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ParticleBlend {
+    Alpha,
+    Additive,
+}
+
+impl Default for ParticleBlend {
+    fn default() -> Self {
+        ParticleBlend::Alpha
+    }
+}
+
+/// Area where particles are spawned, relative to the emitter position.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum EmitterShape {
+    Point,
+    Line { to: (f32, f32) },
+    Circle { radius: f32 },
+    Rect { width: f32, height: f32 },
+}
+
+impl Default for EmitterShape {
+    fn default() -> Self {
+        EmitterShape::Point
+    }
+}
+
+/// Particles emitted at once, `time` milliseconds after the emitter started.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Burst {
+    pub time: u32,
+    pub count: u32,
+}
+
+fn default_max_particles() -> usize {
+    500
+}
+
+fn default_lifetime() -> (u32, u32) {
+    (1000, 1000)
+}
+
+fn default_color() -> Curve<Color> {
+    Curve::constant((255, 255, 255, 255))
+}
+
+fn default_size() -> Curve<f32> {
+    Curve::constant(8.0)
+}
+
+fn default_scale() -> Curve<f32> {
+    Curve::constant(1.0)
+}
+
+/// Definition of an emitter, loaded from a JSON file by `Resources`.
+///
+/// Times are in milliseconds, distances in pixels and angles in degrees.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct EmitterDef {
+    /// Path of the particle image, relative to the definition file.
+    pub image: String,
+    /// Set by `Resources` when loading the definition.
+    #[serde(skip)]
+    pub texture: Option<Arc<Texture>>,
+    #[serde(default)]
+    pub blend: ParticleBlend,
+    #[serde(default)]
+    pub shape: EmitterShape,
+    /// Particles per second while emitting.
+    #[serde(default)]
+    pub rate: f32,
+    /// Sorted by time.
+    #[serde(default)]
+    pub bursts: Vec<Burst>,
+    /// Emission time, `None` to emit until stopped.
+    #[serde(default)]
+    pub duration: Option<u32>,
+    /// Start again after `duration`, bursts included.
+    #[serde(default)]
+    pub looping: bool,
+    #[serde(default = "default_max_particles")]
+    pub max_particles: usize,
+    #[serde(default = "default_lifetime")]
+    pub lifetime: (u32, u32),
+    /// Initial speed, in pixels per second.
+    #[serde(default)]
+    pub speed: (f32, f32),
+    /// Angle of the emission, 0 points right and 90 down.
+    #[serde(default)]
+    pub direction: f32,
+    /// Width of the emission cone.
+    #[serde(default)]
+    pub spread: f32,
+    /// Acceleration in pixels per second squared.
+    #[serde(default)]
+    pub gravity: (f32, f32),
+    /// Initial rotation.
+    #[serde(default)]
+    pub rotation: (f32, f32),
+    /// Rotation speed, in degrees per second.
+    #[serde(default)]
+    pub spin: (f32, f32),
+    #[serde(default = "default_color")]
+    pub color_curve: Curve<Color>,
+    #[serde(default = "default_size")]
+    pub size_curve: Curve<f32>,
+    /// Multiplier of the initial speed.
+    #[serde(default = "default_scale")]
+    pub velocity_curve: Curve<f32>,
+    /// Multiplier of the rotation speed.
+    #[serde(default = "default_scale")]
+    pub rotation_curve: Curve<f32>,
+}
+
+/// Parse an emitter definition, the texture is left to be loaded (see `EmitterDef::image`).
+pub fn parse_emitter(json: &str) -> Result<EmitterDef, String> {
+    serde_json::from_str(json).map_err(|e| format!("{}", e))
+}
+
+impl EmitterDef {
+    pub fn vram_size(&self) -> usize {
+        self.texture.as_ref().map_or(0, |texture| {
+            let tex_query = texture.query();
+
+            let pixels = tex_query.width * tex_query.height;
+            tex_query.format.byte_size_of_pixels(pixels as usize)
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Particle {
+    position: (f32, f32),
+    /// Initial velocity, scaled by the velocity curve.
+    velocity: (f32, f32),
+    /// Velocity gained from gravity.
+    fall: (f32, f32),
+    rotation: f32,
+    spin: f32,
+    age: u32,
+    lifetime: u32,
+}
+
+impl Particle {
+    fn life(&self) -> f32 {
+        self.age as f32 / self.lifetime as f32
+    }
+}
+
+/// Xorshift generator, good enough for particles.
+#[derive(Clone, Debug)]
+struct Rng(u32);
+
+impl Rng {
+    fn from_time() -> Self {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.subsec_nanos())
+            .unwrap_or(0);
+        Rng(seed | 1)
+    }
+
+    fn next_f32(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        (self.0 >> 8) as f32 / (1 << 24) as f32
+    }
+
+    fn range(&mut self, range: (f32, f32)) -> f32 {
+        lerp(range.0, range.1, self.next_f32())
+    }
+}
+
+/// Spawns, moves and draws the particles of an `EmitterDef`.
+pub struct ParticleEmitter {
+    def: Arc<EmitterDef>,
+    pub position: (f32, f32),
+    particles: Vec<Particle>,
+    emitting: bool,
+    // Time since the start of the emission cycle
+    elapsed: u32,
+    next_burst: usize,
+    // Fraction of particle left by the continuous rate
+    pending: f32,
+    rng: Rng,
+}
+
+impl ParticleEmitter {
+    /// Create an emitter that starts emitting immediately.
+    pub fn new(def: Arc<EmitterDef>, position: (f32, f32)) -> Self {
+        ParticleEmitter {
+            def,
+            position,
+            particles: Vec::new(),
+            emitting: true,
+            elapsed: 0,
+            next_burst: 0,
+            pending: 0.0,
+            rng: Rng::from_time(),
+        }
+    }
+
+    /// Use a fixed seed, to get the same particles every time.
+    pub fn with_seed(mut self, seed: u32) -> Self {
+        self.rng = Rng(seed | 1);
+        self
+    }
+
+    pub fn def(&self) -> &EmitterDef {
+        &self.def
+    }
+
+    /// Definition to edit, cloned the first time if shared.
+    pub fn def_mut(&mut self) -> &mut EmitterDef {
+        Arc::make_mut(&mut self.def)
+    }
+
+    /// Replace the definition (e.g. after a reload), alive particles are kept.
+    pub fn set_def(&mut self, def: Arc<EmitterDef>) {
+        self.def = def;
+    }
+
+    /// Restart the emission from the beginning.
+    pub fn start(&mut self) {
+        self.emitting = true;
+        self.elapsed = 0;
+        self.next_burst = 0;
+        self.pending = 0.0;
+    }
+
+    /// Stop emitting, alive particles keep moving.
+    pub fn stop(&mut self) {
+        self.emitting = false;
+    }
+
+    pub fn is_emitting(&self) -> bool {
+        self.emitting
+    }
+
+    /// `true` once the emission is over and every particle is dead.
+    pub fn is_finished(&self) -> bool {
+        !self.emitting && self.particles.is_empty()
+    }
+
+    pub fn particle_count(&self) -> usize {
+        self.particles.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.particles.clear();
+    }
+
+    /// Spawn `count` particles at once.
+    pub fn burst(&mut self, count: u32) {
+        for _ in 0..count {
+            if self.particles.len() >= self.def.max_particles {
+                break;
+            }
+            let particle = self.spawn();
+            self.particles.push(particle);
+        }
+    }
+
+    fn spawn(&mut self) -> Particle {
+        let def = &self.def;
+        let rng = &mut self.rng;
+
+        let offset = match def.shape {
+            EmitterShape::Point => (0.0, 0.0),
+            EmitterShape::Line { to } => {
+                let t = rng.next_f32();
+                (to.0 * t, to.1 * t)
+            }
+            EmitterShape::Circle { radius } => {
+                let angle = rng.next_f32() * 2.0 * PI;
+                let distance = radius * rng.next_f32().sqrt();
+                (angle.cos() * distance, angle.sin() * distance)
+            }
+            EmitterShape::Rect { width, height } => (
+                (rng.next_f32() - 0.5) * width,
+                (rng.next_f32() - 0.5) * height,
+            ),
+        };
+
+        let angle = (def.direction + (rng.next_f32() - 0.5) * def.spread).to_radians();
+        let speed = rng.range(def.speed);
+        let lifetime = rng.range((def.lifetime.0 as f32, def.lifetime.1 as f32));
+
+        Particle {
+            position: (self.position.0 + offset.0, self.position.1 + offset.1),
+            velocity: (angle.cos() * speed, angle.sin() * speed),
+            fall: (0.0, 0.0),
+            rotation: rng.range(def.rotation),
+            spin: rng.range(def.spin),
+            age: 0,
+            lifetime: (lifetime as u32).max(1),
+        }
+    }
+
+    pub fn update(&mut self, context: &EngineContext) {
+        self.advance(context.delta_time);
+    }
+
+    /// Move the particles and emit new ones, `delta_time` is in milliseconds.
+    pub fn advance(&mut self, delta_time: u32) {
+        let seconds = delta_time as f32 / 1000.0;
+        {
+            let def = &self.def;
+            self.particles
+                .retain(|particle| particle.age + delta_time < particle.lifetime);
+            for particle in &mut self.particles {
+                particle.age += delta_time;
+                let t = particle.life();
+                let scale = def.velocity_curve.sample(t).unwrap_or(1.0);
+
+                particle.fall.0 += def.gravity.0 * seconds;
+                particle.fall.1 += def.gravity.1 * seconds;
+                particle.position.0 += (particle.velocity.0 * scale + particle.fall.0) * seconds;
+                particle.position.1 += (particle.velocity.1 * scale + particle.fall.1) * seconds;
+                particle.rotation +=
+                    particle.spin * def.rotation_curve.sample(t).unwrap_or(1.0) * seconds;
+            }
+        }
+
+        if self.emitting {
+            self.emit(delta_time);
+        }
+    }
+
+    fn emit(&mut self, delta_time: u32) {
+        let def = Arc::clone(&self.def);
+        let mut remaining = delta_time;
+        loop {
+            let end = def.duration.map_or(u32::max_value(), |duration| {
+                duration.min(self.elapsed.saturating_add(remaining))
+            });
+            let step = end.saturating_sub(self.elapsed).min(remaining);
+            self.elapsed += step;
+            remaining -= step;
+
+            while let Some(burst) = def.bursts.get(self.next_burst) {
+                if burst.time > self.elapsed {
+                    break;
+                }
+                self.burst(burst.count);
+                self.next_burst += 1;
+            }
+
+            self.pending += def.rate * step as f32 / 1000.0;
+            let count = self.pending.floor();
+            self.pending -= count;
+            self.burst(count as u32);
+
+            match def.duration {
+                Some(duration) if self.elapsed >= duration => {
+                    if def.looping && duration > 0 {
+                        self.elapsed = 0;
+                        self.next_burst = 0;
+                    } else {
+                        self.emitting = false;
+                        return;
+                    }
+                }
+                _ => {}
+            }
+            if remaining == 0 {
+                return;
+            }
+        }
+    }
+
+    /// Call `draw` with the destination rect, angle and color of each particle.
+    fn visit_particles<F>(&self, mut draw: F) -> Result<(), String>
+    where
+        F: FnMut(Rect, f64, Color) -> Result<(), String>,
+    {
+        let def = &self.def;
+        for particle in &self.particles {
+            let t = particle.life();
+            let size = def.size_curve.sample(t).unwrap_or(1.0).max(0.0);
+            let color = def.color_curve.sample(t).unwrap_or((255, 255, 255, 255));
+            let rect = Rect::from_center(
+                Point::new(
+                    particle.position.0.round() as i32,
+                    particle.position.1.round() as i32,
+                ),
+                size.round() as u32,
+                size.round() as u32,
+            );
+            draw(rect, f64::from(particle.rotation), color)?;
+        }
+        Ok(())
+    }
+
+    pub fn draw(&self, renderer: &mut WindowCanvas) -> Result<(), String> {
+        let texture = match self.def.texture {
+            Some(ref texture) => texture,
+            None => return Ok(()),
+        };
+        let previous = modulation(texture);
+        set_blend(texture, self.def.blend);
+        let result = self.visit_particles(|rect, angle, color| {
+            set_color(texture, color);
+            renderer.copy_ex(texture, None, rect, angle, None, false, false)
+        });
+        restore_modulation(texture, previous);
+        result
+    }
+
+    /// Draw through a camera, the particles out of its view are skipped.
+    pub fn draw_with_camera(&self, view: &mut CameraView) -> Result<(), String> {
+        let texture = match self.def.texture {
+            Some(ref texture) => texture,
+            None => return Ok(()),
+        };
+        let previous = modulation(texture);
+        set_blend(texture, self.def.blend);
+        let result = self.visit_particles(|rect, angle, color| {
+            set_color(texture, color);
+            view.copy_ex(texture, None, rect, angle, None, false, false)
+        });
+        restore_modulation(texture, previous);
+        result
+    }
+}
+
+// The texture is shared with the texture cache, so its modulation is set through the raw
+// pointer and the previous one is restored after drawing.
+
+/// Blend mode and color modulation of a texture.
+struct TextureModulation {
+    blend: SDL_BlendMode,
+    color: Color,
+}
+
+fn modulation(texture: &Texture) -> TextureModulation {
+    use sdl2::sys::{SDL_GetTextureAlphaMod, SDL_GetTextureBlendMode, SDL_GetTextureColorMod};
+
+    let mut modulation = TextureModulation {
+        blend: SDL_BlendMode::SDL_BLENDMODE_NONE,
+        color: (255, 255, 255, 255),
+    };
+    unsafe {
+        SDL_GetTextureBlendMode(texture.raw(), &mut modulation.blend);
+        let (ref mut r, ref mut g, ref mut b, ref mut a) = modulation.color;
+        SDL_GetTextureColorMod(texture.raw(), r, g, b);
+        SDL_GetTextureAlphaMod(texture.raw(), a);
+    }
+    modulation
+}
+
+fn set_blend(texture: &Texture, blend: ParticleBlend) {
+    set_blend_mode(
+        texture,
+        match blend {
+            ParticleBlend::Alpha => SDL_BlendMode::SDL_BLENDMODE_BLEND,
+            ParticleBlend::Additive => SDL_BlendMode::SDL_BLENDMODE_ADD,
+        },
+    );
+}
+
+fn set_blend_mode(texture: &Texture, mode: SDL_BlendMode) {
+    use sdl2::sys::SDL_SetTextureBlendMode;
+
+    unsafe {
+        SDL_SetTextureBlendMode(texture.raw(), mode);
+    }
+}
+
+fn set_color(texture: &Texture, color: Color) {
+    use sdl2::sys::{SDL_SetTextureAlphaMod, SDL_SetTextureColorMod};
+
+    unsafe {
+        SDL_SetTextureColorMod(texture.raw(), color.0, color.1, color.2);
+        SDL_SetTextureAlphaMod(texture.raw(), color.3);
+    }
+}
+
+fn restore_modulation(texture: &Texture, modulation: TextureModulation) {
+    set_blend_mode(texture, modulation.blend);
+    set_color(texture, modulation.color);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn def(json: &str) -> Arc<EmitterDef> {
+        Arc::new(parse_emitter(json).unwrap())
+    }
+
+    #[test]
+    fn test_curve() {
+        let curve = Curve(vec![(0.0, 0.0), (0.5, 10.0), (1.0, 0.0)]);
+        assert_eq!(curve.sample(-1.0), Some(0.0));
+        assert_eq!(curve.sample(0.25), Some(5.0));
+        assert_eq!(curve.sample(0.75), Some(5.0));
+        assert_eq!(curve.sample(2.0), Some(0.0));
+        assert_eq!(Curve::<f32>(Vec::new()).sample(0.5), None);
+
+        let color = Curve::<Color>::linear((0, 0, 0, 255), (255, 255, 255, 255));
+        assert_eq!(color.sample(1.0), Some((255, 255, 255, 255)));
+    }
+
+    #[test]
+    fn test_rate_and_lifetime() {
+        let mut emitter = ParticleEmitter::new(
+            def(r#"{"image": "spark.png", "rate": 10, "lifetime": [500, 500]}"#),
+            (0.0, 0.0),
+        );
+        emitter.advance(250);
+        assert_eq!(emitter.particle_count(), 2);
+        emitter.advance(250);
+        assert_eq!(emitter.particle_count(), 5);
+        // The first ones die after 500ms
+        emitter.advance(250);
+        assert_eq!(emitter.particle_count(), 5);
+    }
+
+    #[test]
+    fn test_bursts_and_duration() {
+        let mut emitter = ParticleEmitter::new(
+            def(r#"{
+                "image": "smoke.png", "duration": 300, "lifetime": [1000, 1000],
+                "bursts": [{"time": 0, "count": 5}, {"time": 200, "count": 3}],
+                "shape": {"type": "Circle", "radius": 4}
+            }"#),
+            (100.0, 100.0),
+        );
+        emitter.advance(100);
+        assert_eq!(emitter.particle_count(), 5);
+        emitter.advance(200);
+        assert_eq!(emitter.particle_count(), 8);
+        assert!(!emitter.is_emitting());
+        emitter.advance(1000);
+        assert!(emitter.is_finished());
+    }
+
+    #[test]
+    fn test_looping_and_max_particles() {
+        let mut emitter = ParticleEmitter::new(
+            def(r#"{
+                "image": "spark.png", "duration": 100, "looping": true, "max_particles": 5,
+                "lifetime": [1000, 1000], "bursts": [{"time": 0, "count": 2}]
+            }"#),
+            (0.0, 0.0),
+        );
+        emitter.advance(250);
+        assert_eq!(emitter.particle_count(), 5);
+        assert!(emitter.is_emitting());
+    }
+
+    #[test]
+    fn test_movement() {
+        let mut emitter = ParticleEmitter::new(
+            def(r#"{
+                "image": "spark.png", "lifetime": [2000, 2000], "speed": [100, 100],
+                "direction": 90, "gravity": [50, 0], "velocity_curve": [[0, 1], [1, 0]]
+            }"#),
+            (0.0, 0.0),
+        )
+        .with_seed(7);
+        emitter.burst(1);
+        emitter.advance(1000);
+
+        // Half the speed at half the life, plus the gravity
+        let particle = emitter.particles[0];
+        assert!(particle.position.0 > 49.0 && particle.position.0 < 51.0);
+        assert!(particle.position.1 > 49.0 && particle.position.1 < 51.0);
+    }
+}
@@ -0,0 +1,145 @@
+use sdl2;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::{Texture, WindowCanvas};
+use sdl2::sys;
+
+use std::ptr;
+use std::sync::Arc;
+
+use sdl2_utils::set_render_target;
+
+/// Renders the game at its logical size in an offscreen texture, then draws it at the
+/// largest integer scale that fits the window, without filtering.
+///
+/// Replaces the SDL logical size, see `EngineBuilder::with_pixel_perfect`.
+pub struct PixelPerfect {
+    target: Texture,
+    size: (u32, u32),
+    /// Color of the bars around the game.
+    pub letterbox_color: Color,
+    /// Drawn stretched over the whole window, under the game.
+    pub border: Option<Arc<Texture>>,
+    previous_target: *mut sys::SDL_Texture,
+}
+
+impl PixelPerfect {
+    pub fn new(renderer: &WindowCanvas, width: u32, height: u32) -> Result<Self, String> {
+        // The filtering is chosen when the texture is created
+        let previous_quality = sdl2::hint::get("SDL_RENDER_SCALE_QUALITY");
+        sdl2::hint::set("SDL_RENDER_SCALE_QUALITY", "nearest");
+        let target = renderer
+            .texture_creator()
+            .create_texture_target(None, width, height)
+            .map_err(|e| format!("{}", e));
+        sdl2::hint::set(
+            "SDL_RENDER_SCALE_QUALITY",
+            previous_quality.as_ref().map_or("0", String::as_str),
+        );
+
+        Ok(PixelPerfect {
+            target: target?,
+            size: (width, height),
+            letterbox_color: Color::RGB(0, 0, 0),
+            border: None,
+            previous_target: ptr::null_mut(),
+        })
+    }
+
+    /// Logical size of the game.
+    pub fn size(&self) -> (u32, u32) {
+        self.size
+    }
+
+    /// Where the game is drawn, in pixels of the renderer output.
+    pub fn viewport(&self, renderer: &WindowCanvas) -> Result<Rect, String> {
+        Ok(integer_viewport(renderer.output_size()?, self.size))
+    }
+
+    /// Redirect the drawing of the renderer to the offscreen texture.
+    pub fn begin(&mut self, renderer: &mut WindowCanvas) -> Result<(), String> {
+        unsafe {
+            self.previous_target = sys::SDL_GetRenderTarget(renderer.raw());
+        }
+        set_render_target(renderer, self.target.raw())
+    }
+
+    /// Draw the letterbox and the scaled game where the renderer drew before `begin`.
+    pub fn present(&mut self, renderer: &mut WindowCanvas) -> Result<(), String> {
+        set_render_target(renderer, self.previous_target)?;
+
+        renderer.set_draw_color(self.letterbox_color);
+        renderer.clear();
+        if let Some(ref border) = self.border {
+            renderer.copy(border, None, None)?;
+        }
+        let viewport = self.viewport(renderer)?;
+        renderer.copy(&self.target, None, viewport)
+    }
+}
+
+/// Largest integer scale of `logical` that fits in `output`, at least 1.
+pub fn integer_scale(output: (u32, u32), logical: (u32, u32)) -> u32 {
+    if logical.0 == 0 || logical.1 == 0 {
+        return 1;
+    }
+    (output.0 / logical.0).min(output.1 / logical.1).max(1)
+}
+
+/// Centered rect where `logical` is drawn at its integer scale.
+pub fn integer_viewport(output: (u32, u32), logical: (u32, u32)) -> Rect {
+    let scale = integer_scale(output, logical);
+    let (width, height) = (logical.0 * scale, logical.1 * scale);
+    Rect::new(
+        (output.0 as i32 - width as i32) / 2,
+        (output.1 as i32 - height as i32) / 2,
+        width,
+        height,
+    )
+}
+
+/// Convert a position in window points to the logical coordinates of the game.
+///
+/// `window_size` is in points and `output_size` in pixels, they differ on high DPI screens.
+pub fn window_to_logical(
+    position: (f32, f32),
+    window_size: (u32, u32),
+    output_size: (u32, u32),
+    logical_size: (u32, u32),
+) -> (f32, f32) {
+    if window_size.0 == 0 || window_size.1 == 0 {
+        return position;
+    }
+    let x = position.0 * output_size.0 as f32 / window_size.0 as f32;
+    let y = position.1 * output_size.1 as f32 / window_size.1 as f32;
+
+    let viewport = integer_viewport(output_size, logical_size);
+    let scale = integer_scale(output_size, logical_size) as f32;
+    (
+        (x - viewport.x() as f32) / scale,
+        (y - viewport.y() as f32) / scale,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_integer_viewport() {
+        // 320x180 fits 3 times in 1024x768, not 4
+        let viewport = integer_viewport((1024, 768), (320, 180));
+        assert_eq!(viewport, Rect::new(32, 114, 960, 540));
+        // Smaller windows crop the game instead of shrinking it
+        assert_eq!(integer_scale((200, 100), (320, 180)), 1);
+    }
+
+    #[test]
+    fn test_window_to_logical() {
+        let position = window_to_logical((512.0, 114.0), (1024, 768), (1024, 768), (320, 180));
+        assert_eq!(position, (160.0, 0.0));
+        // High DPI, the window points are half the pixels
+        let position = window_to_logical((256.0, 57.0), (512, 384), (1024, 768), (320, 180));
+        assert_eq!(position, (160.0, 0.0));
+    }
+}
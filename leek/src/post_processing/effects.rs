@@ -0,0 +1,87 @@
+//! Ready-made passes, with their params set to sensible defaults.
+
+use sdl2::render::Texture;
+
+use std::sync::Arc;
+
+use super::PostProcessPass;
+
+/// Darken the corners of the screen.
+pub fn vignette() -> Result<PostProcessPass, String> {
+    let source = include_str!("./shaders/vignette.frag");
+    Ok(PostProcessPass::new("vignette", source)?.with_ranged_param("intensity", 1.5, 0.0, 3.0))
+}
+
+/// Old monitor look: curved glass, scanlines and dark corners.
+pub fn crt() -> Result<PostProcessPass, String> {
+    let source = include_str!("./shaders/crt.frag");
+    Ok(PostProcessPass::new("crt", source)?
+        .with_ranged_param("curvature", 0.2, 0.0, 1.0)
+        .with_ranged_param("scanlines", 240.0, 0.0, 1080.0)
+        .with_ranged_param("scanline_intensity", 0.3, 0.0, 1.0)
+        .with_ranged_param("vignette", 0.5, 0.0, 1.0))
+}
+
+/// Glow around the parts brighter than `threshold`, `radius` is in pixels.
+pub fn bloom() -> Result<PostProcessPass, String> {
+    let source = include_str!("./shaders/bloom.frag");
+    Ok(PostProcessPass::new("bloom", source)?
+        .with_ranged_param("threshold", 0.7, 0.0, 1.0)
+        .with_ranged_param("intensity", 1.0, 0.0, 5.0)
+        .with_ranged_param("radius", 8.0, 0.0, 32.0))
+}
+
+/// Split the color channels toward the edges, `offset` is in pixels.
+pub fn chromatic_aberration() -> Result<PostProcessPass, String> {
+    let source = include_str!("./shaders/chromatic_aberration.frag");
+    Ok(PostProcessPass::new("chromatic_aberration", source)?
+        .with_ranged_param("offset", 2.0, 0.0, 16.0))
+}
+
+/// Remap the colors with a lookup texture.
+///
+/// The LUT is a strip of `lut_size` squares of `lut_size` pixels, one per blue value from left
+/// to right. In each square red grows to the right and green downward.
+pub fn color_grading(lut: Arc<Texture>, lut_size: u32) -> Result<PostProcessPass, String> {
+    let source = include_str!("./shaders/color_grading.frag");
+    Ok(PostProcessPass::new("color_grading", source)?
+        .with_param("lut", lut)
+        .with_ranged_param("lut_size", lut_size as f32, 2.0, 64.0)
+        .with_ranged_param("intensity", 1.0, 0.0, 1.0))
+}
+
+/// Draw the screen with big pixels, `pixel_size` is in pixels of the window.
+pub fn pixelate() -> Result<PostProcessPass, String> {
+    let source = include_str!("./shaders/pixelate.frag");
+    Ok(PostProcessPass::new("pixelate", source)?.with_ranged_param("pixel_size", 4.0, 1.0, 32.0))
+}
+
+pub fn grayscale() -> Result<PostProcessPass, String> {
+    tone("grayscale", (1.0, 1.0, 1.0))
+}
+
+pub fn sepia() -> Result<PostProcessPass, String> {
+    tone("sepia", (1.2, 1.0, 0.8))
+}
+
+/// Replace the colors by their luminance multiplied by `tint`.
+fn tone(name: &str, tint: (f32, f32, f32)) -> Result<PostProcessPass, String> {
+    let source = include_str!("./shaders/tone.frag");
+    Ok(PostProcessPass::new(name, source)?
+        .with_ranged_param("tint", tint, 0.0, 2.0)
+        .with_ranged_param("intensity", 1.0, 0.0, 1.0))
+}
+
+/// Fill the screen with `color`, fading out in `duration` seconds after `start_flash`.
+pub fn flash() -> Result<PostProcessPass, String> {
+    let source = include_str!("./shaders/flash.frag");
+    Ok(PostProcessPass::new("flash", source)?
+        .with_param("start", -1000.0)
+        .with_param("color", (1.0, 1.0, 1.0))
+        .with_ranged_param("duration", 0.2, 0.0, 2.0))
+}
+
+/// Start the fade of a `flash` pass, `elapsed_time` is the one of `EngineContext`.
+pub fn start_flash(pass: &mut PostProcessPass, elapsed_time: u64) {
+    pass.set_param("start", elapsed_time as f32 / 1000.0);
+}
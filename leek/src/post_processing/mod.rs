@@ -7,95 +7,363 @@ use self::opengl::*;
 
 use std::ptr;
 
-use sdl2::render::Texture;
+#[cfg(debug_assertions)]
+use imgui::Ui;
+use sdl2::rect::Rect;
+use sdl2::render::{Texture, WindowCanvas};
+use sdl2::sys;
 
-pub struct PostProcessEffect {
-    program: Shader,
-    vertex_data: GLuint,
-    u_framebuffer: UniformLocation,
+use sdl2_utils::set_render_target;
+
+pub mod effects;
+mod pass;
+pub use self::pass::*;
+
+/// Fragment shader loaded by `Resources`, see `PostProcessPass::from_asset`.
+pub struct ShaderSource {
+    pub path: String,
+    /// Source with its includes expanded, `Resources` reloads it when one of them changes.
+    pub source: GlslSource,
 }
 
-impl PostProcessEffect {
-    pub fn new() -> Result<PostProcessEffect, String> {
-        unsafe {
-            let vertex_source = include_str!("./shaders/glsl_130.vert");
-            let fragment_source = include_str!("./shaders/glsl_130.frag");
+/// Fullscreen quad as (x, y, u, v), the top of the screen samples v = 0.
+const QUAD: [GLfloat; 16] = [
+    -1.0, -1.0, 0.0, 1.0, 1.0, -1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 0.0, -1.0, 1.0, 0.0, 0.0,
+];
 
-            let program = Shader::new(vertex_source, fragment_source)?;
+/// Same quad upside down, to keep v = 0 at the top when rendering into a framebuffer.
+const FLIPPED_QUAD: [GLfloat; 16] = [
+    -1.0, 1.0, 0.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, -1.0, 1.0, 0.0, -1.0, -1.0, 0.0, 0.0,
+];
 
-            let vertex_data: [GLfloat; 16] = [
-                -1.0, -1.0, 0.0, 1.0, 1.0, -1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 0.0, -1.0, 1.0, 0.0, 0.0
-            ];
-            let mut g_vbo: GLuint = 0;
-            let mut g_vao: GLuint = 0;
-            // Create VBO
-            gl::GenBuffers(1, &mut g_vbo);
-
-            gl::GenVertexArrays(1, &mut g_vao);
-            gl::BindVertexArray(g_vao);
-
-            let v_position = VertexAttribArray::new("v_position", program.raw());
-            let v_uv = VertexAttribArray::new("v_uv", program.raw());
-
-            gl::BindBuffer(gl::ARRAY_BUFFER, g_vbo);
-            gl::BufferData(
-                gl::ARRAY_BUFFER,
-                GL_FLOAT_SIZE as isize * 4 * 4,
-                vertex_data.as_ptr() as *const _,
-                gl::STATIC_DRAW,
-            );
-
-            v_position.enable();
-            v_uv.enable();
-
-            v_position.vertex_attrib_pointer(2, gl::FLOAT, GL_FLOAT_SIZE as i32 * 4, ptr::null());
-            v_uv.vertex_attrib_pointer(
-                2,
-                gl::FLOAT,
-                GL_FLOAT_SIZE as i32 * 4,
-                (GL_FLOAT_SIZE as isize * 2) as *const _,
-            );
-
-            let u_framebuffer = program.get_uniform_location("framebuffer");
-
-            gl::BindVertexArray(0);
-            Ok(PostProcessEffect {
-                program,
-                vertex_data: g_vao,
-                u_framebuffer,
-            })
+struct Quad {
+    vertex_array: GLuint,
+    vertex_buffer: GLuint,
+}
+
+impl Quad {
+    unsafe fn new(vertex_data: &[GLfloat; 16]) -> Quad {
+        let mut vertex_array = 0;
+        let mut vertex_buffer = 0;
+        gl::GenVertexArrays(1, &mut vertex_array);
+        gl::GenBuffers(1, &mut vertex_buffer);
+
+        gl::BindBuffer(gl::ARRAY_BUFFER, vertex_buffer);
+        gl::BufferData(
+            gl::ARRAY_BUFFER,
+            GL_FLOAT_SIZE as isize * 4 * 4,
+            vertex_data.as_ptr() as *const _,
+            gl::STATIC_DRAW,
+        );
+        gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+
+        Quad {
+            vertex_array,
+            vertex_buffer,
         }
     }
 
-    pub fn render(&self, framebuffer: &mut Texture) {
+    /// The attribute locations can change with each program, they are set at every draw.
+    unsafe fn draw(&self, shader: &Shader) {
+        gl::BindVertexArray(self.vertex_array);
+        gl::BindBuffer(gl::ARRAY_BUFFER, self.vertex_buffer);
+
+        let v_position = VertexAttribArray::new("v_position", shader.raw());
+        let v_uv = VertexAttribArray::new("v_uv", shader.raw());
+        v_position.enable();
+        v_uv.enable();
+        v_position.vertex_attrib_pointer(2, gl::FLOAT, GL_FLOAT_SIZE as i32 * 4, ptr::null());
+        v_uv.vertex_attrib_pointer(
+            2,
+            gl::FLOAT,
+            GL_FLOAT_SIZE as i32 * 4,
+            (GL_FLOAT_SIZE as isize * 2) as *const _,
+        );
+
+        gl::DrawArrays(gl::TRIANGLE_FAN, 0, 4);
+
+        gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+        gl::BindVertexArray(0);
+    }
+}
+
+impl Drop for Quad {
+    fn drop(&mut self) {
         unsafe {
-            gl::UseProgram(self.program.raw());
+            gl::DeleteBuffers(1, &self.vertex_buffer);
+            gl::DeleteVertexArrays(1, &self.vertex_array);
+        }
+    }
+}
+
+/// Chain of fullscreen effects applied to the rendered frame, before ImGui.
+///
+/// The engine renders the scene in an offscreen texture when at least one pass is enabled,
+/// then each pass draws into a framebuffer read by the next one. The last pass draws to the
+/// window.
+///
+/// With a logical size the scene is rendered at that size and letterboxed in the window by
+/// the last pass, as SDL does without post-processing.
+pub struct PostProcessPipeline {
+    pub inspect_window: bool,
+    passes: Vec<PostProcessPass>,
+    quad: Quad,
+    flipped_quad: Quad,
+    input: Option<Texture>,
+    framebuffers: Vec<Framebuffer>,
+    size: (u32, u32),
+    previous_target: *mut sys::SDL_Texture,
+}
+
+impl PostProcessPipeline {
+    pub fn new() -> PostProcessPipeline {
+        unsafe {
+            PostProcessPipeline {
+                inspect_window: false,
+                passes: Vec::new(),
+                quad: Quad::new(&QUAD),
+                flipped_quad: Quad::new(&FLIPPED_QUAD),
+                input: None,
+                framebuffers: Vec::new(),
+                size: (0, 0),
+                previous_target: ptr::null_mut(),
+            }
+        }
+    }
+
+    /// Add a pass after the others.
+    pub fn add_pass(&mut self, pass: PostProcessPass) {
+        self.passes.push(pass);
+    }
+
+    pub fn insert_pass(&mut self, index: usize, pass: PostProcessPass) {
+        self.passes.insert(index, pass);
+    }
 
-            framebuffer.gl_bind_texture();
+    pub fn remove_pass(&mut self, name: &str) -> Option<PostProcessPass> {
+        let index = self.passes.iter().position(|pass| pass.name() == name)?;
+        Some(self.passes.remove(index))
+    }
 
-            gl::Enable(gl::TEXTURE_2D);
-            gl::ActiveTexture(gl::TEXTURE0);
-            gl::Uniform1i(self.u_framebuffer.into(), 0);
+    pub fn pass(&self, name: &str) -> Option<&PostProcessPass> {
+        self.passes.iter().find(|pass| pass.name() == name)
+    }
 
-            gl::BindVertexArray(self.vertex_data);
+    pub fn pass_mut(&mut self, name: &str) -> Option<&mut PostProcessPass> {
+        self.passes.iter_mut().find(|pass| pass.name() == name)
+    }
 
-            // render
-            gl::DrawArrays(gl::TRIANGLE_FAN, 0, 4);
+    pub fn passes(&self) -> &[PostProcessPass] {
+        &self.passes
+    }
 
-            // unbind framebuffer
-            framebuffer.gl_unbind_texture();
-            // Unbind program
-            gl::UseProgram(0);
-            gl::BindVertexArray(0);
+    /// Enable or disable the pass `name`, returns `false` when there is no such pass.
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        match self.pass_mut(name) {
+            Some(pass) => {
+                pass.enabled = enabled;
+                true
+            }
+            None => false,
         }
     }
-}
 
-impl Drop for PostProcessEffect {
-    fn drop(&mut self) {
+    /// `true` when at least one pass is enabled.
+    pub fn is_active(&self) -> bool {
+        self.passes.iter().any(|pass| pass.enabled)
+    }
+
+    /// Recompile the passes made from `source`, the engine calls it when `Resources` reloads it.
+    pub fn reload_shader(&mut self, source: &ShaderSource) {
+        let path = source.path.as_str();
+        for pass in self.passes.iter_mut() {
+            if pass.source_path() != Some(path) {
+                continue;
+            }
+            match pass.reload_asset(source) {
+                Ok(()) => println!("Recompiled pass {}", pass.name()),
+                Err(error) => {
+                    println!("{}", error);
+                    self.inspect_window = true;
+                }
+            }
+        }
+    }
+
+    /// Show the error of the shader file `path` on the passes made from it, the engine calls it
+    /// when `Resources` fails to reload it.
+    pub fn shader_reload_failed(&mut self, path: &str, error: &str) {
+        println!("{}", error);
+        for pass in self.passes.iter_mut() {
+            if pass.source_path() == Some(path) {
+                pass.set_reload_error(error.to_string());
+                self.inspect_window = true;
+            }
+        }
+    }
+
+    /// Window to enable the passes and tune their params.
+    #[cfg(debug_assertions)]
+    pub fn inspect(&mut self, ui: &Ui) {
+        let mut opened = self.inspect_window;
+        let passes = &mut self.passes;
+
+        ui.window(im_str!("Post processing"))
+            .resizable(true)
+            .opened(&mut opened)
+            .build(|| {
+                if passes.is_empty() {
+                    ui.text(im_str!("No pass"));
+                }
+                for (index, pass) in passes.iter_mut().enumerate() {
+                    ui.push_id(index as i32);
+                    ui.checkbox(im_str!("{}", pass.name()), &mut pass.enabled);
+                    if let Some(path) = pass.source_path() {
+                        ui.text(im_str!("{}", path));
+                    }
+                    if let Some(error) = pass.error() {
+                        ui.text_colored((1.0, 0.3, 0.3, 1.0), im_str!("{}", error));
+                    }
+                    pass.inspect(ui);
+                    ui.separator();
+                    ui.pop_id();
+                }
+            });
+        self.inspect_window = opened;
+    }
+
+    /// Redirect the drawing of the renderer to the input texture, resized to the logical size
+    /// of the renderer or to its output.
+    pub fn begin(&mut self, renderer: &mut WindowCanvas) -> Result<(), String> {
+        // Drawing into a texture resets the logical size of the renderer to the texture size
+        let size = match renderer.logical_size() {
+            (0, _) | (_, 0) => renderer.output_size()?,
+            logical_size => logical_size,
+        };
+        if self.input.is_none() || self.size != size {
+            let input = renderer
+                .texture_creator()
+                .create_texture_target(None, size.0, size.1)
+                .map_err(|e| format!("{}", e))?;
+            self.input = Some(input);
+            self.framebuffers = vec![
+                Framebuffer::new(size.0, size.1)?,
+                Framebuffer::new(size.0, size.1)?,
+            ];
+            self.size = size;
+        }
+        let input = self.input.as_ref().map_or(ptr::null_mut(), Texture::raw);
+        unsafe {
+            self.previous_target = sys::SDL_GetRenderTarget(renderer.raw());
+        }
+        set_render_target(renderer, input)
+    }
+
+    /// Run the enabled passes and draw the result where the renderer drew before `begin`.
+    pub fn present(&mut self, renderer: &mut WindowCanvas, time: f32) -> Result<(), String> {
+        set_render_target(renderer, self.previous_target)?;
+        let input = match self.input {
+            Some(ref input) => input,
+            None => return Ok(()),
+        };
+        if !self.previous_target.is_null() || !self.is_active() {
+            // Only the window is drawn with the default framebuffer
+            return renderer.copy(input, None, None);
+        }
+
+        let output_size = renderer.output_size()?;
+        let viewport = letterbox_viewport(output_size, self.size);
+        let passes: Vec<&PostProcessPass> = self.passes.iter().filter(|p| p.enabled).collect();
         unsafe {
-            gl::DeleteVertexArrays(1, [self.vertex_data].as_ptr());
-            // TODO check if other gl deletes are needed
+            let state = GLState::snapshot();
+            gl::Disable(gl::BLEND);
+            gl::Disable(gl::SCISSOR_TEST);
+            gl::Disable(gl::CULL_FACE);
+            gl::Disable(gl::DEPTH_TEST);
+
+            for (index, pass) in passes.iter().enumerate() {
+                let last = index + 1 == passes.len();
+
+                gl::ActiveTexture(gl::TEXTURE0);
+                if index == 0 {
+                    sys::SDL_GL_BindTexture(input.raw(), ptr::null_mut(), ptr::null_mut());
+                } else {
+                    self.framebuffers[(index - 1) % 2].bind_texture();
+                }
+                if last {
+                    gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+                    if viewport.size() != output_size {
+                        gl::ClearColor(0.0, 0.0, 0.0, 1.0);
+                        gl::Clear(gl::COLOR_BUFFER_BIT);
+                    }
+                    // The origin of the window framebuffer is at the bottom
+                    gl::Viewport(
+                        viewport.x(),
+                        output_size.1 as i32 - viewport.bottom(),
+                        viewport.width() as i32,
+                        viewport.height() as i32,
+                    );
+                } else {
+                    self.framebuffers[index % 2].bind();
+                }
+
+                pass.apply(time, self.size);
+                if last {
+                    self.quad.draw(pass.shader());
+                } else {
+                    self.flipped_quad.draw(pass.shader());
+                }
+                pass.unbind_textures();
+
+                if index == 0 {
+                    sys::SDL_GL_UnbindTexture(input.raw());
+                }
+            }
+            state.restore();
         }
+        Ok(())
+    }
+}
+
+/// Where a `logical` sized image is drawn in `output`, the largest centered rect with its
+/// aspect ratio, like `SDL_RenderSetLogicalSize`.
+pub fn letterbox_viewport(output: (u32, u32), logical: (u32, u32)) -> Rect {
+    if logical.0 == 0 || logical.1 == 0 || output.0 == 0 || output.1 == 0 {
+        return Rect::new(0, 0, output.0, output.1);
+    }
+    let want_aspect = logical.0 as f32 / logical.1 as f32;
+    let real_aspect = output.0 as f32 / output.1 as f32;
+    if (want_aspect - real_aspect).abs() < 0.0001 {
+        Rect::new(0, 0, output.0, output.1)
+    } else if want_aspect > real_aspect {
+        // Bars above and below
+        let scale = output.0 as f32 / logical.0 as f32;
+        let height = (logical.1 as f32 * scale).ceil() as u32;
+        Rect::new(0, (output.1 as i32 - height as i32) / 2, output.0, height)
+    } else {
+        // Bars on the sides
+        let scale = output.1 as f32 / logical.1 as f32;
+        let width = (logical.0 as f32 * scale).ceil() as u32;
+        Rect::new((output.0 as i32 - width as i32) / 2, 0, width, output.1)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_letterbox_viewport() {
+        // 400x400 in 800x600, bars on the sides
+        let viewport = letterbox_viewport((800, 600), (400, 400));
+        assert_eq!(viewport, Rect::new(100, 0, 600, 600));
+        // 320x180 scaled 3.2 times in 1024x768, bars above and below
+        let viewport = letterbox_viewport((1024, 768), (320, 180));
+        assert_eq!(viewport, Rect::new(0, 96, 1024, 576));
+        // Same aspect ratio, or no logical size
+        let viewport = letterbox_viewport((1280, 720), (320, 180));
+        assert_eq!(viewport, Rect::new(0, 0, 1280, 720));
+        let viewport = letterbox_viewport((1280, 720), (0, 0));
+        assert_eq!(viewport, Rect::new(0, 0, 1280, 720));
     }
 }
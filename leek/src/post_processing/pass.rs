@@ -0,0 +1,342 @@
+use gl;
+
+#[cfg(debug_assertions)]
+use imgui::Ui;
+use sdl2::render::Texture;
+use sdl2::sys;
+
+use std::ptr;
+use std::sync::Arc;
+
+use opengl::{GlslSource, Sampler, Shader, ShaderStage};
+
+use super::ShaderSource;
+
+/// Vertex shader shared by every pass, it gives the `f_uv` input to the fragment shaders.
+pub const VERTEX_SOURCE: &str = include_str!("./shaders/glsl_130.vert");
+
+/// Value of a uniform of a pass.
+#[derive(Clone)]
+pub enum PassParam {
+    Float(f32),
+    Vec2(f32, f32),
+    Vec3(f32, f32, f32),
+    Vec4(f32, f32, f32, f32),
+    Int(i32),
+    /// Float, seconds since the start of the engine.
+    Time,
+    /// Vec2, size of the output in pixels.
+    Resolution,
+    /// Sampler2D, an extra texture.
+    Texture(Arc<Texture>),
+}
+
+impl From<f32> for PassParam {
+    fn from(value: f32) -> Self {
+        PassParam::Float(value)
+    }
+}
+
+impl From<(f32, f32)> for PassParam {
+    fn from(value: (f32, f32)) -> Self {
+        PassParam::Vec2(value.0, value.1)
+    }
+}
+
+impl From<(f32, f32, f32)> for PassParam {
+    fn from(value: (f32, f32, f32)) -> Self {
+        PassParam::Vec3(value.0, value.1, value.2)
+    }
+}
+
+impl From<(f32, f32, f32, f32)> for PassParam {
+    fn from(value: (f32, f32, f32, f32)) -> Self {
+        PassParam::Vec4(value.0, value.1, value.2, value.3)
+    }
+}
+
+impl From<i32> for PassParam {
+    fn from(value: i32) -> Self {
+        PassParam::Int(value)
+    }
+}
+
+impl From<Arc<Texture>> for PassParam {
+    fn from(value: Arc<Texture>) -> Self {
+        PassParam::Texture(value)
+    }
+}
+
+struct Param {
+    name: String,
+    value: PassParam,
+    /// Range of the inspector sliders.
+    range: (f32, f32),
+}
+
+/// One fullscreen effect of a `PostProcessPipeline`.
+///
+/// The fragment shader reads the previous pass from the `framebuffer` sampler at `f_uv`,
+/// (0, 0) is the top left corner. The uniforms `time` (float, in seconds) and `resolution`
+/// (vec2, in pixels) are set when they exist, other params are uniforms of the same name.
+pub struct PostProcessPass {
+    name: String,
+    shader: Shader,
+    params: Vec<Param>,
+    source_path: Option<String>,
+    /// Defines injected in the asset source, kept for the reloads.
+    defines: Vec<(String, String)>,
+    error: Option<String>,
+    pub enabled: bool,
+}
+
+fn compile(name: &str, fragment_source: &str) -> Result<Shader, String> {
+    Shader::new(VERTEX_SOURCE, fragment_source)
+        .map_err(|e| format!("Error in pass {}: {}", name, e))
+}
+
+/// Compile a preprocessed source, the errors point to the files before preprocessing.
+fn compile_glsl(name: &str, fragment_source: &GlslSource) -> Result<Shader, String> {
+    Shader::new(VERTEX_SOURCE, fragment_source.source()).map_err(|e| {
+        let e = e.with_origins(ShaderStage::Fragment, 0, fragment_source);
+        format!("Error in pass {}: {}", name, e)
+    })
+}
+
+impl PostProcessPass {
+    pub fn new(name: &str, fragment_source: &str) -> Result<PostProcessPass, String> {
+        Ok(PostProcessPass {
+            name: name.to_string(),
+            shader: compile(name, fragment_source)?,
+            params: Vec::new(),
+            source_path: None,
+            defines: Vec::new(),
+            error: None,
+            enabled: true,
+        })
+    }
+
+    /// Pass of a shader loaded by `Resources`, the engine recompiles it when the file changes.
+    pub fn from_asset(name: &str, source: &ShaderSource) -> Result<PostProcessPass, String> {
+        PostProcessPass::from_asset_with_defines(name, source, &[])
+    }
+
+    /// Variant of a shader loaded by `Resources`, with a `#define name value` for each of
+    /// `defines` after its `#version`.
+    pub fn from_asset_with_defines(
+        name: &str,
+        source: &ShaderSource,
+        defines: &[(&str, &str)],
+    ) -> Result<PostProcessPass, String> {
+        let fragment_source = source.source.with_defines(defines);
+        Ok(PostProcessPass {
+            name: name.to_string(),
+            shader: compile_glsl(name, &fragment_source)?,
+            params: Vec::new(),
+            source_path: Some(source.path.clone()),
+            defines: defines
+                .iter()
+                .map(|&(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            error: None,
+            enabled: true,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Path of the shader of a pass made with `from_asset`.
+    pub fn source_path(&self) -> Option<&str> {
+        self.source_path.as_ref().map(String::as_str)
+    }
+
+    /// Error of the last `reload`, the previous program is used until it succeeds.
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_ref().map(String::as_str)
+    }
+
+    /// Replace the fragment shader, the params are kept.
+    pub fn reload(&mut self, fragment_source: &str) -> Result<(), String> {
+        let shader = compile(&self.name, fragment_source);
+        self.replace_shader(shader)
+    }
+
+    /// Recompile the shader of a pass made with `from_asset`, with the same defines.
+    pub fn reload_asset(&mut self, source: &ShaderSource) -> Result<(), String> {
+        let defines: Vec<(&str, &str)> = self
+            .defines
+            .iter()
+            .map(|&(ref name, ref value)| (name.as_str(), value.as_str()))
+            .collect();
+        let shader = compile_glsl(&self.name, &source.source.with_defines(&defines));
+        self.replace_shader(shader)
+    }
+
+    /// Show the error of a shader file that could not be read, the previous program is kept.
+    pub fn set_reload_error(&mut self, error: String) {
+        self.error = Some(error);
+    }
+
+    fn replace_shader(&mut self, shader: Result<Shader, String>) -> Result<(), String> {
+        let shader = shader.map_err(|e| {
+            self.error = Some(e.clone());
+            e
+        })?;
+        self.shader = shader;
+        self.error = None;
+        Ok(())
+    }
+
+    pub fn shader(&self) -> &Shader {
+        &self.shader
+    }
+
+    pub fn with_param<P: Into<PassParam>>(mut self, name: &str, value: P) -> Self {
+        self.set_param(name, value);
+        self
+    }
+
+    /// Set the value of the uniform `name`.
+    ///
+    /// Uniforms missing from the shader or of another type are ignored, with a warning in debug
+    /// builds.
+    pub fn set_param<P: Into<PassParam>>(&mut self, name: &str, value: P) {
+        let value = value.into();
+        if let Some(param) = self.params.iter_mut().find(|param| param.name == name) {
+            param.value = value;
+            return;
+        }
+        self.params.push(Param {
+            name: name.to_string(),
+            value,
+            range: (0.0, 1.0),
+        });
+    }
+
+    /// Set a param with the range of its inspector slider, (0, 1) by default.
+    pub fn with_ranged_param<P: Into<PassParam>>(
+        mut self,
+        name: &str,
+        value: P,
+        min: f32,
+        max: f32,
+    ) -> Self {
+        self.set_param(name, value);
+        if let Some(param) = self.params.iter_mut().find(|param| param.name == name) {
+            param.range = (min, max);
+        }
+        self
+    }
+
+    pub fn param(&self, name: &str) -> Option<&PassParam> {
+        self.params
+            .iter()
+            .find(|param| param.name == name)
+            .map(|param| &param.value)
+    }
+
+    pub fn remove_param(&mut self, name: &str) -> Option<PassParam> {
+        let index = self.params.iter().position(|param| param.name == name)?;
+        Some(self.params.remove(index).value)
+    }
+
+    pub fn params(&self) -> impl Iterator<Item = (&str, &PassParam)> {
+        self.params
+            .iter()
+            .map(|param| (param.name.as_str(), &param.value))
+    }
+
+    /// Use the program and set its uniforms, the source must be bound to the texture unit 0.
+    pub(super) unsafe fn apply(&self, time: f32, resolution: (u32, u32)) {
+        let shader = &self.shader;
+        let resolution = (resolution.0 as f32, resolution.1 as f32);
+        shader.activate();
+        shader.set_uniform("framebuffer", Sampler(0));
+        if shader.has_uniform("time") {
+            shader.set_uniform("time", time);
+        }
+        if shader.has_uniform("resolution") {
+            shader.set_uniform("resolution", resolution);
+        }
+
+        let mut unit = 1;
+        for param in &self.params {
+            let name = param.name.as_str();
+            match param.value {
+                PassParam::Float(x) => shader.set_uniform(name, x),
+                PassParam::Vec2(x, y) => shader.set_uniform(name, (x, y)),
+                PassParam::Vec3(x, y, z) => shader.set_uniform(name, (x, y, z)),
+                PassParam::Vec4(x, y, z, w) => shader.set_uniform(name, (x, y, z, w)),
+                PassParam::Int(x) => shader.set_uniform(name, x),
+                PassParam::Time => shader.set_uniform(name, time),
+                PassParam::Resolution => shader.set_uniform(name, resolution),
+                PassParam::Texture(ref texture) => {
+                    gl::ActiveTexture(gl::TEXTURE0 + unit);
+                    sys::SDL_GL_BindTexture(texture.raw(), ptr::null_mut(), ptr::null_mut());
+                    unit += 1;
+                    shader.set_uniform(name, Sampler(unit - 1))
+                }
+            };
+        }
+        gl::ActiveTexture(gl::TEXTURE0);
+    }
+
+    /// Unbind the extra textures bound by `apply`.
+    pub(super) unsafe fn unbind_textures(&self) {
+        let mut unit = 1;
+        for param in &self.params {
+            if let PassParam::Texture(ref texture) = param.value {
+                gl::ActiveTexture(gl::TEXTURE0 + unit);
+                sys::SDL_GL_UnbindTexture(texture.raw());
+                unit += 1;
+            }
+        }
+        gl::ActiveTexture(gl::TEXTURE0);
+    }
+
+    /// Widgets to tune the params.
+    #[cfg(debug_assertions)]
+    pub(super) fn inspect(&mut self, ui: &Ui) {
+        for param in &mut self.params {
+            let (min, max) = param.range;
+            let label = im_str!("{}", param.name);
+            match param.value {
+                PassParam::Float(ref mut x) => {
+                    ui.slider_float(label, x, min, max).build();
+                }
+                PassParam::Vec2(ref mut x, ref mut y) => {
+                    let mut values = [*x, *y];
+                    if ui.slider_float2(label, &mut values, min, max).build() {
+                        *x = values[0];
+                        *y = values[1];
+                    }
+                }
+                PassParam::Vec3(ref mut x, ref mut y, ref mut z) => {
+                    let mut values = [*x, *y, *z];
+                    if ui.slider_float3(label, &mut values, min, max).build() {
+                        *x = values[0];
+                        *y = values[1];
+                        *z = values[2];
+                    }
+                }
+                PassParam::Vec4(ref mut x, ref mut y, ref mut z, ref mut w) => {
+                    let mut values = [*x, *y, *z, *w];
+                    if ui.slider_float4(label, &mut values, min, max).build() {
+                        *x = values[0];
+                        *y = values[1];
+                        *z = values[2];
+                        *w = values[3];
+                    }
+                }
+                PassParam::Int(ref mut x) => {
+                    ui.slider_int(label, x, min as i32, max as i32).build();
+                }
+                PassParam::Time => ui.text(im_str!("{}: time", param.name)),
+                PassParam::Resolution => ui.text(im_str!("{}: resolution", param.name)),
+                PassParam::Texture(_) => ui.text(im_str!("{}: texture", param.name)),
+            }
+        }
+    }
+}
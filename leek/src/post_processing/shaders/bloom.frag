@@ -0,0 +1,30 @@
+#version 130
+in vec2 f_uv;
+out vec4 LFragment;
+
+uniform sampler2D framebuffer;
+uniform vec2 resolution;
+uniform float threshold;
+uniform float intensity;
+uniform float radius;
+
+vec3 bright(vec2 uv) {
+    vec3 color = texture(framebuffer, uv).rgb;
+    float luma = dot(color, vec3(0.2126, 0.7152, 0.0722));
+    return color * smoothstep(threshold, threshold + 0.1, luma);
+}
+
+void main() {
+    vec2 texel = radius / 4.0 / resolution;
+    vec3 glow = vec3(0.0);
+    float total = 0.0;
+    for (int x = -4; x <= 4; x++) {
+        for (int y = -4; y <= 4; y++) {
+            float weight = exp(-float(x * x + y * y) / 8.0);
+            glow += bright(f_uv + vec2(x, y) * texel) * weight;
+            total += weight;
+        }
+    }
+    vec3 color = texture(framebuffer, f_uv).rgb;
+    LFragment = vec4(color + glow / total * intensity, 1.0);
+}
@@ -0,0 +1,16 @@
+#version 130
+in vec2 f_uv;
+out vec4 LFragment;
+
+uniform sampler2D framebuffer;
+uniform vec2 resolution;
+uniform float offset;
+
+void main() {
+    // Offset in pixels at the edges, none at the center
+    vec2 direction = (f_uv - vec2(0.5)) * 2.0 * offset / resolution;
+    float red = texture(framebuffer, f_uv + direction).r;
+    float green = texture(framebuffer, f_uv).g;
+    float blue = texture(framebuffer, f_uv - direction).b;
+    LFragment = vec4(red, green, blue, 1.0);
+}
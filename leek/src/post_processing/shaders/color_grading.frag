@@ -0,0 +1,27 @@
+#version 130
+in vec2 f_uv;
+out vec4 LFragment;
+
+uniform sampler2D framebuffer;
+uniform sampler2D lut;
+uniform float lut_size;
+uniform float intensity;
+
+// The LUT is a strip of lut_size squares, one per blue value, red grows to the right and
+// green downward
+vec3 grade(vec3 color) {
+    float blue = color.b * (lut_size - 1.0);
+    float slice = floor(blue);
+    float next_slice = min(slice + 1.0, lut_size - 1.0);
+
+    vec2 texel = vec2(1.0 / (lut_size * lut_size), 1.0 / lut_size);
+    vec2 uv = (color.rg * (lut_size - 1.0) + 0.5) * texel;
+    vec3 first = texture(lut, uv + vec2(slice / lut_size, 0.0)).rgb;
+    vec3 second = texture(lut, uv + vec2(next_slice / lut_size, 0.0)).rgb;
+    return mix(first, second, blue - slice);
+}
+
+void main() {
+    vec3 color = texture(framebuffer, f_uv).rgb;
+    LFragment = vec4(mix(color, grade(color), intensity), 1.0);
+}
@@ -0,0 +1,34 @@
+#version 130
+in vec2 f_uv;
+out vec4 LFragment;
+
+uniform sampler2D framebuffer;
+uniform float curvature;
+uniform float scanlines;
+uniform float scanline_intensity;
+uniform float vignette;
+
+// Bend the screen like the glass of a tube
+vec2 curve(vec2 uv) {
+    uv = uv * 2.0 - 1.0;
+    vec2 offset = abs(uv.yx) * curvature;
+    uv = uv + uv * offset * offset;
+    return uv * 0.5 + 0.5;
+}
+
+void main() {
+    vec2 uv = curve(f_uv);
+    if (uv.x < 0.0 || uv.x > 1.0 || uv.y < 0.0 || uv.y > 1.0) {
+        LFragment = vec4(0.0, 0.0, 0.0, 1.0);
+        return;
+    }
+    vec3 color = texture(framebuffer, uv).rgb;
+
+    float scanline = 0.5 + 0.5 * sin(uv.y * scanlines * 3.14159265);
+    color *= 1.0 - scanline_intensity * (1.0 - scanline);
+
+    vec2 edge = uv * (1.0 - uv.yx);
+    color *= mix(1.0, pow(edge.x * edge.y * 16.0, 0.25), vignette);
+
+    LFragment = vec4(color, 1.0);
+}
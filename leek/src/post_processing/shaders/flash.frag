@@ -0,0 +1,15 @@
+#version 130
+in vec2 f_uv;
+out vec4 LFragment;
+
+uniform sampler2D framebuffer;
+uniform vec3 color;
+uniform float time;
+uniform float start;
+uniform float duration;
+
+void main() {
+    float amount = 1.0 - clamp((time - start) / max(duration, 0.001), 0.0, 1.0);
+    vec3 frame = texture(framebuffer, f_uv).rgb;
+    LFragment = vec4(mix(frame, color, amount), 1.0);
+}
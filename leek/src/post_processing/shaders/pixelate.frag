@@ -0,0 +1,13 @@
+#version 130
+in vec2 f_uv;
+out vec4 LFragment;
+
+uniform sampler2D framebuffer;
+uniform vec2 resolution;
+uniform float pixel_size;
+
+void main() {
+    vec2 size = max(pixel_size, 1.0) / resolution;
+    vec2 uv = (floor(f_uv / size) + 0.5) * size;
+    LFragment = vec4(texture(framebuffer, uv).rgb, 1.0);
+}
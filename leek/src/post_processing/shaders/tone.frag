@@ -0,0 +1,13 @@
+#version 130
+in vec2 f_uv;
+out vec4 LFragment;
+
+uniform sampler2D framebuffer;
+uniform vec3 tint;
+uniform float intensity;
+
+void main() {
+    vec3 color = texture(framebuffer, f_uv).rgb;
+    float luma = dot(color, vec3(0.299, 0.587, 0.114));
+    LFragment = vec4(mix(color, min(luma * tint, vec3(1.0)), intensity), 1.0);
+}
@@ -0,0 +1,12 @@
+#version 130
+in vec2 f_uv;
+out vec4 LFragment;
+
+uniform sampler2D framebuffer;
+uniform float intensity;
+
+void main() {
+    float len = 1.0 - intensity * length(f_uv - vec2(0.5));
+    vec4 color = len * texture(framebuffer, f_uv);
+    LFragment = vec4(color.xyz, 1.0);
+}
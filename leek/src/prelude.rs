@@ -1,7 +1,10 @@
 pub use super::*;
+pub use atlas::DrawRegion;
+pub use draw_queue::{DrawQueue, Shape, Sprite};
 pub use imgui::Ui;
 pub use sdl2::event::Event;
-pub use sdl2::keyboard::Scancode;
+pub use sdl2::keyboard::{Keycode, Scancode};
 pub use sdl2::pixels::Color;
 pub use sdl2::rect::Rect;
 pub use sdl2::render::Texture;
+pub use shapes::{DrawShapes, ShapeStyle};
@@ -1,7 +1,14 @@
 use alto::Buffer;
 use sdl2::render::Texture;
 
+use animation::AnimationSet;
+use atlas::TextureAtlas;
 use font::BitmapFont;
+use ldtk::LdtkProject;
+use nine_slice::{EdgeMode, Insets, NineSlice};
+use particles::EmitterDef;
+use post_processing::ShaderSource;
+use tiled::TiledMap;
 use std::fmt::Debug;
 
 pub trait CacheKey<Target>: Debug {
@@ -26,6 +33,42 @@ impl From<&'static str> for PathKey {
     }
 }
 
+/// Key of a nine-slice: the path of its texture, the size of the borders and how the edges are drawn
+#[derive(Debug, Clone, Hash, Eq, PartialEq, PartialOrd)]
+pub struct NineSliceKey {
+    pub texture: &'static str,
+    pub insets: Insets,
+    pub mode: EdgeMode,
+}
+
+impl NineSliceKey {
+    pub fn stretched(texture: &'static str, insets: Insets) -> Self {
+        NineSliceKey {
+            texture,
+            insets,
+            mode: EdgeMode::Stretch,
+        }
+    }
+
+    pub fn tiled(texture: &'static str, insets: Insets) -> Self {
+        NineSliceKey {
+            texture,
+            insets,
+            mode: EdgeMode::Tile,
+        }
+    }
+}
+
+impl CacheKey<NineSlice> for NineSliceKey {
+    type Target = NineSlice;
+}
+
+impl From<(&'static str, Insets)> for NineSliceKey {
+    fn from(s: (&'static str, Insets)) -> Self {
+        NineSliceKey::stretched(s.0, s.1)
+    }
+}
+
 /// This key contains the path to the bitmap font and the size of the char
 #[derive(Debug, Clone, Hash, Eq, PartialEq, PartialOrd)]
 pub struct BitmapFontKey(pub String, pub (u32, u32));
@@ -39,3 +82,92 @@ impl<'a> From<(&'a str, (u32, u32))> for BitmapFontKey {
         BitmapFontKey(s.0.to_string(), s.1)
     }
 }
+
+/// Key of a texture atlas: a TexturePacker / Aseprite JSON file, or an image split in cells of the given size
+#[derive(Debug, Clone, Hash, Eq, PartialEq, PartialOrd)]
+pub enum AtlasKey {
+    Json(&'static str),
+    Grid(&'static str, (u32, u32)),
+}
+
+impl AtlasKey {
+    pub fn path(&self) -> &'static str {
+        match *self {
+            AtlasKey::Json(path) | AtlasKey::Grid(path, _) => path,
+        }
+    }
+}
+
+impl CacheKey<TextureAtlas> for AtlasKey {
+    type Target = TextureAtlas;
+}
+
+/// Key of the animations of an Aseprite JSON export
+#[derive(Debug, Clone, Hash, Eq, PartialEq, PartialOrd)]
+pub struct AnimationKey(pub &'static str);
+
+impl CacheKey<AnimationSet> for AnimationKey {
+    type Target = AnimationSet;
+}
+
+impl From<&'static str> for AnimationKey {
+    fn from(s: &'static str) -> Self {
+        AnimationKey(s)
+    }
+}
+
+/// Key of a Tiled map, saved as TMX or JSON
+#[derive(Debug, Clone, Hash, Eq, PartialEq, PartialOrd)]
+pub struct MapKey(pub &'static str);
+
+impl CacheKey<TiledMap> for MapKey {
+    type Target = TiledMap;
+}
+
+impl From<&'static str> for MapKey {
+    fn from(s: &'static str) -> Self {
+        MapKey(s)
+    }
+}
+
+/// Key of an LDtk project
+#[derive(Debug, Clone, Hash, Eq, PartialEq, PartialOrd)]
+pub struct LdtkKey(pub &'static str);
+
+impl CacheKey<LdtkProject> for LdtkKey {
+    type Target = LdtkProject;
+}
+
+impl From<&'static str> for LdtkKey {
+    fn from(s: &'static str) -> Self {
+        LdtkKey(s)
+    }
+}
+
+/// Key of a particle emitter definition, saved as JSON
+#[derive(Debug, Clone, Hash, Eq, PartialEq, PartialOrd)]
+pub struct ParticleKey(pub &'static str);
+
+impl CacheKey<EmitterDef> for ParticleKey {
+    type Target = EmitterDef;
+}
+
+impl From<&'static str> for ParticleKey {
+    fn from(s: &'static str) -> Self {
+        ParticleKey(s)
+    }
+}
+
+/// Key of a post-processing fragment shader
+#[derive(Debug, Clone, Hash, Eq, PartialEq, PartialOrd)]
+pub struct ShaderKey(pub &'static str);
+
+impl CacheKey<ShaderSource> for ShaderKey {
+    type Target = ShaderSource;
+}
+
+impl From<&'static str> for ShaderKey {
+    fn from(s: &'static str) -> Self {
+        ShaderKey(s)
+    }
+}
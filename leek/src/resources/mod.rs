@@ -7,8 +7,18 @@ use sdl2::video::WindowContext;
 use alto;
 use alto_utils::load_buffer_from_ogg_file;
 
+use animation::AnimationSet;
+use atlas::{parse_atlas, parse_frame_tags, TextureAtlas};
 use font::BitmapFont;
-
+use ldtk::{parse_ldtk_level, parse_ldtk_project, LdtkProject};
+use nine_slice::NineSlice;
+use particles::{parse_emitter, EmitterDef};
+use opengl::GlslSource;
+use post_processing::ShaderSource;
+use tiled::{parse_json_map, parse_json_tileset, parse_tmx_map, parse_tsx_tileset, TiledMap};
+
+use std::fs;
+use std::path::{Component, Path, PathBuf};
 use std::sync::Arc;
 
 use imgui::Ui;
@@ -29,8 +39,18 @@ pub use self::load::*;
 pub struct Resources {
     pub inspect_window: bool,
     texture_cache: HashCache<PathKey, Texture>,
+    nine_slice_cache: HashCache<NineSliceKey, NineSlice>,
     bitmap_font_cache: HashCache<BitmapFontKey, BitmapFont>,
     audio_buffer_cache: HashCache<PathKey, alto::Buffer>,
+    atlas_cache: HashCache<AtlasKey, TextureAtlas>,
+    animation_cache: HashCache<AnimationKey, AnimationSet>,
+    map_cache: HashCache<MapKey, TiledMap>,
+    ldtk_cache: HashCache<LdtkKey, LdtkProject>,
+    particle_cache: HashCache<ParticleKey, EmitterDef>,
+    shader_cache: HashCache<ShaderKey, ShaderSource>,
+    /// Shaders reloaded by `sync_resources` since the last `take_reloaded_shaders`, with the
+    /// error when the file could not be read or preprocessed.
+    reloaded_shaders: Vec<(ShaderKey, Result<(), String>)>,
     texture_creator: TextureCreator<WindowContext>,
     alto_context: alto::Context,
     watcher: RecommendedWatcher,
@@ -81,13 +101,27 @@ impl Resources {
             texture_creator,
             alto_context,
             texture_cache: Default::default(),
+            nine_slice_cache: Default::default(),
             bitmap_font_cache: Default::default(),
             audio_buffer_cache: Default::default(),
+            atlas_cache: Default::default(),
+            animation_cache: Default::default(),
+            map_cache: Default::default(),
+            ldtk_cache: Default::default(),
+            particle_cache: Default::default(),
+            shader_cache: Default::default(),
+            reloaded_shaders: Vec::new(),
             watcher,
             receiver,
         }
     }
 
+    /// Keys of the shaders reloaded since the last call, an include shared by several shaders
+    /// reloads all of them. The cache keeps the previous source of the ones that failed.
+    pub fn take_reloaded_shaders(&mut self) -> Vec<(ShaderKey, Result<(), String>)> {
+        self.reloaded_shaders.drain(..).collect()
+    }
+
     pub fn sync_resources(&mut self) -> Option<PathKey> {
         match self.receiver.try_recv() {
             Ok(event) => match event {
@@ -102,12 +136,31 @@ impl Resources {
                         if let Ok(new_value) = self.load_resource(&key) {
                             println!("Reloaded {}", key.0);
                             self.texture_cache.insert(key.clone(), new_value);
+                            self.reload_texture_users(key.0);
                             return Some(key);
                         } else {
                             println!("Error during reloading {}", key.0);
                         }
                     }
 
+                    // Same for nine-slices
+
+                    let key: Option<NineSliceKey> = self.nine_slice_cache
+                        .into_iter()
+                        .filter(|&(k, _)| path.ends_with(k.texture))
+                        .map(|(ref k, _)| (*k).clone())
+                        .nth(0);
+
+                    if let Some(key) = key {
+                        if let Ok(new_value) = self.load_resource(&key) {
+                            println!("Reloaded {}", key.texture);
+                            self.nine_slice_cache.insert(key.clone(), new_value);
+                            return Some(PathKey(key.texture));
+                        } else {
+                            println!("Error during reloading {}", key.texture);
+                        }
+                    }
+
                     // Same for audio
 
                     let key: Option<PathKey> = self.audio_buffer_cache
@@ -125,6 +178,147 @@ impl Resources {
                             println!("Error during reloading {}", key.0);
                         }
                     }
+
+                    // Atlases are reloaded when either their file or their image changes
+
+                    let key: Option<AtlasKey> = self.atlas_cache
+                        .into_iter()
+                        .filter(|&(k, atlas)| {
+                            path.ends_with(k.path()) || path.ends_with(atlas.image_path())
+                        })
+                        .map(|(ref k, _)| (*k).clone())
+                        .nth(0);
+
+                    if let Some(key) = key {
+                        if let Ok(new_value) = self.load_resource(&key) {
+                            println!("Reloaded {}", key.path());
+                            self.atlas_cache.insert(key.clone(), new_value);
+                            self.reload_animations_of(key.path());
+                            return Some(PathKey(key.path()));
+                        } else {
+                            println!("Error during reloading {}", key.path());
+                        }
+                    }
+
+                    // Same for animations
+
+                    let key: Option<AnimationKey> = self.animation_cache
+                        .into_iter()
+                        .filter(|&(k, animations)| {
+                            path.ends_with(k.0) || path.ends_with(animations.atlas().image_path())
+                        })
+                        .map(|(ref k, _)| (*k).clone())
+                        .nth(0);
+
+                    if let Some(key) = key {
+                        if let Ok(new_value) = self.load_resource(&key) {
+                            println!("Reloaded {}", key.0);
+                            self.animation_cache.insert(key.clone(), new_value);
+                            return Some(PathKey(key.0));
+                        } else {
+                            println!("Error during reloading {}", key.0);
+                        }
+                    }
+
+                    // Maps are reloaded when their file, a tileset or a tileset image changes
+
+                    let key: Option<MapKey> = self.map_cache
+                        .into_iter()
+                        .filter(|&(k, map)| {
+                            path.ends_with(k.0) || map.tilesets.iter().any(|tileset| {
+                                path.ends_with(&tileset.image)
+                                    || tileset.source.as_ref().map_or(false, |s| path.ends_with(s))
+                            })
+                        })
+                        .map(|(ref k, _)| (*k).clone())
+                        .nth(0);
+
+                    if let Some(key) = key {
+                        if let Ok(new_value) = self.load_resource(&key) {
+                            println!("Reloaded {}", key.0);
+                            self.map_cache.insert(key.clone(), new_value);
+                            return Some(PathKey(key.0));
+                        } else {
+                            println!("Error during reloading {}", key.0);
+                        }
+                    }
+
+                    // LDtk projects are reloaded when their file, a level file or a tileset changes
+
+                    let key: Option<LdtkKey> = self.ldtk_cache
+                        .into_iter()
+                        .filter(|&(k, project)| {
+                            path.ends_with(k.0)
+                                || project.tilesets.iter().any(|t| path.ends_with(&t.image))
+                                || project.levels.iter().any(|level| {
+                                    level.external.as_ref().map_or(false, |e| path.ends_with(e))
+                                })
+                        })
+                        .map(|(ref k, _)| (*k).clone())
+                        .nth(0);
+
+                    if let Some(key) = key {
+                        if let Ok(new_value) = self.load_resource(&key) {
+                            println!("Reloaded {}", key.0);
+                            self.ldtk_cache.insert(key.clone(), new_value);
+                            return Some(PathKey(key.0));
+                        } else {
+                            println!("Error during reloading {}", key.0);
+                        }
+                    }
+
+                    // Particle emitters are reloaded when their file or their image changes
+
+                    let key: Option<ParticleKey> = self.particle_cache
+                        .into_iter()
+                        .filter(|&(k, def)| {
+                            path.ends_with(k.0)
+                                || path.ends_with(relative_path(k.0, &def.image))
+                        })
+                        .map(|(ref k, _)| (*k).clone())
+                        .nth(0);
+
+                    if let Some(key) = key {
+                        if let Ok(new_value) = self.load_resource(&key) {
+                            println!("Reloaded {}", key.0);
+                            self.particle_cache.insert(key.clone(), new_value);
+                            return Some(PathKey(key.0));
+                        } else {
+                            println!("Error during reloading {}", key.0);
+                        }
+                    }
+
+                    // Shaders are reloaded when their file or an included file changes, an
+                    // include can be shared so all of them are reloaded. The engine recompiles
+                    // the passes using them.
+
+                    let keys: Vec<ShaderKey> = self.shader_cache
+                        .into_iter()
+                        .filter(|&(k, shader)| {
+                            path.ends_with(k.0)
+                                || shader.source.includes().iter().any(|i| path.ends_with(i))
+                        })
+                        .map(|(ref k, _)| (*k).clone())
+                        .collect();
+
+                    let mut reloaded = None;
+                    for key in keys {
+                        match self.load_resource(&key) {
+                            Ok(new_value) => {
+                                println!("Reloaded {}", key.0);
+                                self.shader_cache.insert(key.clone(), new_value);
+                                reloaded = reloaded.or(Some(PathKey(key.0)));
+                                self.reloaded_shaders.push((key, Ok(())));
+                            }
+                            Err(e) => {
+                                println!("Error during reloading {}", key.0);
+                                self.reloaded_shaders.push((key, Err(e)));
+                            }
+                        }
+                    }
+                    if reloaded.is_some() {
+                        return reloaded;
+                    }
                 }
                 _ => {}
             },
@@ -133,6 +327,104 @@ impl Resources {
         None
     }
 
+    /// Reload the animations made from the atlas file or image at `path`, they still hold the
+    /// previous atlas.
+    fn reload_animations_of(&mut self, path: &str) {
+        let animations: Vec<AnimationKey> = self.animation_cache
+            .into_iter()
+            .filter(|&(k, animations)| k.0 == path || animations.atlas().image_path() == path)
+            .map(|(ref k, _)| (*k).clone())
+            .collect();
+        for key in animations {
+            match self.load_resource(&key) {
+                Ok(new_value) => {
+                    println!("Reloaded {}", key.0);
+                    self.animation_cache.insert(key, new_value);
+                }
+                Err(e) => println!("Error during reloading {}: {}", key.0, e),
+            }
+        }
+    }
+
+    /// Reload the resources sharing the texture at `path`, they still hold the previous one.
+    fn reload_texture_users(&mut self, path: &str) {
+        let atlases: Vec<AtlasKey> = self.atlas_cache
+            .into_iter()
+            .filter(|&(_, atlas)| atlas.image_path() == path)
+            .map(|(ref k, _)| (*k).clone())
+            .collect();
+        for key in atlases {
+            match self.load_resource(&key) {
+                Ok(new_value) => {
+                    println!("Reloaded {}", key.path());
+                    self.atlas_cache.insert(key, new_value);
+                }
+                Err(e) => println!("Error during reloading {}: {}", key.path(), e),
+            }
+        }
+        self.reload_animations_of(path);
+
+        let nine_slices: Vec<NineSliceKey> = self.nine_slice_cache
+            .into_iter()
+            .filter(|&(k, _)| k.texture == path)
+            .map(|(ref k, _)| (*k).clone())
+            .collect();
+        for key in nine_slices {
+            match self.load_resource(&key) {
+                Ok(new_value) => {
+                    println!("Reloaded {}", key.texture);
+                    self.nine_slice_cache.insert(key, new_value);
+                }
+                Err(e) => println!("Error during reloading {}: {}", key.texture, e),
+            }
+        }
+
+        let maps: Vec<MapKey> = self.map_cache
+            .into_iter()
+            .filter(|&(_, map)| map.tilesets.iter().any(|tileset| tileset.image == path))
+            .map(|(ref k, _)| (*k).clone())
+            .collect();
+        for key in maps {
+            match self.load_resource(&key) {
+                Ok(new_value) => {
+                    println!("Reloaded {}", key.0);
+                    self.map_cache.insert(key, new_value);
+                }
+                Err(e) => println!("Error during reloading {}: {}", key.0, e),
+            }
+        }
+
+        let projects: Vec<LdtkKey> = self.ldtk_cache
+            .into_iter()
+            .filter(|&(_, project)| project.tilesets.iter().any(|tileset| tileset.image == path))
+            .map(|(ref k, _)| (*k).clone())
+            .collect();
+        for key in projects {
+            match self.load_resource(&key) {
+                Ok(new_value) => {
+                    println!("Reloaded {}", key.0);
+                    self.ldtk_cache.insert(key, new_value);
+                }
+                Err(e) => println!("Error during reloading {}: {}", key.0, e),
+            }
+        }
+
+        let emitters: Vec<ParticleKey> = self.particle_cache
+            .into_iter()
+            .filter(|&(k, def)| relative_path(k.0, &def.image) == path)
+            .map(|(ref k, _)| (*k).clone())
+            .collect();
+        for key in emitters {
+            match self.load_resource(&key) {
+                Ok(new_value) => {
+                    println!("Reloaded {}", key.0);
+                    self.particle_cache.insert(key, new_value);
+                }
+                Err(e) => println!("Error during reloading {}: {}", key.0, e),
+            }
+        }
+    }
+
     pub fn inspect(&mut self, ui: &Ui) {
         use math::format_bytes;
         use std::mem::size_of;
@@ -184,6 +476,28 @@ impl Resources {
                 );
                 // -----
                 ui.new_line();
+                ui.text(im_str!("Nine-slice cache"));
+                ui.separator();
+
+                // The textures can be shared with the texture cache
+                let ram_usage = size_of::<NineSlice>() * self.nine_slice_cache.size();
+                let vram_estimate: usize = (self.nine_slice_cache.into_iter())
+                    .fold(0, |acc, (_, nine_slice)| acc + nine_slice.vram_size());
+
+                ui.text(im_str!(
+                    "Using {} of RAM and up to {} of VRAM",
+                    format_bytes(ram_usage as f64),
+                    format_bytes(vram_estimate as f64)
+                ));
+
+                self.nine_slice_cache.inspect(
+                    ui,
+                    "nine_slice_cache",
+                    |key| key.texture.trim_left_matches("assets/textures/"),
+                    |value| value.vram_size(),
+                );
+                // -----
+                ui.new_line();
                 ui.text(im_str!("Audio cache"));
                 ui.separator();
 
@@ -234,6 +548,133 @@ impl Resources {
                     |key| key.0.trim_left_matches("assets/fonts/"),
                     |value| value.vram_size(),
                 );
+
+                // -----
+                ui.new_line();
+                ui.text(im_str!("Atlas cache"));
+                ui.separator();
+
+                let ram_usage = size_of::<TextureAtlas>() * self.atlas_cache.size();
+                let vram_estimate: usize = (self.atlas_cache.into_iter())
+                    .fold(0, |acc, (_, atlas)| acc + atlas.vram_size());
+
+                ui.text(im_str!(
+                    "Using {} of RAM and {} of VRAM",
+                    format_bytes(ram_usage as f64),
+                    format_bytes(vram_estimate as f64)
+                ));
+
+                self.atlas_cache.inspect(
+                    ui,
+                    "atlas_cache",
+                    |key| key.path().trim_left_matches("assets/textures/"),
+                    |value| value.vram_size(),
+                );
+
+                // -----
+                ui.new_line();
+                ui.text(im_str!("Animation cache"));
+                ui.separator();
+
+                let ram_usage = size_of::<AnimationSet>() * self.animation_cache.size();
+                let vram_estimate: usize = (self.animation_cache.into_iter())
+                    .fold(0, |acc, (_, animations)| acc + animations.vram_size());
+
+                ui.text(im_str!(
+                    "Using {} of RAM and {} of VRAM",
+                    format_bytes(ram_usage as f64),
+                    format_bytes(vram_estimate as f64)
+                ));
+
+                self.animation_cache.inspect(
+                    ui,
+                    "animation_cache",
+                    |key| key.0.trim_left_matches("assets/textures/"),
+                    |value| value.vram_size(),
+                );
+
+                // -----
+                ui.new_line();
+                ui.text(im_str!("Map cache"));
+                ui.separator();
+
+                let ram_usage = size_of::<TiledMap>() * self.map_cache.size();
+                let vram_estimate: usize = (self.map_cache.into_iter())
+                    .fold(0, |acc, (_, map)| acc + map.vram_size());
+
+                ui.text(im_str!(
+                    "Using {} of RAM and {} of VRAM",
+                    format_bytes(ram_usage as f64),
+                    format_bytes(vram_estimate as f64)
+                ));
+
+                self.map_cache.inspect(
+                    ui,
+                    "map_cache",
+                    |key| key.0.trim_left_matches("assets/maps/"),
+                    |value| value.vram_size(),
+                );
+
+                // -----
+                ui.new_line();
+                ui.text(im_str!("LDtk cache"));
+                ui.separator();
+
+                let ram_usage = size_of::<LdtkProject>() * self.ldtk_cache.size();
+                let vram_estimate: usize = (self.ldtk_cache.into_iter())
+                    .fold(0, |acc, (_, project)| acc + project.vram_size());
+
+                ui.text(im_str!(
+                    "Using {} of RAM and {} of VRAM",
+                    format_bytes(ram_usage as f64),
+                    format_bytes(vram_estimate as f64)
+                ));
+
+                self.ldtk_cache.inspect(
+                    ui,
+                    "ldtk_cache",
+                    |key| key.0.trim_left_matches("assets/maps/"),
+                    |value| value.vram_size(),
+                );
+
+                // -----
+                ui.new_line();
+                ui.text(im_str!("Particle cache"));
+                ui.separator();
+
+                let ram_usage = size_of::<EmitterDef>() * self.particle_cache.size();
+                let vram_estimate: usize = (self.particle_cache.into_iter())
+                    .fold(0, |acc, (_, def)| acc + def.vram_size());
+
+                ui.text(im_str!(
+                    "Using {} of RAM and {} of VRAM",
+                    format_bytes(ram_usage as f64),
+                    format_bytes(vram_estimate as f64)
+                ));
+
+                self.particle_cache.inspect(
+                    ui,
+                    "particle_cache",
+                    |key| key.0.trim_left_matches("assets/particles/"),
+                    |value| value.vram_size(),
+                );
+
+                // -----
+                ui.new_line();
+                ui.text(im_str!("Shader cache"));
+                ui.separator();
+
+                let ram_usage: usize = (self.shader_cache.into_iter())
+                    .fold(0, |acc, (_, shader)| acc + shader.source.source().len());
+
+                ui.text(im_str!("Using {} of RAM", format_bytes(ram_usage as f64)));
+
+                self.shader_cache.inspect(
+                    ui,
+                    "shader_cache",
+                    |key| key.0.trim_left_matches("assets/shaders/"),
+                    |_| 0,
+                );
             });
 
         self.inspect_window = opened;
@@ -295,6 +736,18 @@ impl Resources {
             .create_texture_target(format, width, height)
     }
 
+    /// The texture of the texture cache if it is loaded, a new one otherwise.
+    fn shared_texture(&self, path: &str) -> Result<Arc<Texture>, String> {
+        let cached = self.texture_cache
+            .into_iter()
+            .find(|&(key, _)| key.0 == path)
+            .map(|(_, texture)| Arc::clone(texture));
+        match cached {
+            Some(texture) => Ok(texture),
+            None => self.texture_creator.load_texture(path).map(Arc::new),
+        }
+    }
+
     pub fn create_texture_from_surface<S: AsRef<SurfaceRef>>(
         &self,
         surface: S,
@@ -338,6 +791,42 @@ impl Cache<PathKey, Texture> for Resources {
     }
 }
 
+impl LoadCache<NineSliceKey, NineSlice> for Resources {}
+
+impl Loader<NineSliceKey, NineSlice> for Resources {
+    type Error = String;
+
+    fn load_resource(&self, key: &NineSliceKey) -> Result<NineSlice, Self::Error> {
+        let texture = self.shared_texture(key.texture)?;
+        Ok(NineSlice::new(texture, key.texture, key.insets, key.mode))
+    }
+}
+
+impl Cache<NineSliceKey, NineSlice> for Resources {
+    fn get(&self, key: &NineSliceKey) -> Option<Arc<NineSlice>> {
+        self.nine_slice_cache.get(key)
+    }
+
+    fn insert(&mut self, key: NineSliceKey, value: NineSlice) -> Option<Arc<NineSlice>> {
+        self.nine_slice_cache.insert(key, value)
+    }
+
+    fn remove(&mut self, key: &NineSliceKey) -> Option<Arc<NineSlice>> {
+        self.nine_slice_cache.remove(key)
+    }
+
+    fn clear(&mut self) {
+        self.nine_slice_cache.clear();
+    }
+
+    fn size(&self) -> usize {
+        self.nine_slice_cache.size()
+    }
+    fn drop_unused(&mut self) {
+        self.nine_slice_cache.drop_unused()
+    }
+}
+
 impl LoadCache<BitmapFontKey, BitmapFont> for Resources {}
 
 impl Loader<BitmapFontKey, BitmapFont> for Resources {
@@ -409,3 +898,338 @@ impl Cache<PathKey, alto::Buffer> for Resources {
         self.texture_cache.drop_unused()
     }
 }
+
+impl LoadCache<AtlasKey, TextureAtlas> for Resources {}
+
+impl Loader<AtlasKey, TextureAtlas> for Resources {
+    type Error = String;
+
+    fn load_resource(&self, key: &AtlasKey) -> Result<TextureAtlas, Self::Error> {
+        match *key {
+            AtlasKey::Json(path) => {
+                let json = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+                let (image, regions) =
+                    parse_atlas(&json).map_err(|e| format!("{}: {}", path, e))?;
+
+                // The image path is relative to the JSON file
+                let image_path = relative_path(path, &image);
+                let texture = self.shared_texture(&image_path)?;
+
+                Ok(TextureAtlas::new(texture, &image_path, regions))
+            }
+            AtlasKey::Grid(path, cell_size) => {
+                let texture = self.shared_texture(path)?;
+                Ok(TextureAtlas::from_grid(texture, path, cell_size))
+            }
+        }
+    }
+}
+
+impl Cache<AtlasKey, TextureAtlas> for Resources {
+    fn get(&self, key: &AtlasKey) -> Option<Arc<TextureAtlas>> {
+        self.atlas_cache.get(key)
+    }
+
+    fn insert(&mut self, key: AtlasKey, value: TextureAtlas) -> Option<Arc<TextureAtlas>> {
+        self.atlas_cache.insert(key, value)
+    }
+
+    fn remove(&mut self, key: &AtlasKey) -> Option<Arc<TextureAtlas>> {
+        self.atlas_cache.remove(key)
+    }
+
+    fn clear(&mut self) {
+        self.atlas_cache.clear();
+    }
+
+    fn size(&self) -> usize {
+        self.atlas_cache.size()
+    }
+    fn drop_unused(&mut self) {
+        self.atlas_cache.drop_unused()
+    }
+}
+
+impl LoadCache<AnimationKey, AnimationSet> for Resources {}
+
+impl Loader<AnimationKey, AnimationSet> for Resources {
+    type Error = String;
+
+    fn load_resource(&self, key: &AnimationKey) -> Result<AnimationSet, Self::Error> {
+        let atlas_key = AtlasKey::Json(key.0);
+        let atlas = match self.atlas_cache.get(&atlas_key) {
+            Some(atlas) => atlas,
+            None => Arc::new(self.load_resource(&atlas_key)?),
+        };
+
+        let json = fs::read_to_string(key.0).map_err(|e| format!("{}: {}", key.0, e))?;
+        let tags = parse_frame_tags(&json).map_err(|e| format!("{}: {}", key.0, e))?;
+
+        AnimationSet::new(atlas, &tags).map_err(|e| format!("{}: {}", key.0, e))
+    }
+}
+
+impl Cache<AnimationKey, AnimationSet> for Resources {
+    fn get(&self, key: &AnimationKey) -> Option<Arc<AnimationSet>> {
+        self.animation_cache.get(key)
+    }
+
+    fn insert(&mut self, key: AnimationKey, value: AnimationSet) -> Option<Arc<AnimationSet>> {
+        self.animation_cache.insert(key, value)
+    }
+
+    fn remove(&mut self, key: &AnimationKey) -> Option<Arc<AnimationSet>> {
+        self.animation_cache.remove(key)
+    }
+
+    fn clear(&mut self) {
+        self.animation_cache.clear();
+    }
+
+    fn size(&self) -> usize {
+        self.animation_cache.size()
+    }
+    fn drop_unused(&mut self) {
+        self.animation_cache.drop_unused()
+    }
+}
+
+impl LoadCache<MapKey, TiledMap> for Resources {}
+
+impl Loader<MapKey, TiledMap> for Resources {
+    type Error = String;
+
+    fn load_resource(&self, key: &MapKey) -> Result<TiledMap, Self::Error> {
+        let path = key.0;
+        let content = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        let mut map = if path.ends_with(".json") || path.ends_with(".tmj") {
+            parse_json_map(&content)
+        } else {
+            parse_tmx_map(&content)
+        }.map_err(|e| format!("{}: {}", path, e))?;
+
+        for tileset in &mut map.tilesets {
+            // The paths are relative to the file containing them
+            let mut base = path.to_string();
+            if let Some(source) = tileset.source.clone() {
+                let source = relative_path(path, &source);
+                let content =
+                    fs::read_to_string(&source).map_err(|e| format!("{}: {}", source, e))?;
+                *tileset = if source.ends_with(".json") || source.ends_with(".tsj") {
+                    parse_json_tileset(&content, tileset.first_gid)
+                } else {
+                    parse_tsx_tileset(&content, tileset.first_gid)
+                }.map_err(|e| format!("{}: {}", source, e))?;
+                tileset.source = Some(source.clone());
+                base = source;
+            }
+
+            tileset.image = relative_path(&base, &tileset.image);
+            tileset.texture = Some(self.shared_texture(&tileset.image)?);
+        }
+        Ok(map)
+    }
+}
+
+impl Cache<MapKey, TiledMap> for Resources {
+    fn get(&self, key: &MapKey) -> Option<Arc<TiledMap>> {
+        self.map_cache.get(key)
+    }
+
+    fn insert(&mut self, key: MapKey, value: TiledMap) -> Option<Arc<TiledMap>> {
+        self.map_cache.insert(key, value)
+    }
+
+    fn remove(&mut self, key: &MapKey) -> Option<Arc<TiledMap>> {
+        self.map_cache.remove(key)
+    }
+
+    fn clear(&mut self) {
+        self.map_cache.clear();
+    }
+
+    fn size(&self) -> usize {
+        self.map_cache.size()
+    }
+    fn drop_unused(&mut self) {
+        self.map_cache.drop_unused()
+    }
+}
+
+impl LoadCache<LdtkKey, LdtkProject> for Resources {}
+
+impl Loader<LdtkKey, LdtkProject> for Resources {
+    type Error = String;
+
+    fn load_resource(&self, key: &LdtkKey) -> Result<LdtkProject, Self::Error> {
+        let path = key.0;
+        let content = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        let mut project = parse_ldtk_project(&content).map_err(|e| format!("{}: {}", path, e))?;
+
+        // The paths are relative to the project
+        for level in &mut project.levels {
+            if let Some(external) = level.external.clone() {
+                let external = relative_path(path, &external);
+                let content =
+                    fs::read_to_string(&external).map_err(|e| format!("{}: {}", external, e))?;
+                *level = parse_ldtk_level(&content).map_err(|e| format!("{}: {}", external, e))?;
+                level.external = Some(external);
+            }
+        }
+
+        for tileset in &mut project.tilesets {
+            tileset.image = relative_path(path, &tileset.image);
+            tileset.texture = Some(self.shared_texture(&tileset.image)?);
+        }
+        Ok(project)
+    }
+}
+
+impl Cache<LdtkKey, LdtkProject> for Resources {
+    fn get(&self, key: &LdtkKey) -> Option<Arc<LdtkProject>> {
+        self.ldtk_cache.get(key)
+    }
+
+    fn insert(&mut self, key: LdtkKey, value: LdtkProject) -> Option<Arc<LdtkProject>> {
+        self.ldtk_cache.insert(key, value)
+    }
+
+    fn remove(&mut self, key: &LdtkKey) -> Option<Arc<LdtkProject>> {
+        self.ldtk_cache.remove(key)
+    }
+
+    fn clear(&mut self) {
+        self.ldtk_cache.clear();
+    }
+
+    fn size(&self) -> usize {
+        self.ldtk_cache.size()
+    }
+    fn drop_unused(&mut self) {
+        self.ldtk_cache.drop_unused()
+    }
+}
+
+impl LoadCache<ParticleKey, EmitterDef> for Resources {}
+
+impl Loader<ParticleKey, EmitterDef> for Resources {
+    type Error = String;
+
+    fn load_resource(&self, key: &ParticleKey) -> Result<EmitterDef, Self::Error> {
+        let path = key.0;
+        let content = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        let mut def = parse_emitter(&content).map_err(|e| format!("{}: {}", path, e))?;
+
+        // The image path stays relative, so that the editor can save the definition back
+        let image = relative_path(path, &def.image);
+        def.texture = Some(self.shared_texture(&image)?);
+        Ok(def)
+    }
+}
+
+impl Cache<ParticleKey, EmitterDef> for Resources {
+    fn get(&self, key: &ParticleKey) -> Option<Arc<EmitterDef>> {
+        self.particle_cache.get(key)
+    }
+
+    fn insert(&mut self, key: ParticleKey, value: EmitterDef) -> Option<Arc<EmitterDef>> {
+        self.particle_cache.insert(key, value)
+    }
+
+    fn remove(&mut self, key: &ParticleKey) -> Option<Arc<EmitterDef>> {
+        self.particle_cache.remove(key)
+    }
+
+    fn clear(&mut self) {
+        self.particle_cache.clear();
+    }
+
+    fn size(&self) -> usize {
+        self.particle_cache.size()
+    }
+    fn drop_unused(&mut self) {
+        self.particle_cache.drop_unused()
+    }
+}
+
+impl LoadCache<ShaderKey, ShaderSource> for Resources {}
+
+impl Loader<ShaderKey, ShaderSource> for Resources {
+    type Error = String;
+
+    fn load_resource(&self, key: &ShaderKey) -> Result<ShaderSource, Self::Error> {
+        let path = key.0;
+        let source = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        let source = GlslSource::preprocess(path, &source, |include| {
+            fs::read_to_string(include).map_err(|e| format!("{}: {}", include, e))
+        })?;
+        Ok(ShaderSource {
+            path: path.to_string(),
+            source,
+        })
+    }
+}
+
+impl Cache<ShaderKey, ShaderSource> for Resources {
+    fn get(&self, key: &ShaderKey) -> Option<Arc<ShaderSource>> {
+        self.shader_cache.get(key)
+    }
+
+    fn insert(&mut self, key: ShaderKey, value: ShaderSource) -> Option<Arc<ShaderSource>> {
+        self.shader_cache.insert(key, value)
+    }
+
+    fn remove(&mut self, key: &ShaderKey) -> Option<Arc<ShaderSource>> {
+        self.shader_cache.remove(key)
+    }
+
+    fn clear(&mut self) {
+        self.shader_cache.clear();
+    }
+
+    fn size(&self) -> usize {
+        self.shader_cache.size()
+    }
+    fn drop_unused(&mut self) {
+        self.shader_cache.drop_unused()
+    }
+}
+
+/// Path of `relative`, relative to the file `file`, without `.` and `..` so that it can be
+/// compared to the keys of the caches and to the paths of the file events.
+fn relative_path(file: &str, relative: &str) -> String {
+    let mut path = PathBuf::new();
+    for component in Path::new(file).with_file_name(relative).components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match path.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    path.pop();
+                }
+                Some(Component::RootDir) | Some(Component::Prefix(_)) => {}
+                _ => path.push(".."),
+            },
+            _ => path.push(component.as_os_str()),
+        }
+    }
+    path.to_string_lossy().into_owned()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_relative_path() {
+        assert_eq!(
+            relative_path("assets/maps/level.tmx", "../textures/tiles.png"),
+            "assets/textures/tiles.png"
+        );
+        assert_eq!(
+            relative_path("assets/maps/level.tmx", "./tiles.png"),
+            "assets/maps/tiles.png"
+        );
+        assert_eq!(relative_path("level.tmx", "../tiles.png"), "../tiles.png");
+        assert_eq!(relative_path("/level.tmx", "../tiles.png"), "/tiles.png");
+    }
+}
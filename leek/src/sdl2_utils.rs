@@ -5,10 +5,19 @@ use gl;
 use opengl;
 
 use engine::make_engine;
+use text_input::TextInput;
 use Engine;
 
 use failure::{err_msg, Error};
 
+use sdl2::get_error;
+use sdl2::render::WindowCanvas;
+use sdl2::sys;
+
+use std::ffi::CString;
+use std::os::raw::c_void;
+use std::path::PathBuf;
+
 #[inline]
 pub fn initialize_engine(
     window_title: &str,
@@ -72,6 +81,7 @@ pub fn initialize_engine(
     let event_pump = sdl_context.event_pump().map_err(err_msg)?;
 
     let texture_creator = renderer.texture_creator();
+    let text_input = TextInput::new(&video_subsystem);
 
     make_engine(
         sdl_context,
@@ -79,9 +89,76 @@ pub fn initialize_engine(
         texture_creator,
         ttf_context,
         event_pump,
+        text_input,
     )
 }
 
+/// Draw into `target` instead of the window, which is drawn again with a null `target`.
+pub fn set_render_target(
+    renderer: &mut WindowCanvas,
+    target: *mut sys::SDL_Texture,
+) -> Result<(), String> {
+    let ret = unsafe { sys::SDL_SetRenderTarget(renderer.raw(), target) };
+    if ret != 0 {
+        Err(format!("Error setting the render target: {}", get_error()))
+    } else {
+        Ok(())
+    }
+}
+
+/// `true` when the SDL library in use is at least `major.minor.patch`.
+pub fn sdl_version_at_least(major: u8, minor: u8, patch: u8) -> bool {
+    let version = sdl2::version::version();
+    (version.major, version.minor, version.patch) >= (major, minor, patch)
+}
+
+#[cfg(target_os = "windows")]
+const SDL_LIBRARY_NAMES: [&str; 1] = ["SDL2.dll"];
+#[cfg(target_os = "macos")]
+const SDL_LIBRARY_NAMES: [&str; 2] = ["libSDL2-2.0.0.dylib", "libSDL2.dylib"];
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+const SDL_LIBRARY_NAMES: [&str; 2] = ["libSDL2-2.0.so.0", "libSDL2.so"];
+
+/// Find a function added to SDL after the 2.0.6 headers of the sdl2 bindings.
+///
+/// Returns `None` when the SDL in use is older than `since` or does not export `name`. The
+/// function is looked up at runtime so the engine still links with older SDL versions.
+pub fn load_sdl_function(name: &str, since: (u8, u8, u8)) -> Option<*mut c_void> {
+    if !sdl_version_at_least(since.0, since.1, since.2) {
+        return None;
+    }
+    let c_name = CString::new(name).ok()?;
+    SDL_LIBRARY_NAMES
+        .iter()
+        .filter_map(|library| unsafe {
+            let c_library = CString::new(*library).unwrap();
+            // The library is already loaded, this only gives a handle to it
+            let handle = sys::SDL_LoadObject(c_library.as_ptr());
+            if handle.is_null() {
+                return None;
+            }
+            let function = sys::SDL_LoadFunction(handle, c_name.as_ptr());
+            if function.is_null() {
+                sys::SDL_UnloadObject(handle);
+                None
+            } else {
+                Some(function)
+            }
+        })
+        .next()
+}
+
+/// Returns the per user directory where settings can be stored (it is created if missing).
+pub fn settings_dir(organization: &str, application: &str) -> Option<PathBuf> {
+    match sdl2::filesystem::pref_path(organization, application) {
+        Ok(path) => Some(PathBuf::from(path)),
+        Err(error) => {
+            println!("Could not find the settings directory: {}", error);
+            None
+        }
+    }
+}
+
 pub fn log_system_info() -> String {
     format!(
         r#"System info:
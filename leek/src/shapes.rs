@@ -0,0 +1,471 @@
+use sdl2::gfx::primitives::DrawRenderer;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::WindowCanvas;
+
+use camera::CameraView;
+
+/// Points computed for each bezier curve.
+const BEZIER_STEPS: usize = 32;
+
+/// How a closed shape is drawn.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ShapeStyle {
+    Outline,
+    /// Anti-aliased outline.
+    SmoothOutline,
+    Filled,
+    /// Filled, with an anti-aliased outline.
+    SmoothFilled,
+}
+
+/// Primitive shapes drawn with SDL2_gfx, without textures.
+///
+/// On a canvas the coordinates are the logical ones, on a `CameraView` they are in the
+/// world and the shapes follow the camera position, zoom and rotation.
+/// Angles are in degrees, clockwise from the positive x axis.
+/// SDL2_gfx changes the draw color and blend mode of the renderer.
+pub trait DrawShapes {
+    fn draw_polygon(
+        &mut self,
+        points: &[(f32, f32)],
+        color: Color,
+        style: ShapeStyle,
+    ) -> Result<(), String>;
+
+    /// Open line through `points`.
+    fn draw_polyline(
+        &mut self,
+        points: &[(f32, f32)],
+        color: Color,
+        smooth: bool,
+    ) -> Result<(), String>;
+
+    fn draw_thick_line(
+        &mut self,
+        start: (f32, f32),
+        end: (f32, f32),
+        width: f32,
+        color: Color,
+    ) -> Result<(), String>;
+
+    fn draw_circle(
+        &mut self,
+        center: (f32, f32),
+        radius: f32,
+        color: Color,
+        style: ShapeStyle,
+    ) -> Result<(), String>;
+
+    fn draw_segment(
+        &mut self,
+        start: (f32, f32),
+        end: (f32, f32),
+        color: Color,
+        smooth: bool,
+    ) -> Result<(), String> {
+        self.draw_polyline(&[start, end], color, smooth)
+    }
+
+    fn draw_triangle(
+        &mut self,
+        points: [(f32, f32); 3],
+        color: Color,
+        style: ShapeStyle,
+    ) -> Result<(), String> {
+        self.draw_polygon(&points, color, style)
+    }
+
+    fn draw_rectangle(
+        &mut self,
+        rect: Rect,
+        color: Color,
+        style: ShapeStyle,
+    ) -> Result<(), String> {
+        self.draw_polygon(&rect_points(rect), color, style)
+    }
+
+    fn draw_rounded_rect(
+        &mut self,
+        rect: Rect,
+        radius: f32,
+        color: Color,
+        style: ShapeStyle,
+    ) -> Result<(), String> {
+        self.draw_polygon(&rounded_rect_points(rect, radius), color, style)
+    }
+
+    fn draw_ellipse(
+        &mut self,
+        center: (f32, f32),
+        radii: (f32, f32),
+        color: Color,
+        style: ShapeStyle,
+    ) -> Result<(), String> {
+        let mut points = arc_points(center, radii, 0.0, 360.0);
+        points.pop();
+        self.draw_polygon(&points, color, style)
+    }
+
+    fn draw_arc(
+        &mut self,
+        center: (f32, f32),
+        radius: f32,
+        start: f32,
+        end: f32,
+        color: Color,
+        smooth: bool,
+    ) -> Result<(), String> {
+        let points = arc_points(center, (radius, radius), start, end);
+        self.draw_polyline(&points, color, smooth)
+    }
+
+    /// Slice of a circle, between the angles `start` and `end`.
+    fn draw_pie(
+        &mut self,
+        center: (f32, f32),
+        radius: f32,
+        start: f32,
+        end: f32,
+        color: Color,
+        style: ShapeStyle,
+    ) -> Result<(), String> {
+        let mut points = vec![center];
+        points.extend(arc_points(center, (radius, radius), start, end));
+        self.draw_polygon(&points, color, style)
+    }
+
+    /// Bezier curve of any degree, from the first control point to the last one.
+    fn draw_bezier(
+        &mut self,
+        controls: &[(f32, f32)],
+        color: Color,
+        smooth: bool,
+    ) -> Result<(), String> {
+        self.draw_polyline(&bezier_points(controls, BEZIER_STEPS), color, smooth)
+    }
+}
+
+fn to_i16(value: f32) -> i16 {
+    value
+        .round()
+        .max(f32::from(i16::min_value()))
+        .min(f32::from(i16::max_value())) as i16
+}
+
+fn split_points(points: &[(f32, f32)]) -> (Vec<i16>, Vec<i16>) {
+    points.iter().map(|&(x, y)| (to_i16(x), to_i16(y))).unzip()
+}
+
+impl DrawShapes for WindowCanvas {
+    fn draw_polygon(
+        &mut self,
+        points: &[(f32, f32)],
+        color: Color,
+        style: ShapeStyle,
+    ) -> Result<(), String> {
+        if points.len() < 3 {
+            return self.draw_polyline(points, color, style != ShapeStyle::Outline);
+        }
+        let (xs, ys) = split_points(points);
+        match style {
+            ShapeStyle::Outline => self.polygon(&xs, &ys, color),
+            ShapeStyle::SmoothOutline => self.aa_polygon(&xs, &ys, color),
+            ShapeStyle::Filled => self.filled_polygon(&xs, &ys, color),
+            ShapeStyle::SmoothFilled => {
+                self.filled_polygon(&xs, &ys, color)?;
+                self.aa_polygon(&xs, &ys, color)
+            }
+        }
+    }
+
+    fn draw_polyline(
+        &mut self,
+        points: &[(f32, f32)],
+        color: Color,
+        smooth: bool,
+    ) -> Result<(), String> {
+        let (xs, ys) = split_points(points);
+        if xs.len() == 1 {
+            return self.pixel(xs[0], ys[0], color);
+        }
+        for i in 1..xs.len() {
+            if smooth {
+                self.aa_line(xs[i - 1], ys[i - 1], xs[i], ys[i], color)?;
+            } else {
+                self.line(xs[i - 1], ys[i - 1], xs[i], ys[i], color)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn draw_thick_line(
+        &mut self,
+        start: (f32, f32),
+        end: (f32, f32),
+        width: f32,
+        color: Color,
+    ) -> Result<(), String> {
+        let width = width.round().max(1.0).min(255.0) as u8;
+        self.thick_line(
+            to_i16(start.0),
+            to_i16(start.1),
+            to_i16(end.0),
+            to_i16(end.1),
+            width,
+            color,
+        )
+    }
+
+    fn draw_circle(
+        &mut self,
+        center: (f32, f32),
+        radius: f32,
+        color: Color,
+        style: ShapeStyle,
+    ) -> Result<(), String> {
+        let (x, y, radius) = (to_i16(center.0), to_i16(center.1), to_i16(radius));
+        match style {
+            ShapeStyle::Outline => self.circle(x, y, radius, color),
+            ShapeStyle::SmoothOutline => self.aa_circle(x, y, radius, color),
+            ShapeStyle::Filled => self.filled_circle(x, y, radius, color),
+            ShapeStyle::SmoothFilled => {
+                self.filled_circle(x, y, radius, color)?;
+                self.aa_circle(x, y, radius, color)
+            }
+        }
+    }
+
+    // SDL2_gfx draws axis aligned ellipses directly
+
+    fn draw_ellipse(
+        &mut self,
+        center: (f32, f32),
+        radii: (f32, f32),
+        color: Color,
+        style: ShapeStyle,
+    ) -> Result<(), String> {
+        let (x, y) = (to_i16(center.0), to_i16(center.1));
+        let (rx, ry) = (to_i16(radii.0), to_i16(radii.1));
+        match style {
+            ShapeStyle::Outline => self.ellipse(x, y, rx, ry, color),
+            ShapeStyle::SmoothOutline => self.aa_ellipse(x, y, rx, ry, color),
+            ShapeStyle::Filled => self.filled_ellipse(x, y, rx, ry, color),
+            ShapeStyle::SmoothFilled => {
+                self.filled_ellipse(x, y, rx, ry, color)?;
+                self.aa_ellipse(x, y, rx, ry, color)
+            }
+        }
+    }
+}
+
+fn to_screen(view: &CameraView, points: &[(f32, f32)]) -> Vec<(f32, f32)> {
+    points
+        .iter()
+        .map(|&point| view.camera().world_to_screen(point))
+        .collect()
+}
+
+fn is_visible(view: &CameraView, points: &[(f32, f32)], margin: f32) -> bool {
+    bounding_rect(points, margin).map_or(false, |rect| view.camera().is_visible(rect))
+}
+
+impl<'a> DrawShapes for CameraView<'a> {
+    fn draw_polygon(
+        &mut self,
+        points: &[(f32, f32)],
+        color: Color,
+        style: ShapeStyle,
+    ) -> Result<(), String> {
+        if !is_visible(self, points, 0.0) {
+            return Ok(());
+        }
+        let points = to_screen(self, points);
+        self.renderer.draw_polygon(&points, color, style)
+    }
+
+    fn draw_polyline(
+        &mut self,
+        points: &[(f32, f32)],
+        color: Color,
+        smooth: bool,
+    ) -> Result<(), String> {
+        if !is_visible(self, points, 0.0) {
+            return Ok(());
+        }
+        let points = to_screen(self, points);
+        self.renderer.draw_polyline(&points, color, smooth)
+    }
+
+    fn draw_thick_line(
+        &mut self,
+        start: (f32, f32),
+        end: (f32, f32),
+        width: f32,
+        color: Color,
+    ) -> Result<(), String> {
+        if !is_visible(self, &[start, end], width / 2.0) {
+            return Ok(());
+        }
+        let zoom = self.camera().zoom;
+        let points = to_screen(self, &[start, end]);
+        self.renderer
+            .draw_thick_line(points[0], points[1], width * zoom, color)
+    }
+
+    fn draw_circle(
+        &mut self,
+        center: (f32, f32),
+        radius: f32,
+        color: Color,
+        style: ShapeStyle,
+    ) -> Result<(), String> {
+        if !is_visible(self, &[center], radius) {
+            return Ok(());
+        }
+        let zoom = self.camera().zoom;
+        let center = self.camera().world_to_screen(center);
+        self.renderer
+            .draw_circle(center, radius * zoom, color, style)
+    }
+}
+
+/// Smallest rect containing `points`, grown by `margin` on each side.
+fn bounding_rect(points: &[(f32, f32)], margin: f32) -> Option<Rect> {
+    let first = match points.first() {
+        Some(&point) => point,
+        None => return None,
+    };
+    let (min, max) = points.iter().fold((first, first), |(min, max), &(x, y)| {
+        ((min.0.min(x), min.1.min(y)), (max.0.max(x), max.1.max(y)))
+    });
+    Some(Rect::new(
+        (min.0 - margin).floor() as i32,
+        (min.1 - margin).floor() as i32,
+        ((max.0 - min.0 + margin * 2.0).ceil() as u32).max(1),
+        ((max.1 - min.1 + margin * 2.0).ceil() as u32).max(1),
+    ))
+}
+
+/// Number of segments used to approximate a curve of the given radius.
+fn segment_count(radius: f32, angle: f32) -> usize {
+    let full = (radius.abs().sqrt() * 4.0).max(12.0).min(128.0);
+    ((full * angle.abs() / 360.0).ceil() as usize).max(1)
+}
+
+fn rect_points(rect: Rect) -> [(f32, f32); 4] {
+    let (left, top) = (rect.left() as f32, rect.top() as f32);
+    let (right, bottom) = (rect.right() as f32, rect.bottom() as f32);
+    [(left, top), (right, top), (right, bottom), (left, bottom)]
+}
+
+/// Points of an elliptic arc from `start` to `end`, both included.
+pub fn arc_points(center: (f32, f32), radii: (f32, f32), start: f32, end: f32) -> Vec<(f32, f32)> {
+    let segments = segment_count(radii.0.max(radii.1), end - start);
+    (0..segments + 1)
+        .map(|i| {
+            let angle = (start + (end - start) * i as f32 / segments as f32).to_radians();
+            (
+                center.0 + angle.cos() * radii.0,
+                center.1 + angle.sin() * radii.1,
+            )
+        })
+        .collect()
+}
+
+/// Outline of a rect with its corners rounded by `radius`.
+pub fn rounded_rect_points(rect: Rect, radius: f32) -> Vec<(f32, f32)> {
+    let (left, top) = (rect.left() as f32, rect.top() as f32);
+    let (right, bottom) = (rect.right() as f32, rect.bottom() as f32);
+    let radius = radius
+        .max(0.0)
+        .min((right - left) / 2.0)
+        .min((bottom - top) / 2.0);
+    if radius == 0.0 {
+        return rect_points(rect).to_vec();
+    }
+
+    let corners = [
+        ((right - radius, top + radius), 270.0),
+        ((right - radius, bottom - radius), 0.0),
+        ((left + radius, bottom - radius), 90.0),
+        ((left + radius, top + radius), 180.0),
+    ];
+    corners
+        .iter()
+        .flat_map(|&(center, start)| arc_points(center, (radius, radius), start, start + 90.0))
+        .collect()
+}
+
+/// Points of a bezier curve, computed with De Casteljau's algorithm.
+pub fn bezier_points(controls: &[(f32, f32)], steps: usize) -> Vec<(f32, f32)> {
+    if controls.len() < 2 {
+        return controls.to_vec();
+    }
+    let mut work = controls.to_vec();
+    (0..steps + 1)
+        .map(|step| {
+            let t = step as f32 / steps as f32;
+            work.copy_from_slice(controls);
+            for level in (1..controls.len()).rev() {
+                for i in 0..level {
+                    work[i] = (
+                        work[i].0 + (work[i + 1].0 - work[i].0) * t,
+                        work[i].1 + (work[i + 1].1 - work[i].1) * t,
+                    );
+                }
+            }
+            work[0]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn assert_near(a: (f32, f32), b: (f32, f32)) {
+        assert!(
+            (a.0 - b.0).abs() < 0.01 && (a.1 - b.1).abs() < 0.01,
+            "{:?} != {:?}",
+            a,
+            b
+        );
+    }
+
+    #[test]
+    fn test_arc_points() {
+        let points = arc_points((10.0, 10.0), (5.0, 2.0), 0.0, 90.0);
+        assert_near(points[0], (15.0, 10.0));
+        assert_near(*points.last().unwrap(), (10.0, 12.0));
+    }
+
+    #[test]
+    fn test_rounded_rect_points() {
+        let points = rounded_rect_points(Rect::new(0, 0, 20, 10), 20.0);
+        // The radius is limited by the height
+        assert_near(points[0], (15.0, 0.0));
+        assert!(points
+            .iter()
+            .all(|&(x, y)| x >= -0.01 && x <= 20.01 && y >= -0.01 && y <= 10.01));
+        assert_eq!(rounded_rect_points(Rect::new(0, 0, 20, 10), 0.0).len(), 4);
+    }
+
+    #[test]
+    fn test_bezier_points() {
+        let controls = [(0.0, 0.0), (10.0, 20.0), (20.0, 0.0)];
+        let points = bezier_points(&controls, 2);
+        assert_eq!(points.len(), 3);
+        assert_near(points[0], (0.0, 0.0));
+        assert_near(points[1], (10.0, 10.0));
+        assert_near(points[2], (20.0, 0.0));
+    }
+
+    #[test]
+    fn test_bounding_rect() {
+        let rect = bounding_rect(&[(1.0, 5.0), (-3.0, 2.0)], 1.0).unwrap();
+        assert_eq!(rect, Rect::new(-4, 1, 6, 5));
+        assert_eq!(bounding_rect(&[], 0.0), None);
+        assert_eq!(to_i16(1e9), i16::max_value());
+    }
+}
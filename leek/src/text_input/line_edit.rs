@@ -0,0 +1,299 @@
+use sdl2::keyboard::Keycode;
+
+use super::{Composition, TextInput};
+use EngineContext;
+
+/// Single line text buffer with cursor, selection and clipboard support.
+///
+/// Cursor and selection are byte offsets into `text()`, always on a char boundary.
+#[derive(Clone, Debug, Default)]
+pub struct LineEdit {
+    text: String,
+    cursor: usize,
+    anchor: Option<usize>,
+    max_chars: Option<usize>,
+    composition: Option<Composition>,
+}
+
+impl LineEdit {
+    pub fn new() -> LineEdit {
+        LineEdit::default()
+    }
+
+    pub fn with_text(text: &str) -> LineEdit {
+        LineEdit {
+            text: text.to_string(),
+            cursor: text.len(),
+            ..LineEdit::default()
+        }
+    }
+
+    /// Limit the number of chars that can be inserted.
+    pub fn with_max_chars(mut self, max_chars: usize) -> LineEdit {
+        self.max_chars = Some(max_chars);
+        self
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// In-progress IME composition, to be drawn at the cursor position.
+    pub fn composition(&self) -> Option<&Composition> {
+        self.composition.as_ref()
+    }
+
+    /// Returns the selected range as (start, end), if the selection is not empty.
+    pub fn selection(&self) -> Option<(usize, usize)> {
+        match self.anchor {
+            Some(anchor) if anchor != self.cursor => {
+                Some((anchor.min(self.cursor), anchor.max(self.cursor)))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn selected_text(&self) -> &str {
+        match self.selection() {
+            Some((start, end)) => &self.text[start..end],
+            None => "",
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.text.clear();
+        self.cursor = 0;
+        self.anchor = None;
+        self.composition = None;
+    }
+
+    /// Replace the selection (if any) with `text`, control chars are skipped.
+    pub fn insert(&mut self, text: &str) {
+        self.delete_selection();
+
+        let mut available = match self.max_chars {
+            Some(max_chars) => max_chars.saturating_sub(self.text.chars().count()),
+            None => usize::max_value(),
+        };
+
+        for character in text.chars().filter(|c| !c.is_control()) {
+            if available == 0 {
+                break;
+            }
+            self.text.insert(self.cursor, character);
+            self.cursor += character.len_utf8();
+            available -= 1;
+        }
+    }
+
+    pub fn backspace(&mut self) {
+        if !self.delete_selection() && self.cursor > 0 {
+            let previous = self.previous_boundary(self.cursor);
+            self.text.drain(previous..self.cursor);
+            self.cursor = previous;
+        }
+    }
+
+    pub fn delete(&mut self) {
+        if !self.delete_selection() && self.cursor < self.text.len() {
+            let next = self.next_boundary(self.cursor);
+            self.text.drain(self.cursor..next);
+        }
+    }
+
+    pub fn move_left(&mut self, select: bool) {
+        let position = match self.selection() {
+            Some((start, _)) if !select => start,
+            _ => self.previous_boundary(self.cursor),
+        };
+        self.move_to(position, select);
+    }
+
+    pub fn move_right(&mut self, select: bool) {
+        let position = match self.selection() {
+            Some((_, end)) if !select => end,
+            _ => self.next_boundary(self.cursor),
+        };
+        self.move_to(position, select);
+    }
+
+    pub fn move_home(&mut self, select: bool) {
+        self.move_to(0, select);
+    }
+
+    pub fn move_end(&mut self, select: bool) {
+        let end = self.text.len();
+        self.move_to(end, select);
+    }
+
+    pub fn select_all(&mut self) {
+        self.anchor = Some(0);
+        self.cursor = self.text.len();
+    }
+
+    pub fn copy(&self, text_input: &TextInput) {
+        if self.selection().is_some() {
+            text_input.set_clipboard_text(self.selected_text());
+        }
+    }
+
+    pub fn cut(&mut self, text_input: &TextInput) {
+        self.copy(text_input);
+        self.delete_selection();
+    }
+
+    pub fn paste(&mut self, text_input: &TextInput) {
+        if let Some(text) = text_input.clipboard_text() {
+            self.insert(&text);
+        }
+    }
+
+    /// Apply the text and editing keys received during this frame.
+    /// Returns `true` if Enter has been pressed.
+    pub fn update(&mut self, context: &EngineContext, text_input: &TextInput) -> bool {
+        let state = context.text_input();
+        self.insert(state.text());
+        self.composition = state.composition().cloned();
+
+        let typed =
+            |keycode: Keycode| context.is_keycode_pressed(keycode) || context.is_keycode_repeated(keycode);
+        let select = context.is_shift_down();
+
+        if typed(Keycode::Backspace) {
+            self.backspace();
+        }
+        if typed(Keycode::Delete) {
+            self.delete();
+        }
+        if typed(Keycode::Left) {
+            self.move_left(select);
+        }
+        if typed(Keycode::Right) {
+            self.move_right(select);
+        }
+        if typed(Keycode::Home) {
+            self.move_home(select);
+        }
+        if typed(Keycode::End) {
+            self.move_end(select);
+        }
+
+        if context.is_ctrl_down() {
+            if context.is_keycode_pressed(Keycode::A) {
+                self.select_all();
+            }
+            if context.is_keycode_pressed(Keycode::C) {
+                self.copy(text_input);
+            }
+            if context.is_keycode_pressed(Keycode::X) {
+                self.cut(text_input);
+            }
+            if typed(Keycode::V) {
+                self.paste(text_input);
+            }
+        }
+
+        context.is_keycode_pressed(Keycode::Return) || context.is_keycode_pressed(Keycode::KpEnter)
+    }
+
+    fn move_to(&mut self, position: usize, select: bool) {
+        if select {
+            if self.anchor.is_none() {
+                self.anchor = Some(self.cursor);
+            }
+        } else {
+            self.anchor = None;
+        }
+        self.cursor = position;
+    }
+
+    /// Returns `true` if there was a selection to delete.
+    fn delete_selection(&mut self) -> bool {
+        let selection = self.selection();
+        self.anchor = None;
+        if let Some((start, end)) = selection {
+            self.text.drain(start..end);
+            self.cursor = start;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn previous_boundary(&self, position: usize) -> usize {
+        self.text[..position]
+            .char_indices()
+            .next_back()
+            .map_or(0, |(index, _)| index)
+    }
+
+    fn next_boundary(&self, position: usize) -> usize {
+        self.text[position..]
+            .chars()
+            .next()
+            .map_or(position, |c| position + c.len_utf8())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_insert_and_backspace() {
+        let mut line = LineEdit::new();
+        line.insert("héllo");
+        assert_eq!(line.text(), "héllo");
+        assert_eq!(line.cursor(), 6);
+
+        line.move_left(false);
+        line.move_left(false);
+        line.move_left(false);
+        line.backspace();
+        assert_eq!(line.text(), "hllo");
+        assert_eq!(line.cursor(), 1);
+    }
+
+    #[test]
+    fn test_selection() {
+        let mut line = LineEdit::with_text("hello world");
+        line.move_home(false);
+        for _ in 0..5 {
+            line.move_right(true);
+        }
+        assert_eq!(line.selected_text(), "hello");
+
+        line.insert("goodbye");
+        assert_eq!(line.text(), "goodbye world");
+        assert_eq!(line.selection(), None);
+
+        line.select_all();
+        line.delete();
+        assert_eq!(line.text(), "");
+    }
+
+    #[test]
+    fn test_max_chars() {
+        let mut line = LineEdit::new().with_max_chars(3);
+        line.insert("ab\ncde");
+        assert_eq!(line.text(), "abc");
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut line = LineEdit::with_text("hello");
+        line.composition = Some(Composition {
+            text: "ka".to_string(),
+            cursor: 2,
+            selection_length: 0,
+        });
+        line.clear();
+        assert_eq!(line.text(), "");
+        assert_eq!(line.cursor(), 0);
+        assert!(line.composition().is_none());
+    }
+}
@@ -0,0 +1,122 @@
+use sdl2::clipboard::ClipboardUtil;
+use sdl2::event::Event;
+use sdl2::keyboard::TextInputUtil;
+use sdl2::rect::Rect;
+use sdl2::VideoSubsystem;
+
+mod line_edit;
+pub use self::line_edit::LineEdit;
+
+/// Text that the IME is still composing (not committed yet).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Composition {
+    pub text: String,
+    /// Cursor position inside the composition (in chars).
+    pub cursor: i32,
+    pub selection_length: i32,
+}
+
+/// Text input received during a single frame.
+#[derive(Clone, Debug, Default)]
+pub struct TextInputState {
+    text: String,
+    composition: Option<Composition>,
+}
+
+impl TextInputState {
+    /// Text committed during this frame.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// In-progress IME composition, if any.
+    pub fn composition(&self) -> Option<&Composition> {
+        self.composition.as_ref()
+    }
+}
+
+/// Engine service to control SDL text input and access the clipboard.
+pub struct TextInput {
+    util: TextInputUtil,
+    clipboard: ClipboardUtil,
+    text: String,
+    composition: Option<Composition>,
+}
+
+impl TextInput {
+    pub fn new(video_subsystem: &VideoSubsystem) -> TextInput {
+        TextInput {
+            util: video_subsystem.text_input(),
+            clipboard: video_subsystem.clipboard(),
+            text: String::new(),
+            composition: None,
+        }
+    }
+
+    /// Start receiving text input (this also shows the on-screen keyboard where available).
+    pub fn start(&self) {
+        self.util.start()
+    }
+
+    pub fn stop(&mut self) {
+        self.util.stop();
+        self.composition = None;
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.util.is_active()
+    }
+
+    /// Set the rectangle used to place the IME candidate list (usually the edited field).
+    pub fn set_candidate_rect(&self, rect: Rect) {
+        self.util.set_rect(rect)
+    }
+
+    pub fn clipboard_text(&self) -> Option<String> {
+        if self.clipboard.has_clipboard_text() {
+            self.clipboard.clipboard_text().ok()
+        } else {
+            None
+        }
+    }
+
+    pub fn set_clipboard_text(&self, text: &str) {
+        if let Err(error) = self.clipboard.set_clipboard_text(text) {
+            println!("Error setting clipboard text: {}", error);
+        }
+    }
+
+    pub fn process_event(&mut self, event: &Event) {
+        match *event {
+            Event::TextInput { ref text, .. } => {
+                self.text.push_str(text);
+                self.composition = None;
+            }
+            Event::TextEditing {
+                ref text,
+                start,
+                length,
+                ..
+            } => {
+                self.composition = if text.is_empty() {
+                    None
+                } else {
+                    Some(Composition {
+                        text: text.clone(),
+                        cursor: start,
+                        selection_length: length,
+                    })
+                };
+            }
+            _ => {}
+        }
+    }
+
+    /// Returns the text input of the current frame, the committed text is consumed.
+    pub fn take_state(&mut self) -> TextInputState {
+        TextInputState {
+            text: self.text.split_off(0),
+            composition: self.composition.clone(),
+        }
+    }
+}
@@ -0,0 +1,414 @@
+use serde_json::{self, Value};
+
+use std::collections::HashMap;
+
+use super::{
+    decode_base64_gids, Layer, ObjectLayer, ObjectShape, Properties, PropertyValue, TileData,
+    TileFrame, TileLayer, TiledMap, TiledObject, Tileset,
+};
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_opacity() -> f32 {
+    1.0
+}
+
+fn default_orientation() -> String {
+    "orthogonal".to_string()
+}
+
+#[derive(Deserialize)]
+struct JsonProperty {
+    name: String,
+    #[serde(rename = "type", default)]
+    kind: String,
+    value: Value,
+}
+
+fn parse_properties(properties: Vec<JsonProperty>) -> Result<Properties, String> {
+    properties
+        .into_iter()
+        .map(|property| {
+            let value = match property.value {
+                Value::String(value) => value,
+                value => value.to_string(),
+            };
+            Ok((property.name, PropertyValue::parse(&property.kind, &value)?))
+        })
+        .collect()
+}
+
+#[derive(Deserialize)]
+struct JsonFrame {
+    tileid: u32,
+    duration: u32,
+}
+
+#[derive(Deserialize)]
+struct JsonTile {
+    id: u32,
+    #[serde(default)]
+    properties: Vec<JsonProperty>,
+    #[serde(default)]
+    animation: Vec<JsonFrame>,
+}
+
+#[derive(Deserialize)]
+struct JsonTileset {
+    #[serde(default)]
+    firstgid: u32,
+    source: Option<String>,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    tilewidth: u32,
+    #[serde(default)]
+    tileheight: u32,
+    #[serde(default)]
+    spacing: u32,
+    #[serde(default)]
+    margin: u32,
+    #[serde(default)]
+    tilecount: u32,
+    #[serde(default)]
+    columns: u32,
+    image: Option<String>,
+    #[serde(default)]
+    properties: Vec<JsonProperty>,
+    #[serde(default)]
+    tiles: Vec<JsonTile>,
+}
+
+impl JsonTileset {
+    fn into_tileset(self, first_gid: u32) -> Result<Tileset, String> {
+        if let Some(source) = self.source {
+            return Ok(Tileset::external(first_gid, &source));
+        }
+        let image = self
+            .image
+            .ok_or("Tilesets made of a collection of images are not supported")?;
+
+        let mut tiles = HashMap::new();
+        for tile in self.tiles {
+            let animation = tile
+                .animation
+                .into_iter()
+                .map(|frame| TileFrame {
+                    tile_id: frame.tileid,
+                    duration: frame.duration,
+                })
+                .collect();
+            let data = TileData {
+                properties: parse_properties(tile.properties)?,
+                animation,
+            };
+            tiles.insert(tile.id, data);
+        }
+
+        Ok(Tileset {
+            name: self.name,
+            first_gid,
+            tile_size: (self.tilewidth, self.tileheight),
+            columns: self.columns,
+            tile_count: self.tilecount,
+            spacing: self.spacing,
+            margin: self.margin,
+            image,
+            source: None,
+            properties: parse_properties(self.properties)?,
+            tiles,
+            texture: None,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonData {
+    Gids(Vec<u32>),
+    Encoded(String),
+}
+
+#[derive(Deserialize)]
+struct JsonPoint {
+    x: f32,
+    y: f32,
+}
+
+#[derive(Deserialize)]
+struct JsonObject {
+    #[serde(default)]
+    id: u32,
+    #[serde(default)]
+    name: String,
+    #[serde(rename = "type", default)]
+    kind: String,
+    // Renamed from type in Tiled 1.9
+    #[serde(default)]
+    class: String,
+    #[serde(default)]
+    x: f32,
+    #[serde(default)]
+    y: f32,
+    #[serde(default)]
+    width: f32,
+    #[serde(default)]
+    height: f32,
+    #[serde(default)]
+    rotation: f32,
+    gid: Option<u32>,
+    #[serde(default = "default_true")]
+    visible: bool,
+    #[serde(default)]
+    ellipse: bool,
+    #[serde(default)]
+    point: bool,
+    polygon: Option<Vec<JsonPoint>>,
+    polyline: Option<Vec<JsonPoint>>,
+    #[serde(default)]
+    properties: Vec<JsonProperty>,
+}
+
+impl JsonObject {
+    fn into_object(self) -> Result<TiledObject, String> {
+        let points = |points: Vec<JsonPoint>| points.into_iter().map(|p| (p.x, p.y)).collect();
+        let shape = if self.ellipse {
+            ObjectShape::Ellipse
+        } else if self.point {
+            ObjectShape::Point
+        } else if let Some(polygon) = self.polygon {
+            ObjectShape::Polygon(points(polygon))
+        } else if let Some(polyline) = self.polyline {
+            ObjectShape::Polyline(points(polyline))
+        } else {
+            ObjectShape::Rectangle
+        };
+
+        Ok(TiledObject {
+            id: self.id,
+            name: self.name,
+            kind: if self.kind.is_empty() {
+                self.class
+            } else {
+                self.kind
+            },
+            position: (self.x, self.y),
+            size: (self.width, self.height),
+            rotation: self.rotation,
+            gid: self.gid,
+            visible: self.visible,
+            shape,
+            properties: parse_properties(self.properties)?,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct JsonLayer {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    width: u32,
+    #[serde(default)]
+    height: u32,
+    data: Option<JsonData>,
+    encoding: Option<String>,
+    compression: Option<String>,
+    chunks: Option<Value>,
+    #[serde(default = "default_true")]
+    visible: bool,
+    #[serde(default = "default_opacity")]
+    opacity: f32,
+    #[serde(default)]
+    offsetx: f32,
+    #[serde(default)]
+    offsety: f32,
+    #[serde(default)]
+    properties: Vec<JsonProperty>,
+    #[serde(default)]
+    objects: Vec<JsonObject>,
+    #[serde(default)]
+    layers: Vec<JsonLayer>,
+}
+
+fn layer_gids(layer: &mut JsonLayer) -> Result<Vec<u32>, String> {
+    if layer.chunks.is_some() {
+        return Err("Infinite maps are not supported".to_string());
+    }
+    match layer.compression {
+        Some(ref compression) if !compression.is_empty() => {
+            return Err(format!(
+                "Compressed layer data ({}) is not supported, use CSV or uncompressed Base64",
+                compression
+            ));
+        }
+        _ => {}
+    }
+
+    let gids = match layer.data.take() {
+        Some(JsonData::Gids(gids)) => gids,
+        Some(JsonData::Encoded(ref data)) => match layer.encoding.as_ref().map(String::as_str) {
+            Some("base64") => decode_base64_gids(data)?,
+            encoding => return Err(format!("Unknown layer encoding {:?}", encoding)),
+        },
+        None => return Err(format!("Missing data in layer {}", layer.name)),
+    };
+    if gids.len() != (layer.width * layer.height) as usize {
+        return Err(format!(
+            "Layer {} has {} tiles instead of {}",
+            layer.name,
+            gids.len(),
+            layer.width * layer.height
+        ));
+    }
+    Ok(gids)
+}
+
+fn parse_layers(
+    json_layers: Vec<JsonLayer>,
+    offset: (f32, f32),
+    visible: bool,
+    layers: &mut Vec<Layer>,
+) -> Result<(), String> {
+    for mut layer in json_layers {
+        let offset = (offset.0 + layer.offsetx, offset.1 + layer.offsety);
+        let visible = visible && layer.visible;
+
+        match layer.kind.as_str() {
+            "tilelayer" => {
+                let tiles = layer_gids(&mut layer)?;
+                layers.push(Layer::Tiles(TileLayer {
+                    name: layer.name,
+                    size: (layer.width, layer.height),
+                    tiles,
+                    visible,
+                    opacity: layer.opacity,
+                    offset,
+                    properties: parse_properties(layer.properties)?,
+                }));
+            }
+            "objectgroup" => {
+                layers.push(Layer::Objects(ObjectLayer {
+                    name: layer.name,
+                    objects: layer
+                        .objects
+                        .into_iter()
+                        .map(JsonObject::into_object)
+                        .collect::<Result<_, String>>()?,
+                    visible,
+                    offset,
+                    properties: parse_properties(layer.properties)?,
+                }));
+            }
+            "group" => parse_layers(layer.layers, offset, visible, layers)?,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+#[derive(Deserialize)]
+struct JsonMap {
+    width: u32,
+    height: u32,
+    tilewidth: u32,
+    tileheight: u32,
+    #[serde(default = "default_orientation")]
+    orientation: String,
+    #[serde(default)]
+    infinite: bool,
+    #[serde(default)]
+    tilesets: Vec<JsonTileset>,
+    #[serde(default)]
+    layers: Vec<JsonLayer>,
+    #[serde(default)]
+    properties: Vec<JsonProperty>,
+}
+
+/// Parse a map exported as JSON, external tilesets are left to be loaded (see `Tileset::source`).
+pub fn parse_json_map(json: &str) -> Result<TiledMap, String> {
+    let map: JsonMap = serde_json::from_str(json).map_err(|e| format!("{}", e))?;
+    if map.orientation != "orthogonal" {
+        return Err("Only orthogonal maps are supported".to_string());
+    }
+    if map.infinite {
+        return Err("Infinite maps are not supported".to_string());
+    }
+
+    let tilesets = map
+        .tilesets
+        .into_iter()
+        .map(|tileset| {
+            let first_gid = tileset.firstgid;
+            tileset.into_tileset(first_gid)
+        })
+        .collect::<Result<_, String>>()?;
+
+    let mut layers = Vec::new();
+    parse_layers(map.layers, (0.0, 0.0), true, &mut layers)?;
+
+    Ok(TiledMap {
+        size: (map.width, map.height),
+        tile_size: (map.tilewidth, map.tileheight),
+        tilesets,
+        layers,
+        properties: parse_properties(map.properties)?,
+    })
+}
+
+/// Parse an external tileset exported as JSON, `first_gid` comes from the map using it.
+pub fn parse_json_tileset(json: &str, first_gid: u32) -> Result<Tileset, String> {
+    let tileset: JsonTileset = serde_json::from_str(json).map_err(|e| format!("{}", e))?;
+    tileset.into_tileset(first_gid)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_json() {
+        let json = r#"{
+            "width": 2, "height": 2, "tilewidth": 8, "tileheight": 8,
+            "orientation": "orthogonal", "infinite": false,
+            "tilesets": [
+                {"firstgid": 1, "name": "tiles", "tilewidth": 8, "tileheight": 8,
+                 "columns": 2, "tilecount": 4, "image": "tiles.png",
+                 "tiles": [{"id": 1, "animation": [{"tileid": 1, "duration": 80}]}]},
+                {"firstgid": 5, "source": "enemies.json"}
+            ],
+            "layers": [
+                {"type": "tilelayer", "name": "ground", "width": 2, "height": 2,
+                 "encoding": "base64", "compression": "", "data": "AQAAAAIAAAADAAAABAAAAA=="},
+                {"type": "group", "name": "actors", "layers": [
+                    {"type": "objectgroup", "name": "triggers", "objects": [
+                        {"id": 4, "name": "door", "class": "warp", "x": 8, "y": 0,
+                         "width": 8, "height": 16, "ellipse": true,
+                         "properties": [{"name": "to", "type": "string", "value": "level2"},
+                                        {"name": "delay", "type": "float", "value": 0.5}]}
+                    ]}
+                ]}
+            ],
+            "properties": [{"name": "dark", "type": "bool", "value": true}]
+        }"#;
+        let map = parse_json_map(json).unwrap();
+        assert_eq!(map.properties["dark"], PropertyValue::Bool(true));
+        assert_eq!(map.tilesets[0].tiles[&1].animation[0].duration, 80);
+        assert_eq!(map.tilesets[1].source, Some("enemies.json".to_string()));
+        assert_eq!(map.tile_layer("ground").unwrap().tiles, vec![1, 2, 3, 4]);
+
+        let door = map
+            .object_layer("triggers")
+            .unwrap()
+            .object("door")
+            .unwrap();
+        assert_eq!(door.kind, "warp");
+        assert_eq!(door.shape, ObjectShape::Ellipse);
+        assert_eq!(door.properties["to"].as_str(), Some("level2"));
+        assert_eq!(door.properties["delay"].as_float(), Some(0.5));
+    }
+}
@@ -0,0 +1,625 @@
+use sdl2::rect::Rect;
+use sdl2::render::{Texture, WindowCanvas};
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use camera::CameraView;
+
+mod json;
+mod tmx;
+
+pub use self::json::{parse_json_map, parse_json_tileset};
+pub use self::tmx::{parse_tmx_map, parse_tsx_tileset};
+
+/// Flags stored in the high bits of a gid.
+pub const FLIPPED_HORIZONTALLY: u32 = 0x8000_0000;
+pub const FLIPPED_VERTICALLY: u32 = 0x4000_0000;
+pub const FLIPPED_DIAGONALLY: u32 = 0x2000_0000;
+const FLIP_FLAGS: u32 = FLIPPED_HORIZONTALLY | FLIPPED_VERTICALLY | FLIPPED_DIAGONALLY;
+
+/// A custom property set in Tiled.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PropertyValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+    /// Color as written by Tiled (#AARRGGBB).
+    Color(String),
+    /// File path, relative to the map.
+    File(String),
+}
+
+impl PropertyValue {
+    /// Parse a property from its Tiled type and its value written as text.
+    pub fn parse(kind: &str, value: &str) -> Result<PropertyValue, String> {
+        let error = || format!("Invalid {} property: {}", kind, value);
+        Ok(match kind {
+            "bool" => PropertyValue::Bool(value == "true"),
+            "int" | "object" => PropertyValue::Int(value.parse().map_err(|_| error())?),
+            "float" => PropertyValue::Float(value.parse().map_err(|_| error())?),
+            "color" => PropertyValue::Color(value.to_string()),
+            "file" => PropertyValue::File(value.to_string()),
+            _ => PropertyValue::String(value.to_string()),
+        })
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            PropertyValue::Bool(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_int(&self) -> Option<i64> {
+        match *self {
+            PropertyValue::Int(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Int properties are converted too.
+    pub fn as_float(&self) -> Option<f64> {
+        match *self {
+            PropertyValue::Float(value) => Some(value),
+            PropertyValue::Int(value) => Some(value as f64),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            PropertyValue::String(ref value)
+            | PropertyValue::Color(ref value)
+            | PropertyValue::File(ref value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+pub type Properties = HashMap<String, PropertyValue>;
+
+/// A frame of an animated tile, `tile_id` is local to the tileset.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TileFrame {
+    pub tile_id: u32,
+    /// Duration in milliseconds.
+    pub duration: u32,
+}
+
+/// Data attached to a single tile of a tileset.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TileData {
+    pub properties: Properties,
+    pub animation: Vec<TileFrame>,
+}
+
+#[derive(Clone)]
+pub struct Tileset {
+    pub name: String,
+    pub first_gid: u32,
+    pub tile_size: (u32, u32),
+    pub columns: u32,
+    pub tile_count: u32,
+    pub spacing: u32,
+    pub margin: u32,
+    /// Path of the image, relative to the map (or to `source`) until loaded by `Resources`.
+    pub image: String,
+    /// Path of the external tileset file, if the tileset is not embedded in the map.
+    pub source: Option<String>,
+    pub properties: Properties,
+    /// Tiles with properties or animations, by local id.
+    pub tiles: HashMap<u32, TileData>,
+    /// Set by `Resources` when loading the map.
+    pub texture: Option<Arc<Texture>>,
+}
+
+impl Tileset {
+    /// Tileset referencing an external file, to be replaced by its content.
+    pub fn external(first_gid: u32, source: &str) -> Tileset {
+        Tileset {
+            name: String::new(),
+            first_gid,
+            tile_size: (0, 0),
+            columns: 0,
+            tile_count: 0,
+            spacing: 0,
+            margin: 0,
+            image: String::new(),
+            source: Some(source.to_string()),
+            properties: Properties::new(),
+            tiles: HashMap::new(),
+            texture: None,
+        }
+    }
+
+    /// Area of the image used by the tile `local_id`.
+    pub fn source_rect(&self, local_id: u32) -> Rect {
+        let columns = self.columns.max(1);
+        let (width, height) = self.tile_size;
+        Rect::new(
+            (self.margin + (local_id % columns) * (width + self.spacing)) as i32,
+            (self.margin + (local_id / columns) * (height + self.spacing)) as i32,
+            width,
+            height,
+        )
+    }
+
+    /// The tile shown at `time` (in milliseconds) in place of `local_id`, for animated tiles.
+    pub fn animated_tile(&self, local_id: u32, time: u64) -> u32 {
+        let animation = match self.tiles.get(&local_id) {
+            Some(data) if !data.animation.is_empty() => &data.animation,
+            _ => return local_id,
+        };
+        let total: u64 = animation.iter().map(|f| u64::from(f.duration)).sum();
+        let mut time = time % total.max(1);
+        for frame in animation {
+            if time < u64::from(frame.duration) {
+                return frame.tile_id;
+            }
+            time -= u64::from(frame.duration);
+        }
+        animation[animation.len() - 1].tile_id
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TileLayer {
+    pub name: String,
+    /// Size in tiles.
+    pub size: (u32, u32),
+    /// Gids row by row, 0 for empty cells. The high bits contain the flip flags.
+    pub tiles: Vec<u32>,
+    pub visible: bool,
+    pub opacity: f32,
+    /// Offset in pixels.
+    pub offset: (f32, f32),
+    pub properties: Properties,
+}
+
+impl TileLayer {
+    /// Gid of the cell (flags included), 0 when empty or outside of the layer.
+    pub fn gid(&self, x: u32, y: u32) -> u32 {
+        if x >= self.size.0 || y >= self.size.1 {
+            return 0;
+        }
+        self.tiles
+            .get((y * self.size.0 + x) as usize)
+            .cloned()
+            .unwrap_or(0)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ObjectShape {
+    Rectangle,
+    Ellipse,
+    Point,
+    /// Points relative to the object position.
+    Polygon(Vec<(f32, f32)>),
+    Polyline(Vec<(f32, f32)>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TiledObject {
+    pub id: u32,
+    pub name: String,
+    /// The type (or class) of the object.
+    pub kind: String,
+    pub position: (f32, f32),
+    pub size: (f32, f32),
+    /// Clockwise rotation in degrees.
+    pub rotation: f32,
+    /// Set for tile objects.
+    pub gid: Option<u32>,
+    pub visible: bool,
+    pub shape: ObjectShape,
+    pub properties: Properties,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ObjectLayer {
+    pub name: String,
+    pub objects: Vec<TiledObject>,
+    pub visible: bool,
+    pub offset: (f32, f32),
+    pub properties: Properties,
+}
+
+impl ObjectLayer {
+    pub fn object(&self, name: &str) -> Option<&TiledObject> {
+        self.objects.iter().find(|object| object.name == name)
+    }
+}
+
+/// Layers of a map, the layers of groups are flattened.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Layer {
+    Tiles(TileLayer),
+    Objects(ObjectLayer),
+}
+
+impl Layer {
+    pub fn name(&self) -> &str {
+        match *self {
+            Layer::Tiles(ref layer) => &layer.name,
+            Layer::Objects(ref layer) => &layer.name,
+        }
+    }
+}
+
+/// An orthogonal map made with Tiled.
+#[derive(Clone)]
+pub struct TiledMap {
+    /// Size in tiles.
+    pub size: (u32, u32),
+    pub tile_size: (u32, u32),
+    pub tilesets: Vec<Tileset>,
+    pub layers: Vec<Layer>,
+    pub properties: Properties,
+}
+
+impl TiledMap {
+    /// Size in pixels.
+    pub fn pixel_size(&self) -> (u32, u32) {
+        (
+            self.size.0 * self.tile_size.0,
+            self.size.1 * self.tile_size.1,
+        )
+    }
+
+    pub fn layer(&self, name: &str) -> Option<&Layer> {
+        self.layers.iter().find(|layer| layer.name() == name)
+    }
+
+    pub fn tile_layer(&self, name: &str) -> Option<&TileLayer> {
+        match self.layer(name) {
+            Some(&Layer::Tiles(ref layer)) => Some(layer),
+            _ => None,
+        }
+    }
+
+    pub fn object_layer(&self, name: &str) -> Option<&ObjectLayer> {
+        match self.layer(name) {
+            Some(&Layer::Objects(ref layer)) => Some(layer),
+            _ => None,
+        }
+    }
+
+    /// Index of the tileset containing `gid` and the id of the tile in it.
+    pub fn tileset_for_gid(&self, gid: u32) -> Option<(usize, u32)> {
+        let gid = gid & !FLIP_FLAGS;
+        if gid == 0 {
+            return None;
+        }
+        self.tilesets
+            .iter()
+            .enumerate()
+            .filter(|&(_, tileset)| tileset.first_gid <= gid)
+            .max_by_key(|&(_, tileset)| tileset.first_gid)
+            .map(|(index, tileset)| (index, gid - tileset.first_gid))
+    }
+
+    /// Properties of the tile `gid`, if it has any.
+    pub fn tile_data(&self, gid: u32) -> Option<&TileData> {
+        self.tileset_for_gid(gid)
+            .and_then(|(index, id)| self.tilesets[index].tiles.get(&id))
+    }
+
+    pub fn vram_size(&self) -> usize {
+        self.tilesets
+            .iter()
+            .filter_map(|tileset| tileset.texture.as_ref())
+            .map(|texture| {
+                let tex_query = texture.query();
+
+                let pixels = tex_query.width * tex_query.height;
+                tex_query.format.byte_size_of_pixels(pixels as usize)
+            })
+            .sum()
+    }
+
+    /// Call `draw_tile` for every non empty tile of `layer` inside `view` (in pixels).
+    fn visit_tiles<F>(
+        &self,
+        layer: &TileLayer,
+        view: Rect,
+        time: u64,
+        mut draw_tile: F,
+    ) -> Result<(), String>
+    where
+        F: FnMut(&Texture, Rect, Rect, &TileFlip) -> Result<(), String>,
+    {
+        let (columns, rows) = visible_range(layer, self.tile_size, view);
+        let (tile_width, tile_height) = self.tile_size;
+
+        for y in rows.0..rows.1 {
+            for x in columns.0..columns.1 {
+                let gid = layer.gid(x, y);
+                let (index, local_id) = match self.tileset_for_gid(gid) {
+                    Some(tile) => tile,
+                    None => continue,
+                };
+                let tileset = &self.tilesets[index];
+                let texture = match tileset.texture {
+                    Some(ref texture) => texture,
+                    None => continue,
+                };
+
+                let source = tileset.source_rect(tileset.animated_tile(local_id, time));
+                // Tiles bigger than the grid are aligned on the bottom left corner of the cell
+                let dest = Rect::new(
+                    (x * tile_width) as i32 + layer.offset.0 as i32,
+                    ((y + 1) * tile_height) as i32 - tileset.tile_size.1 as i32
+                        + layer.offset.1 as i32,
+                    tileset.tile_size.0,
+                    tileset.tile_size.1,
+                );
+                draw_tile(texture, source, dest, &TileFlip::from_gid(gid))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Draw the part of a tile layer inside `view`, the top left corner of `view` is drawn at (0, 0).
+    /// `time` (in milliseconds) selects the frames of the animated tiles.
+    pub fn draw_layer(
+        &self,
+        renderer: &mut WindowCanvas,
+        layer: &TileLayer,
+        view: Rect,
+        time: u64,
+    ) -> Result<(), String> {
+        self.visit_tiles(layer, view, time, |texture, source, dest, flip| {
+            let dest = Rect::new(
+                dest.x() - view.x(),
+                dest.y() - view.y(),
+                dest.width(),
+                dest.height(),
+            );
+            renderer.copy_ex(
+                texture,
+                source,
+                dest,
+                flip.angle,
+                None,
+                flip.horizontal,
+                flip.vertical,
+            )
+        })
+    }
+
+    /// Draw every visible tile layer, see `draw_layer`.
+    pub fn draw(&self, renderer: &mut WindowCanvas, view: Rect, time: u64) -> Result<(), String> {
+        for layer in &self.layers {
+            if let Layer::Tiles(ref layer) = *layer {
+                if layer.visible {
+                    self.draw_layer(renderer, layer, view, time)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Draw every visible tile layer through a camera, only the tiles it can see are drawn.
+    pub fn draw_with_camera(&self, view: &mut CameraView, time: u64) -> Result<(), String> {
+        let visible = view.camera().visible_bounds();
+        for layer in &self.layers {
+            if let Layer::Tiles(ref layer) = *layer {
+                if layer.visible {
+                    self.visit_tiles(layer, visible, time, |texture, source, dest, flip| {
+                        view.copy_ex(
+                            texture,
+                            source,
+                            dest,
+                            flip.angle,
+                            None,
+                            flip.horizontal,
+                            flip.vertical,
+                        )
+                    })?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// How to draw a tile with `copy_ex` to apply the flags of its gid.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TileFlip {
+    pub angle: f64,
+    pub horizontal: bool,
+    pub vertical: bool,
+}
+
+impl TileFlip {
+    pub fn from_gid(gid: u32) -> TileFlip {
+        let horizontal = gid & FLIPPED_HORIZONTALLY != 0;
+        let vertical = gid & FLIPPED_VERTICALLY != 0;
+        if gid & FLIPPED_DIAGONALLY != 0 {
+            // The diagonal flip is a rotation of 90° plus a vertical flip, done before the others
+            TileFlip {
+                angle: 90.0,
+                horizontal: vertical,
+                vertical: !horizontal,
+            }
+        } else {
+            TileFlip {
+                angle: 0.0,
+                horizontal,
+                vertical,
+            }
+        }
+    }
+}
+
+/// Columns and rows of `layer` (start inclusive, end exclusive) that can be seen in `view`.
+pub fn visible_range(
+    layer: &TileLayer,
+    tile_size: (u32, u32),
+    view: Rect,
+) -> ((u32, u32), (u32, u32)) {
+    let range = |start: f32, end: f32, tile: u32, count: u32| {
+        let tile = tile.max(1) as f32;
+        // One more tile on each side, for the tiles bigger than the grid
+        let first = ((start / tile).floor() - 1.0).max(0.0) as u32;
+        let last = ((end / tile).ceil() + 1.0).max(0.0) as u32;
+        (first.min(count), last.min(count))
+    };
+    (
+        range(
+            view.left() as f32 - layer.offset.0,
+            view.right() as f32 - layer.offset.0,
+            tile_size.0,
+            layer.size.0,
+        ),
+        range(
+            view.top() as f32 - layer.offset.1,
+            view.bottom() as f32 - layer.offset.1,
+            tile_size.1,
+            layer.size.1,
+        ),
+    )
+}
+
+/// Decode the Base64 layer data of Tiled (uncompressed) into gids.
+pub fn decode_base64_gids(data: &str) -> Result<Vec<u32>, String> {
+    fn value(c: u8) -> Result<u32, String> {
+        Ok(match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return Err(format!("Invalid base64 character {}", c as char)),
+        } as u32)
+    }
+
+    let chars: Vec<u8> = data
+        .bytes()
+        .filter(|c| !c.is_ascii_whitespace() && *c != b'=')
+        .collect();
+    let mut bytes = Vec::with_capacity(chars.len() * 3 / 4);
+    for chunk in chars.chunks(4) {
+        let mut buffer = 0;
+        for (i, c) in chunk.iter().enumerate() {
+            buffer |= value(*c)? << (18 - 6 * i);
+        }
+        for i in 0..chunk.len().saturating_sub(1) {
+            bytes.push((buffer >> (16 - 8 * i)) as u8);
+        }
+    }
+
+    if bytes.len() % 4 != 0 {
+        return Err("The layer data is not made of 32 bits gids".to_string());
+    }
+    Ok(bytes
+        .chunks(4)
+        .map(|b| {
+            u32::from(b[0]) | u32::from(b[1]) << 8 | u32::from(b[2]) << 16 | u32::from(b[3]) << 24
+        })
+        .collect())
+}
+
+/// Parse the CSV layer data of Tiled into gids.
+pub fn decode_csv_gids(data: &str) -> Result<Vec<u32>, String> {
+    data.split(',')
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(|value| {
+            value
+                .parse()
+                .map_err(|_| format!("Invalid gid in layer data: {}", value))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn tileset(first_gid: u32) -> Tileset {
+        Tileset {
+            name: "terrain".to_string(),
+            first_gid,
+            tile_size: (16, 16),
+            columns: 4,
+            tile_count: 16,
+            spacing: 2,
+            margin: 1,
+            image: "terrain.png".to_string(),
+            source: None,
+            properties: Properties::new(),
+            tiles: HashMap::new(),
+            texture: None,
+        }
+    }
+
+    #[test]
+    fn test_gids() {
+        let mut map = TiledMap {
+            size: (4, 4),
+            tile_size: (16, 16),
+            tilesets: vec![tileset(1), tileset(17)],
+            layers: Vec::new(),
+            properties: Properties::new(),
+        };
+        assert_eq!(map.tileset_for_gid(0), None);
+        assert_eq!(map.tileset_for_gid(16), Some((0, 15)));
+        assert_eq!(map.tileset_for_gid(18 | FLIPPED_VERTICALLY), Some((1, 1)));
+        assert_eq!(map.tilesets[0].source_rect(5), Rect::new(19, 19, 16, 16));
+
+        map.tilesets[0].tiles.insert(
+            2,
+            TileData {
+                properties: Properties::new(),
+                animation: vec![
+                    TileFrame {
+                        tile_id: 2,
+                        duration: 100,
+                    },
+                    TileFrame {
+                        tile_id: 3,
+                        duration: 50,
+                    },
+                ],
+            },
+        );
+        assert_eq!(map.tilesets[0].animated_tile(2, 120), 3);
+        assert_eq!(map.tilesets[0].animated_tile(2, 160), 2);
+        assert_eq!(map.tilesets[0].animated_tile(1, 120), 1);
+    }
+
+    #[test]
+    fn test_visible_range() {
+        let layer = TileLayer {
+            name: "ground".to_string(),
+            size: (100, 50),
+            tiles: Vec::new(),
+            visible: true,
+            opacity: 1.0,
+            offset: (0.0, 0.0),
+            properties: Properties::new(),
+        };
+        let range = visible_range(&layer, (16, 16), Rect::new(40, -20, 320, 200));
+        assert_eq!(range, ((1, 24), (0, 13)));
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(
+            decode_csv_gids("1,2,\n0,2147483651").unwrap(),
+            vec![1, 2, 0, 2147483651]
+        );
+        // 1, 2, 3 as little endian u32
+        let gids = decode_base64_gids("AQAAAAIAAAADAAAA").unwrap();
+        assert_eq!(gids, vec![1, 2, 3]);
+        assert!(decode_base64_gids("AQAA").is_err());
+
+        let flip = TileFlip::from_gid(5 | FLIPPED_DIAGONALLY | FLIPPED_HORIZONTALLY);
+        assert_eq!(flip.angle, 90.0);
+        assert!(!flip.horizontal && !flip.vertical);
+    }
+}
@@ -0,0 +1,407 @@
+use xml::reader::{EventReader, XmlEvent};
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use super::{
+    decode_base64_gids, decode_csv_gids, Layer, ObjectLayer, ObjectShape, Properties,
+    PropertyValue, TileData, TileFrame, TileLayer, TiledMap, TiledObject, Tileset,
+};
+
+/// Just enough of a DOM to read Tiled files.
+struct Element {
+    name: String,
+    attributes: HashMap<String, String>,
+    children: Vec<Element>,
+    text: String,
+}
+
+impl Element {
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.attributes.get(name).map(String::as_str)
+    }
+
+    fn attr_or<T: FromStr>(&self, name: &str, default: T) -> Result<T, String> {
+        if self.attr(name).is_some() {
+            self.required(name)
+        } else {
+            Ok(default)
+        }
+    }
+
+    fn required<T: FromStr>(&self, name: &str) -> Result<T, String> {
+        match self.attr(name) {
+            Some(value) => value.parse().map_err(|_| {
+                format!(
+                    "Invalid attribute {}=\"{}\" in <{}>",
+                    name, value, self.name
+                )
+            }),
+            None => Err(format!("Missing attribute {} in <{}>", name, self.name)),
+        }
+    }
+
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> + 'a {
+        self.children.iter().filter(move |child| child.name == name)
+    }
+}
+
+fn parse_document(xml: &str) -> Result<Element, String> {
+    let mut stack: Vec<Element> = Vec::new();
+    for event in EventReader::new(xml.as_bytes()) {
+        match event.map_err(|e| format!("{}", e))? {
+            XmlEvent::StartElement {
+                name, attributes, ..
+            } => stack.push(Element {
+                name: name.local_name,
+                attributes: attributes
+                    .into_iter()
+                    .map(|attribute| (attribute.name.local_name, attribute.value))
+                    .collect(),
+                children: Vec::new(),
+                text: String::new(),
+            }),
+            XmlEvent::EndElement { .. } => {
+                // The reader fails on unbalanced tags
+                let element = stack.pop().expect("unbalanced XML");
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(element),
+                    None => return Ok(element),
+                }
+            }
+            XmlEvent::Characters(text) | XmlEvent::CData(text) => {
+                if let Some(element) = stack.last_mut() {
+                    element.text.push_str(&text);
+                }
+            }
+            _ => {}
+        }
+    }
+    Err("Empty XML document".to_string())
+}
+
+fn parse_properties(element: &Element) -> Result<Properties, String> {
+    let mut properties = Properties::new();
+    if let Some(list) = element.child("properties") {
+        for property in list.children("property") {
+            // Multiline strings are stored as text instead of the value attribute
+            let value = property.attr("value").unwrap_or(&property.text);
+            let kind = property.attr("type").unwrap_or("string");
+            properties.insert(
+                property.required("name")?,
+                PropertyValue::parse(kind, value)?,
+            );
+        }
+    }
+    Ok(properties)
+}
+
+fn parse_tileset(element: &Element, first_gid: u32) -> Result<Tileset, String> {
+    let image = element
+        .child("image")
+        .ok_or("Tilesets made of a collection of images are not supported")?;
+
+    let mut tiles = HashMap::new();
+    for tile in element.children("tile") {
+        let animation = match tile.child("animation") {
+            Some(animation) => animation
+                .children("frame")
+                .map(|frame| {
+                    Ok(TileFrame {
+                        tile_id: frame.required("tileid")?,
+                        duration: frame.required("duration")?,
+                    })
+                })
+                .collect::<Result<_, String>>()?,
+            None => Vec::new(),
+        };
+        let data = TileData {
+            properties: parse_properties(tile)?,
+            animation,
+        };
+        tiles.insert(tile.required("id")?, data);
+    }
+
+    let tile_size: (u32, u32) = (
+        element.required("tilewidth")?,
+        element.required("tileheight")?,
+    );
+    let spacing = element.attr_or("spacing", 0)?;
+    let margin = element.attr_or("margin", 0)?;
+    // Old versions of Tiled do not write the columns
+    let image_width: u32 = image.attr_or("width", 0)?;
+    let columns =
+        (image_width.saturating_sub(2 * margin) + spacing) / (tile_size.0 + spacing).max(1);
+
+    Ok(Tileset {
+        name: element.attr_or("name", String::new())?,
+        first_gid,
+        tile_size,
+        columns: element.attr_or("columns", columns)?,
+        tile_count: element.attr_or("tilecount", 0)?,
+        spacing,
+        margin,
+        image: image.required("source")?,
+        source: None,
+        properties: parse_properties(element)?,
+        tiles,
+        texture: None,
+    })
+}
+
+/// Parse an external tileset (TSX), `first_gid` comes from the map using it.
+pub fn parse_tsx_tileset(xml: &str, first_gid: u32) -> Result<Tileset, String> {
+    parse_tileset(&parse_document(xml)?, first_gid)
+}
+
+fn parse_layer_data(layer: &Element, size: (u32, u32)) -> Result<Vec<u32>, String> {
+    let data = layer.child("data").ok_or("Missing layer data")?;
+    if data.child("chunk").is_some() {
+        return Err("Infinite maps are not supported".to_string());
+    }
+    if let Some(compression) = data.attr("compression") {
+        return Err(format!(
+            "Compressed layer data ({}) is not supported, use CSV or uncompressed Base64",
+            compression
+        ));
+    }
+
+    let gids = match data.attr("encoding") {
+        Some("csv") => decode_csv_gids(&data.text)?,
+        Some("base64") => decode_base64_gids(&data.text)?,
+        Some(encoding) => return Err(format!("Unknown layer encoding {}", encoding)),
+        None => data
+            .children("tile")
+            .map(|tile| tile.attr_or("gid", 0))
+            .collect::<Result<_, String>>()?,
+    };
+    if gids.len() != (size.0 * size.1) as usize {
+        return Err(format!(
+            "Layer {} has {} tiles instead of {}",
+            layer.attr("name").unwrap_or(""),
+            gids.len(),
+            size.0 * size.1
+        ));
+    }
+    Ok(gids)
+}
+
+fn parse_points(points: &str) -> Result<Vec<(f32, f32)>, String> {
+    points
+        .split_whitespace()
+        .map(|point| {
+            let mut coordinates = point.split(',').map(f32::from_str);
+            match (coordinates.next(), coordinates.next()) {
+                (Some(Ok(x)), Some(Ok(y))) => Ok((x, y)),
+                _ => Err(format!("Invalid point {}", point)),
+            }
+        })
+        .collect()
+}
+
+fn parse_object(element: &Element) -> Result<TiledObject, String> {
+    let shape = if element.child("ellipse").is_some() {
+        ObjectShape::Ellipse
+    } else if element.child("point").is_some() {
+        ObjectShape::Point
+    } else if let Some(polygon) = element.child("polygon") {
+        ObjectShape::Polygon(parse_points(polygon.attr("points").unwrap_or(""))?)
+    } else if let Some(polyline) = element.child("polyline") {
+        ObjectShape::Polyline(parse_points(polyline.attr("points").unwrap_or(""))?)
+    } else {
+        ObjectShape::Rectangle
+    };
+
+    Ok(TiledObject {
+        id: element.attr_or("id", 0)?,
+        name: element.attr_or("name", String::new())?,
+        // Renamed to class in Tiled 1.9
+        kind: element
+            .attr("type")
+            .or_else(|| element.attr("class"))
+            .unwrap_or("")
+            .to_string(),
+        position: (element.attr_or("x", 0.0)?, element.attr_or("y", 0.0)?),
+        size: (
+            element.attr_or("width", 0.0)?,
+            element.attr_or("height", 0.0)?,
+        ),
+        rotation: element.attr_or("rotation", 0.0)?,
+        gid: match element.attr("gid") {
+            Some(_) => Some(element.required("gid")?),
+            None => None,
+        },
+        visible: element.attr_or("visible", 1)? == 1,
+        shape,
+        properties: parse_properties(element)?,
+    })
+}
+
+fn parse_layers(
+    parent: &Element,
+    offset: (f32, f32),
+    visible: bool,
+    layers: &mut Vec<Layer>,
+) -> Result<(), String> {
+    for child in &parent.children {
+        let offset = (
+            offset.0 + child.attr_or("offsetx", 0.0)?,
+            offset.1 + child.attr_or("offsety", 0.0)?,
+        );
+        let visible = visible && child.attr_or("visible", 1)? == 1;
+        let name = child.attr_or("name", String::new())?;
+
+        match child.name.as_str() {
+            "layer" => {
+                let size = (child.required("width")?, child.required("height")?);
+                layers.push(Layer::Tiles(TileLayer {
+                    name,
+                    size,
+                    tiles: parse_layer_data(child, size)?,
+                    visible,
+                    opacity: child.attr_or("opacity", 1.0)?,
+                    offset,
+                    properties: parse_properties(child)?,
+                }));
+            }
+            "objectgroup" => {
+                layers.push(Layer::Objects(ObjectLayer {
+                    name,
+                    objects: child
+                        .children("object")
+                        .map(parse_object)
+                        .collect::<Result<_, String>>()?,
+                    visible,
+                    offset,
+                    properties: parse_properties(child)?,
+                }));
+            }
+            "group" => parse_layers(child, offset, visible, layers)?,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Parse a TMX map, external tilesets are left to be loaded (see `Tileset::source`).
+pub fn parse_tmx_map(xml: &str) -> Result<TiledMap, String> {
+    let root = parse_document(xml)?;
+    if root.name != "map" {
+        return Err(format!("Expected <map>, found <{}>", root.name));
+    }
+    if root.attr("orientation").unwrap_or("orthogonal") != "orthogonal" {
+        return Err("Only orthogonal maps are supported".to_string());
+    }
+    if root.attr_or("infinite", 0)? == 1 {
+        return Err("Infinite maps are not supported".to_string());
+    }
+
+    let mut tilesets = Vec::new();
+    for tileset in root.children("tileset") {
+        let first_gid = tileset.required("firstgid")?;
+        tilesets.push(match tileset.attr("source") {
+            Some(source) => Tileset::external(first_gid, source),
+            None => parse_tileset(tileset, first_gid)?,
+        });
+    }
+
+    let mut layers = Vec::new();
+    parse_layers(&root, (0.0, 0.0), true, &mut layers)?;
+
+    Ok(TiledMap {
+        size: (root.required("width")?, root.required("height")?),
+        tile_size: (root.required("tilewidth")?, root.required("tileheight")?),
+        tilesets,
+        layers,
+        properties: parse_properties(&root)?,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const MAP: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.2" orientation="orthogonal" width="3" height="2" tilewidth="16" tileheight="16" infinite="0">
+ <properties>
+  <property name="music" type="file" value="level1.ogg"/>
+ </properties>
+ <tileset firstgid="1" name="terrain" tilewidth="16" tileheight="16" tilecount="8" columns="4">
+  <image source="terrain.png" width="64" height="32"/>
+  <tile id="2">
+   <properties><property name="solid" type="bool" value="true"/></properties>
+   <animation><frame tileid="2" duration="100"/><frame tileid="3" duration="100"/></animation>
+  </tile>
+ </tileset>
+ <tileset firstgid="9" source="props.tsx"/>
+ <layer id="1" name="ground" width="3" height="2">
+  <data encoding="csv">
+1,2,3,
+0,9,2147483649
+</data>
+ </layer>
+ <group name="entities" offsetx="4" visible="0">
+  <objectgroup name="spawns">
+   <object id="1" name="player" type="spawn" x="8" y="24">
+    <properties><property name="health" type="int" value="3"/></properties>
+    <point/>
+   </object>
+   <object id="2" x="0" y="0" width="32" height="16">
+    <polygon points="0,0 32,0 16,16"/>
+   </object>
+  </objectgroup>
+ </group>
+</map>"#;
+
+    #[test]
+    fn test_parse_tmx() {
+        let map = parse_tmx_map(MAP).unwrap();
+        assert_eq!(map.pixel_size(), (48, 32));
+        assert_eq!(
+            map.properties["music"],
+            PropertyValue::File("level1.ogg".to_string())
+        );
+
+        assert_eq!(map.tilesets[0].image, "terrain.png");
+        assert_eq!(map.tilesets[0].tiles[&2].animation.len(), 2);
+        assert_eq!(
+            map.tile_data(3).unwrap().properties["solid"].as_bool(),
+            Some(true)
+        );
+        assert_eq!(map.tilesets[1].source, Some("props.tsx".to_string()));
+
+        let ground = map.tile_layer("ground").unwrap();
+        assert_eq!(ground.gid(2, 1), 2_147_483_649);
+        assert_eq!(map.tileset_for_gid(ground.gid(1, 1)), Some((1, 0)));
+
+        let spawns = map.object_layer("spawns").unwrap();
+        assert!(!spawns.visible);
+        assert_eq!(spawns.offset, (4.0, 0.0));
+        let player = spawns.object("player").unwrap();
+        assert_eq!(player.kind, "spawn");
+        assert_eq!(player.shape, ObjectShape::Point);
+        assert_eq!(player.properties["health"].as_int(), Some(3));
+        assert_eq!(
+            spawns.objects[1].shape,
+            ObjectShape::Polygon(vec![(0.0, 0.0), (32.0, 0.0), (16.0, 16.0)])
+        );
+    }
+
+    #[test]
+    fn test_parse_tsx() {
+        let tileset = parse_tsx_tileset(
+            r#"<tileset name="props" tilewidth="32" tileheight="32" spacing="2" margin="1">
+                <image source="images/props.png" width="104" height="70"/>
+            </tileset>"#,
+            9,
+        )
+        .unwrap();
+        assert_eq!(tileset.first_gid, 9);
+        assert_eq!(tileset.columns, 3);
+        assert_eq!(tileset.image, "images/props.png");
+    }
+}